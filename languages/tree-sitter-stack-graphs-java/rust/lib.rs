@@ -14,6 +14,10 @@ pub const STACK_GRAPHS_BUILTINS_CONFIG: &str = include_str!("../src/builtins.cfg
 pub const STACK_GRAPHS_BUILTINS_PATH: &str = "src/builtins.java";
 /// The stack graphs builtins source for this language.
 pub const STACK_GRAPHS_BUILTINS_SOURCE: &str = include_str!("../src/builtins.java");
+/// The precompiled stack graphs builtins for this language, see [`tree_sitter_stack_graphs::builtins`][].
+/// Empty if the builtins could not be precompiled at build time.
+pub const STACK_GRAPHS_BUILTINS_PRECOMPILED: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/builtins.bin"));
 
 /// The name of the file path global variable
 pub const FILE_PATH_VAR: &str = "FILE_PATH";
@@ -27,19 +31,28 @@ pub fn language_configuration(cancellation_flag: &dyn CancellationFlag) -> Langu
 pub fn try_language_configuration(
     cancellation_flag: &dyn CancellationFlag,
 ) -> Result<LanguageConfiguration, LoadError> {
-    LanguageConfiguration::from_sources(
+    let mut lc = LanguageConfiguration::from_sources(
         tree_sitter_java::language(),
         Some(String::from("source.java")),
         None,
         vec![String::from("java")],
-        STACK_GRAPHS_TSG_PATH.into(),
-        STACK_GRAPHS_TSG_SOURCE,
-        Some((
+        &[(STACK_GRAPHS_TSG_PATH.into(), STACK_GRAPHS_TSG_SOURCE)],
+        None,
+        None,
+        vec![],
+        FileAnalyzers::new(),
+        cancellation_flag,
+    )?;
+    lc.load_builtins_with_precompiled(
+        STACK_GRAPHS_BUILTINS_PRECOMPILED,
+        (
             STACK_GRAPHS_BUILTINS_PATH.into(),
             STACK_GRAPHS_BUILTINS_SOURCE,
-        )),
+        ),
         Some(STACK_GRAPHS_BUILTINS_CONFIG),
-        FileAnalyzers::new(),
         cancellation_flag,
-    )
+    )?;
+    lc.detection.interpreters = vec![String::from("java")];
+    lc.detection.modelines = vec![String::from("java")];
+    Ok(lc)
 }
@@ -0,0 +1,43 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Precompiles the builtins, which are embedded in the library. If precompiling fails, empty
+//! builtins are embedded, and the library builds the builtins from source instead.
+
+use std::path::PathBuf;
+use tree_sitter_stack_graphs::builtins::PrecompiledBuiltins;
+use tree_sitter_stack_graphs::NoCancellation;
+
+fn main() {
+    for path in [
+        "src/stack-graphs.tsg",
+        "src/builtins.ts",
+        "src/builtins.cfg",
+    ] {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+    let precompiled = PrecompiledBuiltins::from_sources(
+        tree_sitter_typescript::language_typescript(),
+        &[(
+            "src/stack-graphs.tsg".into(),
+            include_str!("../src/stack-graphs.tsg"),
+        )],
+        ("src/builtins.ts".into(), include_str!("../src/builtins.ts")),
+        Some(include_str!("../src/builtins.cfg")),
+        &NoCancellation,
+    )
+    .and_then(|precompiled| Ok(precompiled.encode()?));
+    let bytes = match precompiled {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            println!("cargo:warning=Builtins are built from source: {}", err);
+            Vec::new()
+        }
+    };
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    std::fs::write(out_dir.join("builtins.bin"), bytes).expect("Failed to write builtins");
+}
@@ -28,6 +28,10 @@ pub const STACK_GRAPHS_BUILTINS_CONFIG: &str = include_str!("../src/builtins.cfg
 pub const STACK_GRAPHS_BUILTINS_PATH: &str = "src/builtins.ts";
 /// The stack graphs builtins source for this language
 pub const STACK_GRAPHS_BUILTINS_SOURCE: &str = include_str!("../src/builtins.ts");
+/// The precompiled stack graphs builtins for this language, see [`tree_sitter_stack_graphs::builtins`][].
+/// Empty if the builtins could not be precompiled at build time.
+pub const STACK_GRAPHS_BUILTINS_PRECOMPILED: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/builtins.bin"));
 
 /// The name of the file path global variable
 pub const FILE_PATH_VAR: &str = "FILE_PATH";
@@ -41,21 +45,30 @@ pub fn language_configuration(cancellation_flag: &dyn CancellationFlag) -> Langu
 pub fn try_language_configuration(
     cancellation_flag: &dyn CancellationFlag,
 ) -> Result<LanguageConfiguration, LoadError> {
-    LanguageConfiguration::from_sources(
+    let mut lc = LanguageConfiguration::from_sources(
         tree_sitter_typescript::language_typescript(),
         Some(String::from("source.ts")),
         None,
         vec![String::from("ts")],
-        STACK_GRAPHS_TSG_PATH.into(),
-        STACK_GRAPHS_TSG_SOURCE,
-        Some((
-            STACK_GRAPHS_BUILTINS_PATH.into(),
-            STACK_GRAPHS_BUILTINS_SOURCE,
-        )),
-        Some(STACK_GRAPHS_BUILTINS_CONFIG),
+        &[(STACK_GRAPHS_TSG_PATH.into(), STACK_GRAPHS_TSG_SOURCE)],
+        None,
+        None,
+        vec![],
         FileAnalyzers::new()
             .add("tsconfig.json".to_string(), TsConfigAnalyzer {})
             .add("package.json".to_string(), NpmPackageAnalyzer {}),
         cancellation_flag,
-    )
+    )?;
+    lc.load_builtins_with_precompiled(
+        STACK_GRAPHS_BUILTINS_PRECOMPILED,
+        (
+            STACK_GRAPHS_BUILTINS_PATH.into(),
+            STACK_GRAPHS_BUILTINS_SOURCE,
+        ),
+        Some(STACK_GRAPHS_BUILTINS_CONFIG),
+        cancellation_flag,
+    )?;
+    lc.detection.interpreters = vec![String::from("deno"), String::from("ts-node")];
+    lc.detection.modelines = vec![String::from("typescript")];
+    Ok(lc)
 }
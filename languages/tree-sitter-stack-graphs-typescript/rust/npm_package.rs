@@ -10,6 +10,7 @@ use std::collections::HashMap;
 use std::path::Path;
 
 use stack_graphs::arena::Handle;
+use stack_graphs::graph::builder::FileGraphBuilder;
 use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
 use tree_sitter_stack_graphs::BuildError;
@@ -37,36 +38,35 @@ impl FileAnalyzer for NpmPackageAnalyzer {
         let npm_pkg: NpmPackage =
             serde_json::from_str(source).map_err(|_| BuildError::ParseError)?;
 
+        let mut builder = FileGraphBuilder::new(graph, file);
+
         // root node
-        let root = StackGraph::root_node();
+        let root = builder.root();
 
         // project scope
         let proj_scope = if let Some(proj_name) = proj_name {
-            let proj_scope_id = graph.new_node_id(file);
-            let proj_scope = graph.add_scope_node(proj_scope_id, false).unwrap();
-            add_debug_name(graph, proj_scope, "npm_package.proj_scope");
+            let proj_scope = builder.scope();
+            builder.debug_name(proj_scope, "npm_package.proj_scope");
 
             // project definition
             let proj_def = add_ns_pop(
-                graph,
-                file,
+                &mut builder,
                 root,
                 PROJ_NS,
                 proj_name,
                 "npm_package.proj_def",
             );
-            add_edge(graph, proj_def, proj_scope, 0);
+            builder.edge(proj_def, proj_scope);
 
             // project reference
             let proj_ref = add_ns_push(
-                graph,
-                file,
+                &mut builder,
                 root,
                 PROJ_NS,
                 proj_name,
                 "npm_package.proj_ref",
             );
-            add_edge(graph, proj_scope, proj_ref, 0);
+            builder.edge(proj_scope, proj_ref);
 
             proj_scope
         } else {
@@ -75,35 +75,32 @@ impl FileAnalyzer for NpmPackageAnalyzer {
 
         // package definition
         let pkg_def = add_module_pops(
-            graph,
-            file,
+            &mut builder,
             NON_REL_M_NS,
             Path::new(&npm_pkg.name),
             root,
             "npm_package.pkg_def",
         );
-        let pkg_ref = add_push(graph, file, proj_scope, PKG_M_NS, "npm_package.pkg_ref");
-        add_edge(graph, pkg_def, pkg_ref, 0);
+        let pkg_ref = add_push(&mut builder, proj_scope, PKG_M_NS, "npm_package.pkg_ref");
+        builder.edge(pkg_def, pkg_ref);
 
         // dependencies (package references)
         for (i, (pkg_name, _)) in npm_pkg.dependencies.iter().enumerate() {
             let pkg_def = add_module_pops(
-                graph,
-                file,
+                &mut builder,
                 NON_REL_M_NS,
                 Path::new(&pkg_name),
                 proj_scope,
                 &format!("npm_package.dep[{}]", i),
             );
             let pkg_ref = add_module_pushes(
-                graph,
-                file,
+                &mut builder,
                 NON_REL_M_NS,
                 Path::new(&pkg_name),
                 root,
                 &format!("npm_package.dep[{}]", i),
             );
-            add_edge(graph, pkg_def, pkg_ref, 0);
+            builder.edge(pkg_def, pkg_ref);
         }
 
         Ok(())
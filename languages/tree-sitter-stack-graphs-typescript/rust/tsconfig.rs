@@ -12,6 +12,7 @@ use std::path::Path;
 use std::path::PathBuf;
 
 use stack_graphs::arena::Handle;
+use stack_graphs::graph::builder::FileGraphBuilder;
 use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
 use tree_sitter_stack_graphs::BuildError;
@@ -38,24 +39,23 @@ impl FileAnalyzer for TsConfigAnalyzer {
         // parse source
         let tsc = TsConfig::parse_str(path, source).map_err(|_| BuildError::ParseError)?;
 
+        let mut builder = FileGraphBuilder::new(graph, file);
+
         // root node
-        let root = StackGraph::root_node();
+        let root = builder.root();
 
         // project scope
         let proj_scope = if let Some(proj_name) = proj_name {
-            let proj_scope_id = graph.new_node_id(file);
-            let proj_scope = graph
-                .add_scope_node(proj_scope_id, false)
-                .expect("no previous node for new id");
-            add_debug_name(graph, proj_scope, "tsconfig.proj_scope");
+            let proj_scope = builder.scope();
+            builder.debug_name(proj_scope, "tsconfig.proj_scope");
 
             // project definition
-            let proj_def = add_ns_pop(graph, file, root, PROJ_NS, proj_name, "tsconfig.proj_def");
-            add_edge(graph, proj_def, proj_scope, 0);
+            let proj_def = add_ns_pop(&mut builder, root, PROJ_NS, proj_name, "tsconfig.proj_def");
+            builder.edge(proj_def, proj_scope);
 
             // project reference
-            let proj_ref = add_ns_push(graph, file, root, PROJ_NS, proj_name, "tsconfig.proj_ref");
-            add_edge(graph, proj_scope, proj_ref, 0);
+            let proj_ref = add_ns_push(&mut builder, root, PROJ_NS, proj_name, "tsconfig.proj_ref");
+            builder.edge(proj_scope, proj_ref);
 
             proj_scope
         } else {
@@ -63,55 +63,50 @@ impl FileAnalyzer for TsConfigAnalyzer {
         };
 
         // root directory
-        let pkg_def = add_pop(graph, file, proj_scope, PKG_M_NS, "tsconfig.pkg_def");
+        let pkg_def = add_pop(&mut builder, proj_scope, PKG_M_NS, "tsconfig.pkg_def");
         let root_dir_ref = add_module_pushes(
-            graph,
-            file,
+            &mut builder,
             M_NS,
             &tsc.root_dir(all_paths),
             proj_scope,
             "tsconfig.root_dir.ref",
         );
-        add_edge(graph, pkg_def, root_dir_ref, 0);
+        builder.edge(pkg_def, root_dir_ref);
 
         // auxiliary root directories, map relative imports to module paths
         for (idx, root_dir) in tsc.root_dirs().iter().enumerate() {
             let root_dir_def = add_pop(
-                graph,
-                file,
+                &mut builder,
                 proj_scope,
                 REL_M_NS,
                 &format!("tsconfig.root_dirs[{}].def", idx),
             );
             let root_dir_ref = add_module_pushes(
-                graph,
-                file,
+                &mut builder,
                 M_NS,
                 root_dir,
                 proj_scope,
                 &format!("tsconfig.root_dirs[{}].ref", idx),
             );
-            add_edge(graph, root_dir_def, root_dir_ref, 0);
+            builder.edge(root_dir_def, root_dir_ref);
         }
 
         // base URL
         let base_url = tsc.base_url();
         let base_url_def = add_pop(
-            graph,
-            file,
+            &mut builder,
             proj_scope,
             NON_REL_M_NS,
             "tsconfig.base_url.def",
         );
         let base_url_ref = add_module_pushes(
-            graph,
-            file,
+            &mut builder,
             M_NS,
             &base_url,
             proj_scope,
             "tsconfig.base_url.ref",
         );
-        add_edge(graph, base_url_def, base_url_ref, 0);
+        builder.edge(base_url_def, base_url_ref);
 
         // path mappings
         for (from_idx, (from, tos)) in tsc.paths().iter().enumerate() {
@@ -125,8 +120,7 @@ impl FileAnalyzer for TsConfigAnalyzer {
                 &from
             };
             let from_def = add_module_pops(
-                graph,
-                file,
+                &mut builder,
                 NON_REL_M_NS,
                 from,
                 proj_scope,
@@ -145,14 +139,13 @@ impl FileAnalyzer for TsConfigAnalyzer {
                     &to
                 };
                 let to_ref = add_module_pushes(
-                    graph,
-                    file,
+                    &mut builder,
                     M_NS,
                     to,
                     proj_scope,
                     &format!("tsconfig.paths[{}][{}].to_ref", from_idx, to_idx),
                 );
-                add_edge(graph, from_def, to_ref, 0);
+                builder.edge(from_def, to_ref);
             }
         }
 
@@ -8,7 +8,7 @@ let x = {
 
     x.f;
   //^ defined: 6
-  //  ^ defined:
+  //  ^ defined: (xfail)
 }
 
 export {};
@@ -7,6 +7,9 @@
 
 //! Defines assertions that can be run against a stack graph.
 
+use std::collections::HashMap;
+use std::collections::HashSet;
+
 use itertools::Itertools;
 use lsp_positions::Position;
 
@@ -30,6 +33,18 @@ pub enum Assertion {
         source: AssertionSource,
         targets: Vec<AssertionTarget>,
     },
+    DefinedCount {
+        source: AssertionSource,
+        count: usize,
+    },
+    NotDefined {
+        source: AssertionSource,
+        targets: Vec<AssertionTarget>,
+    },
+    Referenced {
+        source: AssertionSource,
+        targets: Vec<AssertionTarget>,
+    },
     Defines {
         source: AssertionSource,
         symbols: Vec<Handle<Symbol>>,
@@ -111,18 +126,113 @@ impl AssertionTarget {
     }
 }
 
+/// Context shared between assertions that are run against the same graph.
+///
+/// A context can restrict assertions to a set of files. Only paths ending in those files count
+/// as definitions for `defined` and `defined-count` assertions, and only references in those files
+/// are considered for `referenced` assertions. Resolved references are cached, so that running
+/// several assertions against the same graph does not resolve the same reference repeatedly.
+#[derive(Default)]
+pub struct AssertionContext {
+    files: Option<HashSet<Handle<File>>>,
+    resolutions: HashMap<Handle<Node>, Vec<PartialPath>>,
+}
+
+impl AssertionContext {
+    /// Creates a new context that does not restrict assertions to any files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new context that restricts assertions to the given files.
+    pub fn for_files<I: IntoIterator<Item = Handle<File>>>(files: I) -> Self {
+        Self {
+            files: Some(files.into_iter().collect()),
+            resolutions: HashMap::new(),
+        }
+    }
+
+    /// Returns whether the given node is in one of the files of this context.
+    fn contains(&self, graph: &StackGraph, node: Handle<Node>) -> bool {
+        match &self.files {
+            Some(files) => graph[node].file().is_some_and(|f| files.contains(&f)),
+            None => true,
+        }
+    }
+
+    /// Resolve the given references, returning all complete paths that are not shadowed by
+    /// another path from the same reference.
+    fn resolve(
+        &mut self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        db: &mut Database,
+        references: &[Handle<Node>],
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<PartialPath>, AssertionError> {
+        let mut actual_paths = Vec::new();
+        for reference in references {
+            if let Some(paths) = self.resolutions.get(reference) {
+                actual_paths.extend(paths.iter().cloned());
+                continue;
+            }
+            let mut reference_paths = Vec::new();
+            ForwardPartialPathStitcher::find_all_complete_partial_paths(
+                &mut DatabaseCandidates::new(graph, partials, db),
+                vec![*reference],
+                cancellation_flag,
+                |_, _, p| {
+                    reference_paths.push(p.clone());
+                },
+            )?;
+            let paths = reference_paths
+                .iter()
+                .filter(|reference_path| {
+                    reference_paths
+                        .iter()
+                        .all(|other| !other.shadows(partials, reference_path))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            actual_paths.extend(paths.iter().cloned());
+            self.resolutions.insert(*reference, paths);
+        }
+        Ok(actual_paths)
+    }
+}
+
 /// Error describing assertion failures.
 #[derive(Clone)]
 pub enum AssertionError {
     NoReferences {
         source: AssertionSource,
     },
+    NoDefinitions {
+        source: AssertionSource,
+    },
     IncorrectlyDefined {
         source: AssertionSource,
         references: Vec<Handle<Node>>,
         missing_targets: Vec<AssertionTarget>,
         unexpected_paths: Vec<PartialPath>,
     },
+    UnexpectedlyDefined {
+        source: AssertionSource,
+        references: Vec<Handle<Node>>,
+        unexpected_paths: Vec<PartialPath>,
+    },
+    IncorrectDefinitionCount {
+        source: AssertionSource,
+        references: Vec<Handle<Node>>,
+        expected_count: usize,
+        actual_paths: Vec<PartialPath>,
+    },
+    IncorrectlyReferenced {
+        source: AssertionSource,
+        definitions: Vec<Handle<Node>>,
+        missing_targets: Vec<AssertionTarget>,
+        unexpected_paths: Vec<PartialPath>,
+    },
     IncorrectDefinitions {
         source: AssertionSource,
         missing_symbols: Vec<Handle<Symbol>>,
@@ -143,6 +253,18 @@ impl From<CancellationError> for AssertionError {
 }
 
 impl Assertion {
+    /// Returns the source position of this assertion.
+    pub fn source(&self) -> &AssertionSource {
+        match self {
+            Self::Defined { source, .. }
+            | Self::DefinedCount { source, .. }
+            | Self::NotDefined { source, .. }
+            | Self::Referenced { source, .. }
+            | Self::Defines { source, .. }
+            | Self::Refers { source, .. } => source,
+        }
+    }
+
     /// Run this assertion against the given graph, using the given paths object for path search.
     pub fn run(
         &self,
@@ -150,10 +272,38 @@ impl Assertion {
         partials: &mut PartialPaths,
         db: &mut Database,
         cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), AssertionError> {
+        self.run_in_context(
+            &mut AssertionContext::new(),
+            graph,
+            partials,
+            db,
+            cancellation_flag,
+        )
+    }
+
+    /// Run this assertion against the given graph in the given context, using the given paths
+    /// object for path search.
+    pub fn run_in_context(
+        &self,
+        ctx: &mut AssertionContext,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        db: &mut Database,
+        cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), AssertionError> {
         match self {
             Self::Defined { source, targets } => {
-                self.run_defined(graph, partials, db, source, targets, cancellation_flag)
+                Self::run_defined(ctx, graph, partials, db, source, targets, cancellation_flag)
+            }
+            Self::DefinedCount { source, count } => {
+                Self::run_defined_count(ctx, graph, partials, db, source, *count, cancellation_flag)
+            }
+            Self::NotDefined { source, targets } => {
+                Self::run_not_defined(ctx, graph, partials, db, source, targets, cancellation_flag)
+            }
+            Self::Referenced { source, targets } => {
+                Self::run_referenced(ctx, graph, partials, db, source, targets, cancellation_flag)
             }
             Self::Defines { source, symbols } => self.run_defines(graph, source, symbols),
             Self::Refers { source, symbols } => self.run_refers(graph, source, symbols),
@@ -161,7 +311,7 @@ impl Assertion {
     }
 
     fn run_defined(
-        &self,
+        ctx: &mut AssertionContext,
         graph: &StackGraph,
         partials: &mut PartialPaths,
         db: &mut Database,
@@ -176,26 +326,11 @@ impl Assertion {
             });
         }
 
-        let mut actual_paths = Vec::new();
-        for reference in &references {
-            let mut reference_paths = Vec::new();
-            ForwardPartialPathStitcher::find_all_complete_partial_paths(
-                &mut DatabaseCandidates::new(graph, partials, db),
-                vec![*reference],
-                cancellation_flag,
-                |_, _, p| {
-                    reference_paths.push(p.clone());
-                },
-            )?;
-            for reference_path in &reference_paths {
-                if reference_paths
-                    .iter()
-                    .all(|other| !other.shadows(partials, reference_path))
-                {
-                    actual_paths.push(reference_path.clone());
-                }
-            }
-        }
+        let actual_paths = ctx
+            .resolve(graph, partials, db, &references, cancellation_flag)?
+            .into_iter()
+            .filter(|p| ctx.contains(graph, p.end_node))
+            .collect::<Vec<_>>();
 
         let missing_targets = expected_targets
             .iter()
@@ -228,6 +363,135 @@ impl Assertion {
         Ok(())
     }
 
+    fn run_defined_count(
+        ctx: &mut AssertionContext,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        db: &mut Database,
+        source: &AssertionSource,
+        expected_count: usize,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), AssertionError> {
+        let references = source.iter_references(graph).collect::<Vec<_>>();
+        if references.is_empty() {
+            return Err(AssertionError::NoReferences {
+                source: source.clone(),
+            });
+        }
+
+        let actual_paths = ctx
+            .resolve(graph, partials, db, &references, cancellation_flag)?
+            .into_iter()
+            .filter(|p| ctx.contains(graph, p.end_node))
+            .collect::<Vec<_>>();
+        if actual_paths.len() != expected_count {
+            return Err(AssertionError::IncorrectDefinitionCount {
+                source: source.clone(),
+                references,
+                expected_count,
+                actual_paths,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn run_not_defined(
+        ctx: &mut AssertionContext,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        db: &mut Database,
+        source: &AssertionSource,
+        forbidden_targets: &[AssertionTarget],
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), AssertionError> {
+        let references = source.iter_references(graph).collect::<Vec<_>>();
+        if references.is_empty() {
+            return Err(AssertionError::NoReferences {
+                source: source.clone(),
+            });
+        }
+
+        let actual_paths = ctx.resolve(graph, partials, db, &references, cancellation_flag)?;
+        // without targets, the reference should not resolve at all, not even to definitions
+        // outside of the context files
+        let unexpected_paths = actual_paths
+            .into_iter()
+            .filter(|p| {
+                forbidden_targets.is_empty()
+                    || forbidden_targets
+                        .iter()
+                        .any(|t| t.matches_node(p.end_node, graph))
+            })
+            .collect::<Vec<_>>();
+        if !unexpected_paths.is_empty() {
+            return Err(AssertionError::UnexpectedlyDefined {
+                source: source.clone(),
+                references,
+                unexpected_paths,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn run_referenced(
+        ctx: &mut AssertionContext,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        db: &mut Database,
+        source: &AssertionSource,
+        expected_targets: &[AssertionTarget],
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), AssertionError> {
+        let definitions = source.iter_definitions(graph).collect::<Vec<_>>();
+        if definitions.is_empty() {
+            return Err(AssertionError::NoDefinitions {
+                source: source.clone(),
+            });
+        }
+
+        let references = graph
+            .iter_nodes()
+            .filter(|n| graph[*n].is_reference() && ctx.contains(graph, *n))
+            .collect::<Vec<_>>();
+        let actual_paths = ctx
+            .resolve(graph, partials, db, &references, cancellation_flag)?
+            .into_iter()
+            .filter(|p| definitions.contains(&p.end_node))
+            .collect::<Vec<_>>();
+
+        let missing_targets = expected_targets
+            .iter()
+            .filter(|t| {
+                !actual_paths
+                    .iter()
+                    .any(|p| t.matches_node(p.start_node, graph))
+            })
+            .cloned()
+            .unique()
+            .collect::<Vec<_>>();
+        let unexpected_paths = actual_paths
+            .iter()
+            .filter(|p| {
+                !expected_targets
+                    .iter()
+                    .any(|t| t.matches_node(p.start_node, graph))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if !missing_targets.is_empty() || !unexpected_paths.is_empty() {
+            return Err(AssertionError::IncorrectlyReferenced {
+                source: source.clone(),
+                definitions,
+                missing_targets,
+                unexpected_paths,
+            });
+        }
+
+        Ok(())
+    }
+
     fn run_defines(
         &self,
         graph: &StackGraph,
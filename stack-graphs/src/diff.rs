@@ -0,0 +1,472 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Computes structural differences between two stack graphs.
+//!
+//! The stack graphs that are compared are typically constructed from the same source files, using
+//! different versions of the graph construction rules.  Files are matched by name.  Nodes in
+//! matching files are matched by their [`NodeID`][]s, provided the nodes have the same kind and
+//! symbol.  Remaining nodes are matched by their kind, symbol, and source span, as long as that
+//! combination is unique in both graphs.  Nodes that cannot be matched are reported as added or
+//! removed.  Edges are matched by their (matched) source and sink nodes.
+//!
+//! [`NodeID`]: ../graph/struct.NodeID.html
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::arena::Handle;
+use crate::graph::Edge;
+use crate::graph::File;
+use crate::graph::Node;
+use crate::graph::NodeID;
+use crate::graph::StackGraph;
+use crate::stats::NodeKind;
+
+/// The differences between two stack graphs, which we call the _left_ and the _right_ graph.
+/// Removed nodes and edges refer to the left graph, added ones to the right graph.
+#[derive(Clone, Debug, Default)]
+pub struct GraphDiff {
+    pub added_nodes: Vec<Handle<Node>>,
+    pub removed_nodes: Vec<Handle<Node>>,
+    pub changed_nodes: Vec<ChangedNode>,
+    pub added_edges: Vec<Edge>,
+    pub removed_edges: Vec<Edge>,
+    pub changed_edges: Vec<ChangedEdge>,
+}
+
+/// A node that appears in both graphs, but with different properties.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChangedNode {
+    pub left: Handle<Node>,
+    pub right: Handle<Node>,
+    pub changes: Vec<NodeChange>,
+}
+
+/// A node property that differs between the left and right graph.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum NodeChange {
+    IsDefinition,
+    IsReference,
+    IsExported,
+    Scope,
+    Span,
+    SyntaxType,
+    DefiniensSpan,
+    FullyQualifiedName,
+}
+
+impl Display for NodeChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::IsDefinition => write!(f, "is_definition"),
+            Self::IsReference => write!(f, "is_reference"),
+            Self::IsExported => write!(f, "is_exported"),
+            Self::Scope => write!(f, "scope"),
+            Self::Span => write!(f, "span"),
+            Self::SyntaxType => write!(f, "syntax_type"),
+            Self::DefiniensSpan => write!(f, "definiens_span"),
+            Self::FullyQualifiedName => write!(f, "fully_qualified_name"),
+        }
+    }
+}
+
+/// An edge that appears in both graphs, but with a different precedence.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ChangedEdge {
+    pub left: Edge,
+    pub right: Edge,
+}
+
+impl GraphDiff {
+    /// Returns whether the graphs are structurally equal.
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.changed_edges.is_empty()
+    }
+
+    pub fn display<'a>(&'a self, left: &'a StackGraph, right: &'a StackGraph) -> impl Display + 'a {
+        DisplayGraphDiff {
+            wrapped: self,
+            left,
+            right,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct DisplayGraphDiff<'a> {
+    wrapped: &'a GraphDiff,
+    left: &'a StackGraph,
+    right: &'a StackGraph,
+}
+
+impl<'a> DisplayGraphDiff<'a> {
+    fn fmt_node(
+        f: &mut std::fmt::Formatter,
+        graph: &StackGraph,
+        node: Handle<Node>,
+    ) -> std::fmt::Result {
+        write!(f, "{}", node.display(graph))?;
+        let span = span(graph, node);
+        if span != lsp_positions::Span::default() {
+            write!(
+                f,
+                " at {}:{}",
+                span.start.line + 1,
+                span.start.column.grapheme_offset + 1
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Display for DisplayGraphDiff<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for node in &self.wrapped.removed_nodes {
+            write!(f, "- node ")?;
+            Self::fmt_node(f, self.left, *node)?;
+            writeln!(f)?;
+        }
+        for node in &self.wrapped.added_nodes {
+            write!(f, "+ node ")?;
+            Self::fmt_node(f, self.right, *node)?;
+            writeln!(f)?;
+        }
+        for changed in &self.wrapped.changed_nodes {
+            write!(f, "~ node ")?;
+            Self::fmt_node(f, self.left, changed.left)?;
+            write!(f, " -> ")?;
+            Self::fmt_node(f, self.right, changed.right)?;
+            write!(f, ":")?;
+            for change in &changed.changes {
+                write!(f, " {}", change)?;
+            }
+            writeln!(f)?;
+        }
+        for edge in &self.wrapped.removed_edges {
+            writeln!(
+                f,
+                "- edge {} -> {}",
+                edge.source.display(self.left),
+                edge.sink.display(self.left)
+            )?;
+        }
+        for edge in &self.wrapped.added_edges {
+            writeln!(
+                f,
+                "+ edge {} -> {}",
+                edge.source.display(self.right),
+                edge.sink.display(self.right)
+            )?;
+        }
+        for changed in &self.wrapped.changed_edges {
+            writeln!(
+                f,
+                "~ edge {} -> {}: precedence {} -> {}",
+                changed.left.source.display(self.left),
+                changed.left.sink.display(self.left),
+                changed.left.precedence,
+                changed.right.precedence
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl StackGraph {
+    /// Computes the differences between this stack graph and another one, for all files in either
+    /// graph.
+    pub fn diff(&self, other: &StackGraph) -> GraphDiff {
+        let mut files = self
+            .iter_files()
+            .map(|file| (Some(file), other.get_file(self[file].name())))
+            .collect::<Vec<_>>();
+        files.extend(
+            other
+                .iter_files()
+                .filter(|file| self.get_file(other[*file].name()).is_none())
+                .map(|file| (None, Some(file))),
+        );
+        Differ::new(self, other).diff(&files)
+    }
+
+    /// Computes the differences between this stack graph and another one, for the given file of
+    /// this graph and the file with the same name in the other graph.
+    pub fn diff_file(&self, file: Handle<File>, other: &StackGraph) -> GraphDiff {
+        let other_file = other.get_file(self[file].name());
+        Differ::new(self, other).diff(&[(Some(file), other_file)])
+    }
+}
+
+/// A file in the left graph and the file with the same name in the right graph, if they exist.
+type FilePair = (Option<Handle<File>>, Option<Handle<File>>);
+
+/// Maps the nodes of compared files to their matching node in the other graph, if any.
+type NodeMapping = HashMap<Handle<Node>, Option<Handle<Node>>>;
+
+struct Differ<'a> {
+    left: &'a StackGraph,
+    right: &'a StackGraph,
+    left_to_right: NodeMapping,
+    right_to_left: NodeMapping,
+    result: GraphDiff,
+}
+
+impl<'a> Differ<'a> {
+    fn new(left: &'a StackGraph, right: &'a StackGraph) -> Self {
+        Self {
+            left,
+            right,
+            left_to_right: HashMap::new(),
+            right_to_left: HashMap::new(),
+            result: GraphDiff::default(),
+        }
+    }
+
+    fn diff(mut self, files: &[FilePair]) -> GraphDiff {
+        for (left_file, right_file) in files {
+            self.match_nodes(*left_file, *right_file);
+        }
+        for (left_file, right_file) in files {
+            for left_node in Self::nodes_for_file(self.left, *left_file) {
+                self.left_to_right.entry(left_node).or_insert(None);
+            }
+            for right_node in Self::nodes_for_file(self.right, *right_file) {
+                self.right_to_left.entry(right_node).or_insert(None);
+            }
+        }
+        for (left_file, right_file) in files {
+            self.diff_nodes(*left_file, *right_file);
+        }
+        let singletons = [StackGraph::root_node(), StackGraph::jump_to_node()];
+        let left_nodes = files
+            .iter()
+            .flat_map(|(left_file, _)| Self::nodes_for_file(self.left, *left_file))
+            .chain(singletons)
+            .collect::<Vec<_>>();
+        let right_nodes = files
+            .iter()
+            .flat_map(|(_, right_file)| Self::nodes_for_file(self.right, *right_file))
+            .chain(singletons)
+            .collect::<Vec<_>>();
+        self.diff_edges(&left_nodes, &right_nodes);
+        self.result
+    }
+
+    fn nodes_for_file(graph: &StackGraph, file: Option<Handle<File>>) -> Vec<Handle<Node>> {
+        file.map(|file| graph.nodes_for_file(file).collect())
+            .unwrap_or_default()
+    }
+
+    fn match_nodes(&mut self, left_file: Option<Handle<File>>, right_file: Option<Handle<File>>) {
+        let left_nodes = Self::nodes_for_file(self.left, left_file);
+        let right_nodes = Self::nodes_for_file(self.right, right_file);
+
+        // match by node ID, if kind and symbol are the same
+        if let Some(right_file) = right_file {
+            for left_node in &left_nodes {
+                let id = NodeID::new_in_file(right_file, self.left[*left_node].id().local_id());
+                if let Some(right_node) = self.right.node_for_id(id) {
+                    if node_key(self.left, *left_node) == node_key(self.right, right_node) {
+                        self.left_to_right.insert(*left_node, Some(right_node));
+                        self.right_to_left.insert(right_node, Some(*left_node));
+                    }
+                }
+            }
+        }
+
+        // match remaining nodes by kind, symbol, and span, if that is unique
+        let mut candidates = HashMap::<_, (Vec<Handle<Node>>, Vec<Handle<Node>>)>::new();
+        for left_node in &left_nodes {
+            if !self.left_to_right.contains_key(left_node) {
+                let key = (node_key(self.left, *left_node), span(self.left, *left_node));
+                candidates.entry(key).or_default().0.push(*left_node);
+            }
+        }
+        for right_node in &right_nodes {
+            if !self.right_to_left.contains_key(right_node) {
+                let key = (
+                    node_key(self.right, *right_node),
+                    span(self.right, *right_node),
+                );
+                candidates.entry(key).or_default().1.push(*right_node);
+            }
+        }
+        for (left_nodes, right_nodes) in candidates.values() {
+            if let ([left_node], [right_node]) = (left_nodes.as_slice(), right_nodes.as_slice()) {
+                self.left_to_right.insert(*left_node, Some(*right_node));
+                self.right_to_left.insert(*right_node, Some(*left_node));
+            }
+        }
+    }
+
+    fn diff_nodes(&mut self, left_file: Option<Handle<File>>, right_file: Option<Handle<File>>) {
+        for left_node in Self::nodes_for_file(self.left, left_file) {
+            match self.left_to_right[&left_node] {
+                Some(right_node) => {
+                    let changes = self.node_changes(left_node, right_node);
+                    if !changes.is_empty() {
+                        self.result.changed_nodes.push(ChangedNode {
+                            left: left_node,
+                            right: right_node,
+                            changes,
+                        });
+                    }
+                }
+                None => self.result.removed_nodes.push(left_node),
+            }
+        }
+        for right_node in Self::nodes_for_file(self.right, right_file) {
+            if self.right_to_left[&right_node].is_none() {
+                self.result.added_nodes.push(right_node);
+            }
+        }
+    }
+
+    fn node_changes(&self, left_node: Handle<Node>, right_node: Handle<Node>) -> Vec<NodeChange> {
+        let mut changes = Vec::new();
+        let left = &self.left[left_node];
+        let right = &self.right[right_node];
+        if left.is_definition() != right.is_definition() {
+            changes.push(NodeChange::IsDefinition);
+        }
+        if left.is_reference() != right.is_reference() {
+            changes.push(NodeChange::IsReference);
+        }
+        if left.is_exported_scope() != right.is_exported_scope() {
+            changes.push(NodeChange::IsExported);
+        }
+        let left_scope = left
+            .scope()
+            .and_then(|scope| self.left.node_for_id(scope))
+            .and_then(|scope| Self::map_node(self.left, self.right, &self.left_to_right, scope));
+        let right_scope = right
+            .scope()
+            .and_then(|scope| self.right.node_for_id(scope));
+        if left_scope != right_scope {
+            changes.push(NodeChange::Scope);
+        }
+        let left_info = self.left.source_info(left_node);
+        let right_info = self.right.source_info(right_node);
+        if span(self.left, left_node) != span(self.right, right_node) {
+            changes.push(NodeChange::Span);
+        }
+        let syntax_type = |graph: &'a StackGraph, info: Option<&'a crate::graph::SourceInfo>| {
+            info.and_then(|info| info.syntax_type.into_option())
+                .map(|s| &graph[s])
+        };
+        if syntax_type(self.left, left_info) != syntax_type(self.right, right_info) {
+            changes.push(NodeChange::SyntaxType);
+        }
+        let definiens_span = |info: Option<&crate::graph::SourceInfo>| {
+            info.map(|info| info.definiens_span.clone())
+                .unwrap_or_default()
+        };
+        if definiens_span(left_info) != definiens_span(right_info) {
+            changes.push(NodeChange::DefiniensSpan);
+        }
+        let fqn = |graph: &'a StackGraph, info: Option<&'a crate::graph::SourceInfo>| {
+            info.and_then(|info| info.fully_qualified_name.into_option())
+                .map(|s| &graph[s])
+        };
+        if fqn(self.left, left_info) != fqn(self.right, right_info) {
+            changes.push(NodeChange::FullyQualifiedName);
+        }
+        changes
+    }
+
+    /// Compares the outgoing edges of the given nodes.  Only edges with a source or sink in one of
+    /// the compared files are considered.
+    fn diff_edges(&mut self, left_nodes: &[Handle<Node>], right_nodes: &[Handle<Node>]) {
+        for left_node in left_nodes {
+            for left_edge in self.left.outgoing_edges(*left_node) {
+                if !self.left_to_right.contains_key(&left_edge.source)
+                    && !self.left_to_right.contains_key(&left_edge.sink)
+                {
+                    continue;
+                }
+                match self.map_edge(self.left, self.right, &self.left_to_right, left_edge) {
+                    Some(right_edge) if right_edge.precedence != left_edge.precedence => {
+                        self.result.changed_edges.push(ChangedEdge {
+                            left: left_edge,
+                            right: right_edge,
+                        })
+                    }
+                    Some(_) => {}
+                    None => self.result.removed_edges.push(left_edge),
+                }
+            }
+        }
+        for right_node in right_nodes {
+            for right_edge in self.right.outgoing_edges(*right_node) {
+                if !self.right_to_left.contains_key(&right_edge.source)
+                    && !self.right_to_left.contains_key(&right_edge.sink)
+                {
+                    continue;
+                }
+                if self
+                    .map_edge(self.right, self.left, &self.right_to_left, right_edge)
+                    .is_none()
+                {
+                    self.result.added_edges.push(right_edge);
+                }
+            }
+        }
+    }
+
+    /// Returns the corresponding edge in the other graph, if it exists.
+    fn map_edge(
+        &self,
+        from: &StackGraph,
+        to: &StackGraph,
+        mapping: &NodeMapping,
+        edge: Edge,
+    ) -> Option<Edge> {
+        let source = Self::map_node(from, to, mapping, edge.source)?;
+        let sink = Self::map_node(from, to, mapping, edge.sink)?;
+        to.outgoing_edges(source).find(|e| e.sink == sink)
+    }
+
+    /// Returns the corresponding node in the other graph, if it exists.  Nodes in files that are
+    /// being compared must have been matched, other nodes are mapped by their node ID.
+    fn map_node(
+        from: &StackGraph,
+        to: &StackGraph,
+        mapping: &NodeMapping,
+        node: Handle<Node>,
+    ) -> Option<Handle<Node>> {
+        if let Some(node) = mapping.get(&node) {
+            return *node;
+        }
+        let id = from[node].id();
+        match id.file() {
+            Some(file) => {
+                let file = to.get_file(from[file].name())?;
+                to.node_for_id(NodeID::new_in_file(file, id.local_id()))
+            }
+            None => to.node_for_id(id),
+        }
+    }
+}
+
+/// Returns the kind and symbol of a node, which must be equal for nodes to match.
+fn node_key(graph: &StackGraph, node: Handle<Node>) -> (NodeKind, Option<&str>) {
+    let node = &graph[node];
+    (node.into(), node.symbol().map(|s| &graph[s]))
+}
+
+fn span(graph: &StackGraph, node: Handle<Node>) -> lsp_positions::Span {
+    graph
+        .source_info(node)
+        .map(|info| info.span.clone())
+        .unwrap_or_default()
+}
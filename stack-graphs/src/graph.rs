@@ -64,6 +64,9 @@ use crate::arena::Arena;
 use crate::arena::Handle;
 use crate::arena::SupplementalArena;
 
+pub mod builder;
+pub mod validation;
+
 //-------------------------------------------------------------------------------------------------
 // String content
 
@@ -0,0 +1,230 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Provides a convenient way to construct the stack graph of a file by hand.
+//!
+//! Most stack graphs are constructed from graph construction rules, but it is sometimes easier to
+//! construct (part of) the graph for a file directly, for example for configuration files that
+//! define how modules are resolved.  A [`FileGraphBuilder`][] takes care of allocating fresh node
+//! IDs, interning symbols and strings, and adding chains of nodes for qualified names.
+//!
+//! ```
+//! # use stack_graphs::graph::builder::FileGraphBuilder;
+//! # use stack_graphs::graph::StackGraph;
+//! let mut graph = StackGraph::new();
+//! let file = graph.get_or_create_file("package.json");
+//! let mut builder = FileGraphBuilder::new(&mut graph, file);
+//! let root = builder.root();
+//! // resolve `%M.my_pkg` to `%M.my_pkg.index`
+//! let def = builder.pop_symbols(root, ["%M", "my_pkg"]);
+//! let reference = builder.push_symbols(root, ["%M", "my_pkg", "index"]);
+//! builder.edge(def, reference);
+//! builder.debug_name(def, "pkg_def");
+//! ```
+//!
+//! [`FileGraphBuilder`]: struct.FileGraphBuilder.html
+
+use lsp_positions::Span;
+
+use crate::arena::Handle;
+use crate::graph::File;
+use crate::graph::Node;
+use crate::graph::StackGraph;
+
+/// The debug info key that is used for debug names.
+pub const DEBUG_NAME_KEY: &str = "name";
+
+/// Constructs the stack graph nodes and edges of a single file.
+pub struct FileGraphBuilder<'a> {
+    graph: &'a mut StackGraph,
+    file: Handle<File>,
+}
+
+impl<'a> FileGraphBuilder<'a> {
+    /// Creates a new builder that adds nodes to the given file of a stack graph.
+    pub fn new(graph: &'a mut StackGraph, file: Handle<File>) -> Self {
+        Self { graph, file }
+    }
+
+    /// Returns the stack graph this builder adds to.
+    pub fn graph(&mut self) -> &mut StackGraph {
+        self.graph
+    }
+
+    /// Returns the file this builder adds nodes to.
+    pub fn file(&self) -> Handle<File> {
+        self.file
+    }
+
+    /// Returns the singleton _root_ node.
+    pub fn root(&self) -> Handle<Node> {
+        StackGraph::root_node()
+    }
+
+    /// Returns the singleton _jump to scope_ node.
+    pub fn jump_to(&self) -> Handle<Node> {
+        StackGraph::jump_to_node()
+    }
+
+    /// Adds a new internal _scope_ node.
+    pub fn scope(&mut self) -> Handle<Node> {
+        let id = self.graph.new_node_id(self.file);
+        self.graph
+            .add_scope_node(id, false)
+            .expect("no previous node for new id")
+    }
+
+    /// Adds a new exported _scope_ node, which can be used as the attached scope of _push scoped
+    /// symbol_ nodes.
+    pub fn exported_scope(&mut self) -> Handle<Node> {
+        let id = self.graph.new_node_id(self.file);
+        self.graph
+            .add_scope_node(id, true)
+            .expect("no previous node for new id")
+    }
+
+    /// Adds a new _drop scopes_ node.
+    pub fn drop_scopes(&mut self) -> Handle<Node> {
+        let id = self.graph.new_node_id(self.file);
+        self.graph
+            .add_drop_scopes_node(id)
+            .expect("no previous node for new id")
+    }
+
+    /// Adds a new _pop symbol_ node.
+    pub fn pop_symbol(&mut self, symbol: &str) -> Handle<Node> {
+        self.add_pop_symbol(symbol, false)
+    }
+
+    /// Adds a new _pop symbol_ node that is a definition.
+    pub fn definition(&mut self, symbol: &str) -> Handle<Node> {
+        self.add_pop_symbol(symbol, true)
+    }
+
+    fn add_pop_symbol(&mut self, symbol: &str, is_definition: bool) -> Handle<Node> {
+        let id = self.graph.new_node_id(self.file);
+        let symbol = self.graph.add_symbol(symbol);
+        self.graph
+            .add_pop_symbol_node(id, symbol, is_definition)
+            .expect("no previous node for new id")
+    }
+
+    /// Adds a new _push symbol_ node.
+    pub fn push_symbol(&mut self, symbol: &str) -> Handle<Node> {
+        self.add_push_symbol(symbol, false)
+    }
+
+    /// Adds a new _push symbol_ node that is a reference.
+    pub fn reference(&mut self, symbol: &str) -> Handle<Node> {
+        self.add_push_symbol(symbol, true)
+    }
+
+    fn add_push_symbol(&mut self, symbol: &str, is_reference: bool) -> Handle<Node> {
+        let id = self.graph.new_node_id(self.file);
+        let symbol = self.graph.add_symbol(symbol);
+        self.graph
+            .add_push_symbol_node(id, symbol, is_reference)
+            .expect("no previous node for new id")
+    }
+
+    /// Adds a new _pop scoped symbol_ node.
+    pub fn pop_scoped_symbol(&mut self, symbol: &str, is_definition: bool) -> Handle<Node> {
+        let id = self.graph.new_node_id(self.file);
+        let symbol = self.graph.add_symbol(symbol);
+        self.graph
+            .add_pop_scoped_symbol_node(id, symbol, is_definition)
+            .expect("no previous node for new id")
+    }
+
+    /// Adds a new _push scoped symbol_ node.  The attached scope must be an exported scope node.
+    pub fn push_scoped_symbol(
+        &mut self,
+        symbol: &str,
+        scope: Handle<Node>,
+        is_reference: bool,
+    ) -> Handle<Node> {
+        assert!(
+            self.graph[scope].is_exported_scope(),
+            "attached scope must be an exported scope node"
+        );
+        let scope = self.graph[scope].id();
+        let id = self.graph.new_node_id(self.file);
+        let symbol = self.graph.add_symbol(symbol);
+        self.graph
+            .add_push_scoped_symbol_node(id, symbol, scope, is_reference)
+            .expect("no previous node for new id")
+    }
+
+    /// Adds a chain of _pop symbol_ nodes, starting with an edge from `from`, that pops the given
+    /// symbols in order.  Returns the last node of the chain, or `from` if there are no symbols.
+    pub fn pop_symbols<I>(&mut self, from: Handle<Node>, symbols: I) -> Handle<Node>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut node = from;
+        for symbol in symbols {
+            let pop = self.pop_symbol(symbol.as_ref());
+            self.edge(node, pop);
+            node = pop;
+        }
+        node
+    }
+
+    /// Adds a chain of _push symbol_ nodes, ending with an edge to `to`, that pushes the given
+    /// symbols such that the first symbol ends up on top of the symbol stack.  A path that follows
+    /// the chain will therefore look for the symbols in the given order.  Returns the first node of
+    /// the chain, or `to` if there are no symbols.
+    pub fn push_symbols<I>(&mut self, to: Handle<Node>, symbols: I) -> Handle<Node>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut node = to;
+        for symbol in symbols {
+            let push = self.push_symbol(symbol.as_ref());
+            self.edge(push, node);
+            node = push;
+        }
+        node
+    }
+
+    /// Adds an edge with precedence zero.  Edges from a node to itself are ignored.
+    pub fn edge(&mut self, source: Handle<Node>, sink: Handle<Node>) {
+        self.edge_with_precedence(source, sink, 0);
+    }
+
+    /// Adds an edge with the given precedence.  Edges from a node to itself are ignored.
+    pub fn edge_with_precedence(
+        &mut self,
+        source: Handle<Node>,
+        sink: Handle<Node>,
+        precedence: i32,
+    ) {
+        if source == sink {
+            return;
+        }
+        self.graph.add_edge(source, sink, precedence);
+    }
+
+    /// Sets the source span of a node.
+    pub fn span(&mut self, node: Handle<Node>, span: Span) {
+        self.graph.source_info_mut(node).span = span;
+    }
+
+    /// Adds a debug name to a node.
+    pub fn debug_name(&mut self, node: Handle<Node>, name: &str) {
+        self.debug_info(node, DEBUG_NAME_KEY, name);
+    }
+
+    /// Adds a debug info entry to a node.
+    pub fn debug_info(&mut self, node: Handle<Node>, key: &str, value: &str) {
+        let key = self.graph.add_string(key);
+        let value = self.graph.add_string(value);
+        self.graph.node_debug_info_mut(node).add(key, value);
+    }
+}
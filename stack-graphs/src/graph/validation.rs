@@ -0,0 +1,217 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Checks stack graphs for structural problems.
+//!
+//! A stack graph that is produced by a faulty construction rule is usually still a valid stack
+//! graph — it just doesn't resolve names the way you'd expect.  The validation pass in this module
+//! looks for a handful of patterns that almost always indicate such a problem:
+//!
+//!   - definitions that cannot be reached from the root node, any scope, or any reference
+//!   - push nodes without any outgoing edges, whose symbols can therefore never be resolved
+//!   - exported scopes that are not the attached scope of any _push scoped symbol_ node
+//!   - edges into the root node from pop nodes (including definitions) or _drop scopes_ nodes
+//!
+//! Validation only considers the structure of the graph, so some of the issues it reports can be
+//! legitimate.  In particular, an exported scope might be referenced by a _push scoped symbol_
+//! node in a file that is not part of the graph being validated.
+
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+use crate::arena::Handle;
+use crate::arena::HandleSet;
+use crate::graph::File;
+use crate::graph::Node;
+use crate::graph::StackGraph;
+
+/// A structural problem found in a stack graph.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ValidationIssue {
+    /// A definition that cannot be reached from the root node, any scope, or any reference.
+    UnreachableDefinition(Handle<Node>),
+    /// A push node without outgoing edges.
+    PushWithoutOutgoingEdges(Handle<Node>),
+    /// An exported scope that is not the attached scope of any _push scoped symbol_ node.
+    UnreferencedExportedScope(Handle<Node>),
+    /// An edge into the root node from a pop node (including definitions) or a _drop scopes_
+    /// node.  The handle is the source of the offending edge.
+    NonExportedRootEdge(Handle<Node>),
+}
+
+impl ValidationIssue {
+    /// Returns the node this issue is reported on.
+    pub fn node(&self) -> Handle<Node> {
+        match self {
+            Self::UnreachableDefinition(node) => *node,
+            Self::PushWithoutOutgoingEdges(node) => *node,
+            Self::UnreferencedExportedScope(node) => *node,
+            Self::NonExportedRootEdge(node) => *node,
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            Self::UnreachableDefinition(_) => "unreachable definition",
+            Self::PushWithoutOutgoingEdges(_) => "push node without outgoing edges",
+            Self::UnreferencedExportedScope(_) => {
+                "exported scope not referenced by any push scoped symbol node"
+            }
+            Self::NonExportedRootEdge(_) => "edge into root node from pop or drop scopes node",
+        }
+    }
+
+    /// Returns a displayable description of this issue, including the source span and debug
+    /// info of the node it is reported on.
+    pub fn display<'a>(&'a self, graph: &'a StackGraph) -> impl Display + 'a {
+        DisplayValidationIssue {
+            wrapped: self,
+            graph,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct DisplayValidationIssue<'a> {
+    wrapped: &'a ValidationIssue,
+    graph: &'a StackGraph,
+}
+
+impl<'a> Display for DisplayValidationIssue<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let node = self.wrapped.node();
+        write!(f, "{} {}", self.wrapped.message(), node.display(self.graph))?;
+        if let Some(source_info) = self.graph.source_info(node) {
+            if source_info.span != lsp_positions::Span::default() {
+                write!(
+                    f,
+                    " at {}:{}",
+                    source_info.span.start.line + 1,
+                    source_info.span.start.column.grapheme_offset + 1
+                )?;
+            }
+        }
+        if let Some(debug_info) = self.graph.node_debug_info(node) {
+            let mut entries = debug_info
+                .iter()
+                .map(|entry| {
+                    (
+                        &self.graph[entry.key],
+                        entry.value.display(self.graph).to_string(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            entries.sort();
+            let mut first = true;
+            for (key, value) in entries {
+                write!(f, "{}{} = {}", if first { " (" } else { ", " }, key, value)?;
+                first = false;
+            }
+            if !first {
+                write!(f, ")")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StackGraph {
+    /// Checks the nodes of all files in this stack graph for structural problems.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        self.validate_files(self.iter_files())
+    }
+
+    /// Checks the nodes of a file in this stack graph for structural problems.  Nodes from other
+    /// files are taken into account when determining reachability and references to exported
+    /// scopes, but issues are only reported for nodes in the given file.  Use
+    /// [`validate_files`][Self::validate_files] to check several files at once, which is much
+    /// faster than checking them one by one.
+    pub fn validate_file(&self, file: Handle<File>) -> Vec<ValidationIssue> {
+        self.validate_files(std::iter::once(file))
+    }
+
+    /// Checks the nodes of the given files in this stack graph for structural problems.
+    /// Reachability and references to exported scopes are determined once for the whole graph,
+    /// and issues are only reported for nodes in the given files.
+    pub fn validate_files<I>(&self, files: I) -> Vec<ValidationIssue>
+    where
+        I: IntoIterator<Item = Handle<File>>,
+    {
+        let reachable = self.reachable_nodes();
+        let referenced_scopes = self.referenced_scopes();
+        let mut issues = Vec::new();
+        for file in files {
+            self.validate_file_into(file, &reachable, &referenced_scopes, &mut issues);
+        }
+        issues
+    }
+
+    fn validate_file_into(
+        &self,
+        file: Handle<File>,
+        reachable: &HandleSet<Node>,
+        referenced_scopes: &HashSet<Handle<Node>>,
+        issues: &mut Vec<ValidationIssue>,
+    ) {
+        for handle in self.nodes_for_file(file) {
+            let node = &self[handle];
+            if node.is_definition() && !reachable.contains(handle) {
+                issues.push(ValidationIssue::UnreachableDefinition(handle));
+            }
+            if matches!(node, Node::PushSymbol(_) | Node::PushScopedSymbol(_))
+                && self.outgoing_edges(handle).next().is_none()
+            {
+                issues.push(ValidationIssue::PushWithoutOutgoingEdges(handle));
+            }
+            if node.is_exported_scope() && !referenced_scopes.contains(&handle) {
+                issues.push(ValidationIssue::UnreferencedExportedScope(handle));
+            }
+            if matches!(
+                node,
+                Node::PopSymbol(_) | Node::PopScopedSymbol(_) | Node::DropScopes(_)
+            ) && self
+                .outgoing_edges(handle)
+                .any(|edge| self[edge.sink].is_root())
+            {
+                issues.push(ValidationIssue::NonExportedRootEdge(handle));
+            }
+        }
+    }
+
+    /// Returns all nodes that can be reached from the root node, a scope, or a reference.  A
+    /// definition that is only reachable from an internal scope is still considered reachable,
+    /// since an unused local definition is a property of the analyzed code, not of the rules.
+    fn reachable_nodes(&self) -> HandleSet<Node> {
+        let mut reachable = HandleSet::new();
+        let mut queue = VecDeque::new();
+        for handle in self.iter_nodes() {
+            let node = &self[handle];
+            if node.is_root() || matches!(node, Node::Scope(_)) || node.is_reference() {
+                reachable.add(handle);
+                queue.push_back(handle);
+            }
+        }
+        while let Some(handle) = queue.pop_front() {
+            for edge in self.outgoing_edges(handle) {
+                if !reachable.contains(edge.sink) {
+                    reachable.add(edge.sink);
+                    queue.push_back(edge.sink);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Returns all nodes that are the attached scope of a _push scoped symbol_ node.
+    fn referenced_scopes(&self) -> HashSet<Handle<Node>> {
+        self.iter_nodes()
+            .filter_map(|handle| self[handle].scope())
+            .filter_map(|scope| self.node_for_id(scope))
+            .collect()
+    }
+}
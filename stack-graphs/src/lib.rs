@@ -65,10 +65,12 @@ pub mod c;
 pub mod cycles;
 #[macro_use]
 mod debugging;
+pub mod diff;
 pub mod graph;
 pub mod partial;
 pub mod paths;
 pub mod serde;
+pub mod stats;
 pub mod stitching;
 #[cfg(feature = "storage")]
 pub mod storage;
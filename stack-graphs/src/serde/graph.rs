@@ -53,6 +53,22 @@ impl StackGraph {
         }
     }
 
+    /// Renames a file in this graph, including the IDs of all nodes that belong to it.
+    pub fn rename_file(&mut self, from: &str, to: &str) {
+        for file in &mut self.files.data {
+            if file == from {
+                *file = to.to_string();
+            }
+        }
+        for node in &mut self.nodes.data {
+            node.rename_file(from, to);
+        }
+        for edge in &mut self.edges.data {
+            edge.source.rename_file(from, to);
+            edge.sink.rename_file(from, to);
+        }
+    }
+
     pub fn load_into(&self, graph: &mut crate::graph::StackGraph) -> Result<(), Error> {
         self.load_files(graph)?;
         self.load_nodes(graph)?;
@@ -293,6 +309,22 @@ pub enum Node {
 }
 
 impl Node {
+    fn rename_file(&mut self, from: &str, to: &str) {
+        match self {
+            Self::PushScopedSymbol { id, scope, .. } => {
+                id.rename_file(from, to);
+                scope.rename_file(from, to);
+            }
+            Self::DropScopes { id, .. }
+            | Self::JumpToScope { id, .. }
+            | Self::PopScopedSymbol { id, .. }
+            | Self::PopSymbol { id, .. }
+            | Self::PushSymbol { id, .. }
+            | Self::Root { id, .. }
+            | Self::Scope { id, .. } => id.rename_file(from, to),
+        }
+    }
+
     fn source_info(&self) -> Option<&SourceInfo> {
         match self {
             Self::DropScopes { source_info, .. } => source_info,
@@ -373,6 +405,12 @@ impl NodeID {
         }
     }
 
+    pub(crate) fn rename_file(&mut self, from: &str, to: &str) {
+        if self.file.as_deref() == Some(from) {
+            self.file = Some(to.to_string());
+        }
+    }
+
     pub fn to_node_id(
         &self,
         graph: &crate::graph::StackGraph,
@@ -24,6 +24,19 @@ pub struct PartialPath {
 }
 
 impl PartialPath {
+    /// Renames a file in this path, including the IDs of all nodes that belong to it.
+    pub fn rename_file(&mut self, from: &str, to: &str) {
+        self.start_node.rename_file(from, to);
+        self.end_node.rename_file(from, to);
+        self.symbol_stack_precondition.rename_file(from, to);
+        self.symbol_stack_postcondition.rename_file(from, to);
+        self.scope_stack_precondition.rename_file(from, to);
+        self.scope_stack_postcondition.rename_file(from, to);
+        for edge in &mut self.edges.edges {
+            edge.source.rename_file(from, to);
+        }
+    }
+
     pub fn from_partial_path(
         graph: &crate::graph::StackGraph,
         partials: &mut PartialPaths,
@@ -94,6 +107,12 @@ pub struct PartialScopeStack {
 }
 
 impl PartialScopeStack {
+    fn rename_file(&mut self, from: &str, to: &str) {
+        for scope in &mut self.scopes {
+            scope.rename_file(from, to);
+        }
+    }
+
     pub fn from_partial_scope_stack(
         graph: &crate::graph::StackGraph,
         partials: &mut PartialPaths,
@@ -164,6 +183,14 @@ pub struct PartialSymbolStack {
 }
 
 impl PartialSymbolStack {
+    fn rename_file(&mut self, from: &str, to: &str) {
+        for symbol in &mut self.symbols {
+            if let Some(scopes) = &mut symbol.scopes {
+                scopes.rename_file(from, to);
+            }
+        }
+    }
+
     pub fn from_partial_symbol_stack(
         graph: &crate::graph::StackGraph,
         partials: &mut PartialPaths,
@@ -0,0 +1,327 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Collects statistics about the shape of stack graphs and partial path databases.
+//!
+//! Statistics are collected into [`GraphStats`][] and [`PartialPathStats`][] instances, which can
+//! be combined to aggregate statistics over multiple graphs or databases.  This makes it possible
+//! to collect statistics per file, and to combine them into totals afterwards.
+//!
+//! [`GraphStats`]: struct.GraphStats.html
+//! [`PartialPathStats`]: struct.PartialPathStats.html
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::ops::AddAssign;
+
+use itertools::Itertools;
+
+use crate::arena::Handle;
+use crate::graph::File;
+use crate::graph::Node;
+use crate::graph::StackGraph;
+use crate::partial::PartialPath;
+use crate::stitching::Database;
+
+//-------------------------------------------------------------------------------------------------
+// Frequency distributions
+
+/// The number of times each distinct value was recorded.
+#[derive(Clone, Debug)]
+pub struct FrequencyDistribution<X>
+where
+    X: Eq + Hash,
+{
+    values: HashMap<X, usize>,
+    total: usize,
+}
+
+impl<X: Eq + Hash> FrequencyDistribution<X> {
+    /// Records an occurrence of the given value.
+    pub fn record(&mut self, value: X) {
+        *self.values.entry(value).or_default() += 1;
+        self.total += 1;
+    }
+
+    /// Returns the number of times the given value was recorded.
+    pub fn count(&self, value: &X) -> usize {
+        self.values.get(value).copied().unwrap_or(0)
+    }
+
+    /// Returns the total number of recorded occurrences.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns the number of distinct recorded values.
+    pub fn unique(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns an iterator over all distinct recorded values and their counts, in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (&X, usize)> {
+        self.values.iter().map(|(value, count)| (value, *count))
+    }
+}
+
+impl<X: Eq + Hash + Ord> FrequencyDistribution<X> {
+    /// Returns all distinct recorded values and their counts, ordered by value.
+    pub fn sorted(&self) -> Vec<(&X, usize)> {
+        self.iter().sorted_by(|(a, _), (b, _)| a.cmp(b)).collect()
+    }
+}
+
+impl<X: Eq + Hash> Default for FrequencyDistribution<X> {
+    fn default() -> Self {
+        Self {
+            values: HashMap::default(),
+            total: 0,
+        }
+    }
+}
+
+impl<X: Eq + Hash> AddAssign for FrequencyDistribution<X> {
+    fn add_assign(&mut self, rhs: Self) {
+        for (value, count) in rhs.values {
+            *self.values.entry(value).or_default() += count;
+        }
+        self.total += rhs.total;
+    }
+}
+
+impl<X: Eq + Hash + Clone> AddAssign<&Self> for FrequencyDistribution<X> {
+    fn add_assign(&mut self, rhs: &Self) {
+        for (value, count) in &rhs.values {
+            *self.values.entry(value.clone()).or_default() += count;
+        }
+        self.total += rhs.total;
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Node kinds
+
+/// The kind of a stack graph node, corresponding to the variants of [`Node`][].
+///
+/// [`Node`]: ../graph/enum.Node.html
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum NodeKind {
+    DropScopes,
+    JumpTo,
+    PopScopedSymbol,
+    PopSymbol,
+    PushScopedSymbol,
+    PushSymbol,
+    Root,
+    Scope,
+}
+
+impl From<&Node> for NodeKind {
+    fn from(node: &Node) -> Self {
+        match node {
+            Node::DropScopes(_) => Self::DropScopes,
+            Node::JumpTo(_) => Self::JumpTo,
+            Node::PopScopedSymbol(_) => Self::PopScopedSymbol,
+            Node::PopSymbol(_) => Self::PopSymbol,
+            Node::PushScopedSymbol(_) => Self::PushScopedSymbol,
+            Node::PushSymbol(_) => Self::PushSymbol,
+            Node::Root(_) => Self::Root,
+            Node::Scope(_) => Self::Scope,
+        }
+    }
+}
+
+impl Display for NodeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::DropScopes => write!(f, "drop_scopes"),
+            Self::JumpTo => write!(f, "jump_to"),
+            Self::PopScopedSymbol => write!(f, "pop_scoped_symbol"),
+            Self::PopSymbol => write!(f, "pop_symbol"),
+            Self::PushScopedSymbol => write!(f, "push_scoped_symbol"),
+            Self::PushSymbol => write!(f, "push_symbol"),
+            Self::Root => write!(f, "root"),
+            Self::Scope => write!(f, "scope"),
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Graph statistics
+
+/// Statistics about the nodes and edges of a stack graph.
+#[derive(Clone, Debug, Default)]
+pub struct GraphStats {
+    /// The number of files.
+    pub files: usize,
+    /// The number of nodes of each kind.
+    pub nodes: FrequencyDistribution<NodeKind>,
+    /// The number of edges.
+    pub edges: usize,
+    /// The number of nodes with each out-degree.
+    pub out_degrees: FrequencyDistribution<usize>,
+    /// The number of push and pop nodes using each symbol.
+    pub symbols: FrequencyDistribution<String>,
+}
+
+impl GraphStats {
+    fn add_node(&mut self, graph: &StackGraph, handle: Handle<Node>) {
+        let node = &graph[handle];
+        self.nodes.record(node.into());
+        let out_degree = graph.outgoing_edges(handle).count();
+        self.edges += out_degree;
+        self.out_degrees.record(out_degree);
+        if let Some(symbol) = node.symbol() {
+            self.symbols.record(graph[symbol].to_string());
+        }
+    }
+}
+
+impl AddAssign for GraphStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.files += rhs.files;
+        self.nodes += rhs.nodes;
+        self.edges += rhs.edges;
+        self.out_degrees += rhs.out_degrees;
+        self.symbols += rhs.symbols;
+    }
+}
+
+impl AddAssign<&Self> for GraphStats {
+    fn add_assign(&mut self, rhs: &Self) {
+        self.files += rhs.files;
+        self.nodes += &rhs.nodes;
+        self.edges += rhs.edges;
+        self.out_degrees += &rhs.out_degrees;
+        self.symbols += &rhs.symbols;
+    }
+}
+
+impl Display for GraphStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "files: {}", self.files)?;
+        writeln!(f, "nodes: {}", self.nodes.total())?;
+        for (kind, count) in self.nodes.sorted() {
+            writeln!(f, "  {}: {}", kind, count)?;
+        }
+        writeln!(f, "edges: {}", self.edges)?;
+        writeln!(f, "nodes by out-degree:")?;
+        for (degree, count) in self.out_degrees.sorted() {
+            writeln!(f, "  {}: {}", degree, count)?;
+        }
+        write!(
+            f,
+            "symbols: {} distinct, {} uses",
+            self.symbols.unique(),
+            self.symbols.total()
+        )
+    }
+}
+
+impl StackGraph {
+    /// Returns statistics about all nodes and edges in this stack graph, including the singleton
+    /// _root_ and _jump to scope_ nodes.
+    pub fn stats(&self) -> GraphStats {
+        let mut stats = GraphStats {
+            files: self.iter_files().count(),
+            ..GraphStats::default()
+        };
+        for handle in self.iter_nodes() {
+            stats.add_node(self, handle);
+        }
+        stats
+    }
+
+    /// Returns statistics about the nodes in a file, and the edges that start at them.
+    pub fn file_stats(&self, file: Handle<File>) -> GraphStats {
+        let mut stats = GraphStats {
+            files: 1,
+            ..GraphStats::default()
+        };
+        for handle in self.nodes_for_file(file) {
+            stats.add_node(self, handle);
+        }
+        stats
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Partial path statistics
+
+/// Statistics about a set of partial paths.
+#[derive(Clone, Debug, Default)]
+pub struct PartialPathStats {
+    /// The number of partial paths starting at each kind of node.
+    pub start_nodes: FrequencyDistribution<NodeKind>,
+    /// The number of partial paths ending at each kind of node.
+    pub end_nodes: FrequencyDistribution<NodeKind>,
+    /// The number of partial paths with each symbol stack precondition length.
+    pub precondition_lengths: FrequencyDistribution<usize>,
+}
+
+impl PartialPathStats {
+    /// Returns the number of partial paths.
+    pub fn count(&self) -> usize {
+        self.start_nodes.total()
+    }
+
+    /// Adds a partial path to these statistics.
+    pub fn add_partial_path(&mut self, graph: &StackGraph, path: &PartialPath) {
+        self.start_nodes.record((&graph[path.start_node]).into());
+        self.end_nodes.record((&graph[path.end_node]).into());
+        self.precondition_lengths
+            .record(path.symbol_stack_precondition.len());
+    }
+}
+
+impl AddAssign for PartialPathStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.start_nodes += rhs.start_nodes;
+        self.end_nodes += rhs.end_nodes;
+        self.precondition_lengths += rhs.precondition_lengths;
+    }
+}
+
+impl AddAssign<&Self> for PartialPathStats {
+    fn add_assign(&mut self, rhs: &Self) {
+        self.start_nodes += &rhs.start_nodes;
+        self.end_nodes += &rhs.end_nodes;
+        self.precondition_lengths += &rhs.precondition_lengths;
+    }
+}
+
+impl Display for PartialPathStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "partial paths: {}", self.count())?;
+        writeln!(f, "partial paths by start node:")?;
+        for (kind, count) in self.start_nodes.sorted() {
+            writeln!(f, "  {}: {}", kind, count)?;
+        }
+        writeln!(f, "partial paths by end node:")?;
+        for (kind, count) in self.end_nodes.sorted() {
+            writeln!(f, "  {}: {}", kind, count)?;
+        }
+        write!(f, "partial paths by precondition length:")?;
+        for (length, count) in self.precondition_lengths.sorted() {
+            write!(f, "\n  {}: {}", length, count)?;
+        }
+        Ok(())
+    }
+}
+
+impl Database {
+    /// Returns statistics about the partial paths in this database.
+    pub fn stats(&self, graph: &StackGraph) -> PartialPathStats {
+        let mut stats = PartialPathStats::default();
+        for handle in self.iter_partial_paths() {
+            stats.add_partial_path(graph, &self[handle]);
+        }
+        stats
+    }
+}
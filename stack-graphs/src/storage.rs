@@ -32,17 +32,22 @@ use crate::stitching::ForwardCandidates;
 use crate::CancellationError;
 use crate::CancellationFlag;
 
-const VERSION: usize = 5;
+pub mod artifact;
+
+use self::artifact::FileArtifact;
+
+const VERSION: usize = 6;
 
 const SCHEMA: &str = r#"
         CREATE TABLE metadata (
             version INTEGER NOT NULL
         ) STRICT;
         CREATE TABLE graphs (
-            file   TEXT PRIMARY KEY,
-            tag    TEXT NOT NULL,
-            error  TEXT,
-            value  BLOB NOT NULL
+            file     TEXT PRIMARY KEY,
+            tag      TEXT NOT NULL,
+            error    TEXT,
+            warnings TEXT,
+            value    BLOB NOT NULL
         ) STRICT;
         CREATE TABLE file_paths (
             file     TEXT NOT NULL,
@@ -80,6 +85,10 @@ pub enum StorageError {
     IncorrectVersion(usize),
     #[error("database does not exist {0}")]
     MissingDatabase(String),
+    #[error("invalid artifact")]
+    InvalidArtifact,
+    #[error("unsupported artifact version {0}")]
+    IncorrectArtifactVersion(u32),
     #[error(transparent)]
     Rusqlite(#[from] rusqlite::Error),
     #[error(transparent)]
@@ -102,21 +111,35 @@ impl From<CancellationError> for StorageError {
 pub enum FileStatus {
     Missing,
     Indexed,
+    IndexedWithWarnings(String),
     Error(String),
 }
 
-impl<'a> From<ValueRef<'a>> for FileStatus {
-    fn from(value: ValueRef<'a>) -> Self {
-        match value {
-            ValueRef::Null => Self::Indexed,
-            ValueRef::Text(error) => Self::Error(
-                std::str::from_utf8(error)
-                    .expect("invalid error encoding in database")
-                    .to_string(),
-            ),
+impl FileStatus {
+    /// Creates a file status from the values of the error and warnings columns.
+    fn from_columns(error: ValueRef, warnings: ValueRef) -> Self {
+        match (error, warnings) {
+            (ValueRef::Null, ValueRef::Null) => Self::Indexed,
+            (ValueRef::Null, ValueRef::Text(warnings)) => {
+                Self::IndexedWithWarnings(text_from_database(warnings))
+            }
+            (ValueRef::Text(error), _) => Self::Error(text_from_database(error)),
             _ => panic!("invalid value type in database"),
         }
     }
+
+    fn from_row(row: &rusqlite::Row, index: usize) -> rusqlite::Result<Self> {
+        Ok(Self::from_columns(
+            row.get_ref(index)?,
+            row.get_ref(index + 1)?,
+        ))
+    }
+}
+
+fn text_from_database(text: &[u8]) -> String {
+    std::str::from_utf8(text)
+        .expect("invalid text encoding in database")
+        .to_string()
 }
 
 /// A file entry in the database.
@@ -126,7 +149,7 @@ pub struct FileEntry {
     pub status: FileStatus,
 }
 
-/// An iterator over a query returning rows with (path,tag,error) tuples.
+/// An iterator over a query returning rows with (path,tag,error,warnings) tuples.
 pub struct Files<'a, P: Params>(Statement<'a>, P);
 
 impl<'a, P: Params + Clone> Files<'a, P> {
@@ -135,7 +158,7 @@ impl<'a, P: Params + Clone> Files<'a, P> {
             Ok(FileEntry {
                 path: PathBuf::from(r.get::<_, String>(0)?),
                 tag: r.get::<_, String>(1)?,
-                status: r.get_ref(2)?.into(),
+                status: FileStatus::from_row(r, 2)?,
             })
         })?;
         let entries = entries.map(|r| -> Result<FileEntry> { Ok(r?) });
@@ -305,13 +328,30 @@ impl SQLiteWriter {
         partials: &mut PartialPaths,
         paths: IP,
     ) -> Result<()>
+    where
+        IP: IntoIterator<Item = &'a PartialPath>,
+    {
+        self.store_result_with_warnings_for_file(graph, file, tag, None, partials, paths)
+    }
+
+    /// Store the result of a file index, indicating that the file was indexed with warnings if
+    /// any are given.
+    pub fn store_result_with_warnings_for_file<'a, IP>(
+        &mut self,
+        graph: &StackGraph,
+        file: Handle<File>,
+        tag: &str,
+        warnings: Option<&str>,
+        partials: &mut PartialPaths,
+        paths: IP,
+    ) -> Result<()>
     where
         IP: IntoIterator<Item = &'a PartialPath>,
     {
         let path = Path::new(graph[file].name());
         let tx = self.conn.transaction()?;
         Self::clean_file_inner(&tx, path)?;
-        Self::store_graph_for_file_inner(&tx, graph, file, tag)?;
+        Self::store_graph_for_file_inner(&tx, graph, file, tag, warnings)?;
         Self::store_partial_paths_for_file_inner(&tx, graph, file, partials, paths)?;
         tx.commit()?;
         Ok(())
@@ -325,14 +365,16 @@ impl SQLiteWriter {
         graph: &StackGraph,
         file: Handle<File>,
         tag: &str,
+        warnings: Option<&str>,
     ) -> Result<()> {
         let file_str = graph[file].name();
         copious_debugging!("--> Store graph for {}", file_str);
-        let mut stmt =
-            conn.prepare_cached("INSERT INTO graphs (file, tag, value) VALUES (?, ?, ?)")?;
+        let mut stmt = conn.prepare_cached(
+            "INSERT INTO graphs (file, tag, warnings, value) VALUES (?, ?, ?, ?)",
+        )?;
         let graph = serde::StackGraph::from_graph_filter(graph, &FileFilter(file));
         let serialized = bincode::encode_to_vec(&graph, BINCODE_CONFIG)?;
-        stmt.execute((file_str, tag, &serialized))?;
+        stmt.execute((file_str, tag, warnings, &serialized))?;
         Ok(())
     }
 
@@ -401,6 +443,85 @@ impl SQLiteWriter {
         Ok(())
     }
 
+    /// Store the contents of a file artifact, replacing any existing data for the file.
+    pub fn import_file_artifact(&mut self, artifact: &FileArtifact) -> Result<()> {
+        let path = Path::new(&artifact.file);
+        if let Some(error) = &artifact.error {
+            let tx = self.conn.transaction()?;
+            Self::clean_file_inner(&tx, path)?;
+            Self::store_error_for_file_inner(&tx, path, &artifact.tag, error)?;
+            tx.commit()?;
+            return Ok(());
+        }
+        let mut graph = StackGraph::new();
+        artifact.graph.load_into(&mut graph)?;
+        let file = graph
+            .get_file(&artifact.file)
+            .ok_or_else(|| serde::Error::FileNotFound(artifact.file.clone()))?;
+        let mut partials = PartialPaths::new();
+        let paths = artifact
+            .paths
+            .iter()
+            .map(|path| path.to_partial_path(&mut graph, &mut partials))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        self.store_result_with_warnings_for_file(
+            &graph,
+            file,
+            &artifact.tag,
+            artifact.warnings.as_deref(),
+            &mut partials,
+            &paths,
+        )
+    }
+
+    /// Create an artifact from the stored data of a file.  Returns `None` if the file is not
+    /// in the database.
+    pub fn export_file_artifact(&mut self, file: &str) -> Result<Option<FileArtifact>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT tag, error, warnings, value FROM graphs WHERE file = ?")?;
+        let row = stmt
+            .query_row([file], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Vec<u8>>(3)?,
+                ))
+            })
+            .optional()?;
+        let (tag, error, warnings, value) = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        if let Some(error) = error {
+            return Ok(Some(FileArtifact::from_error(file, &tag, &error)));
+        }
+        let (graph, _): (serde::StackGraph, usize) =
+            bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+        let mut paths = Vec::new();
+        for query in [
+            "SELECT value FROM file_paths WHERE file = ?",
+            "SELECT value FROM root_paths WHERE file = ?",
+        ] {
+            let mut stmt = self.conn.prepare_cached(query)?;
+            let values = stmt.query_map([file], |row| row.get::<_, Vec<u8>>(0))?;
+            for value in values {
+                let (path, _): (serde::PartialPath, usize) =
+                    bincode::decode_from_slice(&value?, BINCODE_CONFIG)?;
+                paths.push(path);
+            }
+        }
+        Ok(Some(FileArtifact {
+            file: file.to_string(),
+            tag,
+            error: None,
+            warnings,
+            graph,
+            paths,
+        }))
+    }
+
     /// Get the file's status in the database. If a tag is provided, it must match or the file
     /// is reported missing.
     pub fn status_for_file(&mut self, file: &str, tag: Option<&str>) -> Result<FileStatus> {
@@ -490,7 +611,7 @@ impl SQLiteReader {
     /// Returns a [`Files`][] value that can be used to iterate over all files in the database.
     pub fn list_all<'a>(&'a mut self) -> Result<Files<'a, ()>> {
         self.conn
-            .prepare("SELECT file, tag, error FROM graphs")
+            .prepare("SELECT file, tag, error, warnings FROM graphs")
             .map(|stmt| Files(stmt, ()))
             .map_err(|e| e.into())
     }
@@ -509,9 +630,11 @@ impl SQLiteReader {
         file_or_directory: &Path,
     ) -> Result<Files<'a, [String; 1]>> {
         let file_or_directory = file_or_directory.to_string_lossy().to_string();
-        conn.prepare("SELECT file, tag, error FROM graphs WHERE path_descendant_of(file, ?)")
-            .map(|stmt| Files(stmt, [file_or_directory]))
-            .map_err(|e| e.into())
+        conn.prepare(
+            "SELECT file, tag, error, warnings FROM graphs WHERE path_descendant_of(file, ?)",
+        )
+        .map(|stmt| Files(stmt, [file_or_directory]))
+        .map_err(|e| e.into())
     }
 
     /// Ensure the graph for the given file is loaded.
@@ -781,13 +904,13 @@ fn status_for_file<T: AsRef<str>>(
 ) -> Result<FileStatus> {
     let result = if let Some(tag) = tag {
         let mut stmt =
-            conn.prepare_cached("SELECT error FROM graphs WHERE file = ? AND tag = ?")?;
-        stmt.query_row([file, tag.as_ref()], |r| r.get_ref(0).map(FileStatus::from))
+            conn.prepare_cached("SELECT error, warnings FROM graphs WHERE file = ? AND tag = ?")?;
+        stmt.query_row([file, tag.as_ref()], |r| FileStatus::from_row(r, 0))
             .optional()?
             .unwrap_or(FileStatus::Missing)
     } else {
-        let mut stmt = conn.prepare_cached("SELECT status FROM graphs WHERE file = ?")?;
-        stmt.query_row([file], |r| r.get_ref(0).map(FileStatus::from))
+        let mut stmt = conn.prepare_cached("SELECT error, warnings FROM graphs WHERE file = ?")?;
+        stmt.query_row([file], |r| FileStatus::from_row(r, 0))
             .optional()?
             .unwrap_or(FileStatus::Missing)
     };
@@ -0,0 +1,138 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines a portable artifact format for the indexing result of a single file.
+//!
+//! A [`FileArtifact`][] contains everything that is stored in a database for a file: the file's
+//! stack graph, its minimal set of partial paths, the tag of the indexed content, and the indexing
+//! error or warnings, if any.  Artifacts can be created on different machines and imported into a single
+//! database later.
+//!
+//! Artifacts are encoded with bincode, preceded by a header that identifies the format and its
+//! version.  Artifacts with a different version cannot be decoded, and must be recreated.
+//!
+//! [`FileArtifact`]: struct.FileArtifact.html
+
+use crate::arena::Handle;
+use crate::graph::File;
+use crate::graph::StackGraph;
+use crate::partial::PartialPath;
+use crate::partial::PartialPaths;
+use crate::serde;
+use crate::serde::FileFilter;
+
+use super::Result;
+use super::StorageError;
+use super::BINCODE_CONFIG;
+
+/// The file extension that is used for artifact files.
+pub const ARTIFACT_EXTENSION: &str = "sga";
+
+/// The version of the artifact format.  Artifacts with a different version are rejected.
+pub const ARTIFACT_VERSION: u32 = 2;
+
+const ARTIFACT_MAGIC: [u8; 4] = *b"SGFA";
+
+#[derive(bincode::Encode, bincode::Decode)]
+struct Header {
+    magic: [u8; 4],
+    version: u32,
+}
+
+/// The self-contained indexing result of a single file.
+#[derive(Clone, Debug, Eq, PartialEq, bincode::Encode, bincode::Decode)]
+pub struct FileArtifact {
+    /// The name of the file.
+    pub file: String,
+    /// The tag of the indexed file content.
+    pub tag: String,
+    /// The error that occurred while indexing the file, if any.
+    pub error: Option<String>,
+    /// The warnings that occurred while indexing the file, if any.
+    pub warnings: Option<String>,
+    /// The stack graph of the file.  Empty if indexing failed.
+    pub graph: serde::StackGraph,
+    /// The minimal set of partial paths of the file.  Empty if indexing failed.
+    pub paths: Vec<serde::PartialPath>,
+}
+
+impl FileArtifact {
+    /// Creates an artifact for a successfully indexed file.  All paths must start in the file or
+    /// at the root node.
+    pub fn from_result<'a, IP>(
+        graph: &StackGraph,
+        file: Handle<File>,
+        tag: &str,
+        partials: &mut PartialPaths,
+        paths: IP,
+    ) -> Self
+    where
+        IP: IntoIterator<Item = &'a PartialPath>,
+    {
+        Self {
+            file: graph[file].name().to_string(),
+            tag: tag.to_string(),
+            error: None,
+            warnings: None,
+            graph: serde::StackGraph::from_graph_filter(graph, &FileFilter(file)),
+            paths: paths
+                .into_iter()
+                .map(|path| serde::PartialPath::from_partial_path(graph, partials, path))
+                .collect(),
+        }
+    }
+
+    /// Creates an artifact for a file that failed to index.
+    pub fn from_error(file: &str, tag: &str, error: &str) -> Self {
+        Self {
+            file: file.to_string(),
+            tag: tag.to_string(),
+            error: Some(error.to_string()),
+            warnings: None,
+            graph: serde::StackGraph::default(),
+            paths: Vec::new(),
+        }
+    }
+
+    /// Renames the file of this artifact, for example to make it relative to a source root before
+    /// it is shared, or absolute again before it is imported.
+    pub fn rename_file(&mut self, file: &str) {
+        self.graph.rename_file(&self.file, file);
+        for path in &mut self.paths {
+            path.rename_file(&self.file, file);
+        }
+        self.file = file.to_string();
+    }
+
+    /// Encodes the artifact, including the versioned header.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let header = Header {
+            magic: ARTIFACT_MAGIC,
+            version: ARTIFACT_VERSION,
+        };
+        let mut bytes = bincode::encode_to_vec(&header, BINCODE_CONFIG)?;
+        bytes.extend(bincode::encode_to_vec(self, BINCODE_CONFIG)?);
+        Ok(bytes)
+    }
+
+    /// Decodes an artifact.  An error is returned if the header is invalid, or if the artifact
+    /// version is not supported.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let (header, header_len): (Header, usize) =
+            bincode::decode_from_slice(bytes, BINCODE_CONFIG)
+                .map_err(|_| StorageError::InvalidArtifact)?;
+        if header.magic != ARTIFACT_MAGIC {
+            return Err(StorageError::InvalidArtifact);
+        }
+        if header.version != ARTIFACT_VERSION {
+            return Err(StorageError::IncorrectArtifactVersion(header.version));
+        }
+        let (artifact, _): (Self, usize) =
+            bincode::decode_from_slice(&bytes[header_len..], BINCODE_CONFIG)?;
+        Ok(artifact)
+    }
+}
@@ -0,0 +1,121 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::collections::BTreeSet;
+
+use stack_graphs::graph::builder::FileGraphBuilder;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::GraphEdgeCandidates;
+use stack_graphs::NoCancellation;
+
+fn resolve_references(graph: &StackGraph) -> BTreeSet<String> {
+    let mut paths = PartialPaths::new();
+    let mut results = BTreeSet::new();
+    let references = graph
+        .iter_nodes()
+        .filter(|handle| graph[*handle].is_reference());
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut GraphEdgeCandidates::new(graph, &mut paths, None),
+        references,
+        &NoCancellation,
+        |graph, paths, path| {
+            results.insert(path.display(graph, paths).to_string());
+        },
+    )
+    .expect("should never be cancelled");
+    results
+}
+
+#[test]
+fn can_build_qualified_name_chains() {
+    let mut graph = StackGraph::new();
+
+    let lib = graph.get_or_create_file("lib.py");
+    let mut builder = FileGraphBuilder::new(&mut graph, lib);
+    let root = builder.root();
+    let module = builder.pop_symbols(root, ["pkg", "lib"]);
+    builder.debug_name(module, "module");
+    let def = builder.definition("x");
+    builder.edge(module, def);
+
+    let main = graph.get_or_create_file("main.py");
+    let mut builder = FileGraphBuilder::new(&mut graph, main);
+    let root = builder.root();
+    let module = builder.push_symbols(root, ["pkg", "lib"]);
+    let reference = builder.reference("x");
+    builder.edge(reference, module);
+
+    assert_eq!(graph.nodes_for_file(lib).count(), 3);
+    assert_eq!(graph.nodes_for_file(main).count(), 3);
+    assert_eq!(
+        resolve_references(&graph),
+        BTreeSet::from([
+            "<> () [main.py(2) reference x] -> [lib.py(2) definition x] <> ()".to_string()
+        ])
+    );
+}
+
+#[test]
+fn can_build_scoped_symbols() {
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let mut builder = FileGraphBuilder::new(&mut graph, file);
+    let root = builder.root();
+    let jump_to = builder.jump_to();
+
+    // def f(): return x
+    let f = builder.pop_scoped_symbol("f", true);
+    builder.edge(root, f);
+    let result = builder.pop_symbol("()");
+    builder.edge(f, result);
+    let drop = builder.drop_scopes();
+    builder.edge(result, drop);
+    let body = builder.scope();
+    builder.edge(drop, body);
+    builder.edge(body, jump_to);
+    builder.edge(body, body);
+
+    // x = 1
+    let x = builder.definition("x");
+    builder.edge(root, x);
+
+    // f()
+    let args = builder.exported_scope();
+    let call = builder.push_scoped_symbol("f", args, false);
+    builder.edge(call, root);
+    let reference = builder.reference("()");
+    builder.edge(reference, call);
+
+    assert!(graph.outgoing_edges(body).all(|e| e.sink != body));
+    assert!(graph[call].scope() == Some(graph[args].id()));
+}
+
+#[test]
+fn can_add_debug_info() {
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let mut builder = FileGraphBuilder::new(&mut graph, file);
+    let def = builder.definition("x");
+    builder.debug_name(def, "def_x");
+    builder.debug_info(def, "kind", "variable");
+
+    let debug_info = graph
+        .node_debug_info(def)
+        .expect("debug info should exist")
+        .iter()
+        .map(|e| (graph[e.key].to_string(), graph[e.value].to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        debug_info,
+        vec![
+            ("name".to_string(), "def_x".to_string()),
+            ("kind".to_string(), "variable".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,159 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use lsp_positions::Offset;
+use lsp_positions::Position;
+use lsp_positions::Span;
+use stack_graphs::diff::ChangedEdge;
+use stack_graphs::diff::ChangedNode;
+use stack_graphs::diff::NodeChange;
+use stack_graphs::graph::Edge;
+use stack_graphs::graph::StackGraph;
+
+use crate::test_graphs;
+use crate::test_graphs::CreateStackGraph;
+
+fn span(line: usize, column: usize) -> Span {
+    let position = Position {
+        line,
+        column: Offset {
+            utf8_offset: column,
+            utf16_offset: column,
+            grapheme_offset: column,
+        },
+        containing_line: 0..0,
+        trimmed_line: 0..0,
+    };
+    Span {
+        start: position.clone(),
+        end: position,
+    }
+}
+
+#[test]
+fn identical_graphs_have_no_differences() {
+    let left = test_graphs::simple::new();
+    let right = test_graphs::simple::new();
+    assert!(left.diff(&right).is_empty());
+}
+
+#[test]
+fn can_detect_added_and_removed_nodes() {
+    let mut left = StackGraph::new();
+    let file = left.file("test.py");
+    let sym_x = left.symbol("x");
+    let left_x = left.definition(file, 1, sym_x);
+
+    let mut right = StackGraph::new();
+    let file = right.file("test.py");
+    let sym_y = right.symbol("y");
+    let right_y = right.definition(file, 1, sym_y);
+
+    let diff = left.diff(&right);
+    assert_eq!(diff.removed_nodes, vec![left_x]);
+    assert_eq!(diff.added_nodes, vec![right_y]);
+    assert!(diff.changed_nodes.is_empty());
+}
+
+#[test]
+fn can_match_nodes_by_symbol_and_span() {
+    let mut left = StackGraph::new();
+    let file = left.file("test.py");
+    let sym_x = left.symbol("x");
+    let left_x = left.definition(file, 1, sym_x);
+    left.source_info_mut(left_x).span = span(0, 4);
+
+    let mut right = StackGraph::new();
+    let file = right.file("test.py");
+    let sym_x = right.symbol("x");
+    right.internal_scope(file, 1);
+    let right_x = right.definition(file, 2, sym_x);
+    right.source_info_mut(right_x).span = span(0, 4);
+    right.add_edge(right_x, StackGraph::root_node(), 0);
+
+    let diff = left.diff_file(left.get_file("test.py").unwrap(), &right);
+    assert!(diff.removed_nodes.is_empty());
+    assert_eq!(diff.added_nodes.len(), 1);
+    assert!(diff.changed_nodes.is_empty());
+    assert_eq!(
+        diff.added_edges,
+        vec![Edge {
+            source: right_x,
+            sink: StackGraph::root_node(),
+            precedence: 0
+        }]
+    );
+}
+
+#[test]
+fn can_detect_changed_nodes_and_edges() {
+    let mut left = StackGraph::new();
+    let file = left.file("test.py");
+    let sym_x = left.symbol("x");
+    let left_ref = left.reference(file, 1, sym_x);
+    let left_def = left.definition(file, 2, sym_x);
+    left.add_edge(left_ref, left_def, 0);
+    left.source_info_mut(left_def).span = span(0, 0);
+
+    let mut right = StackGraph::new();
+    let file = right.file("test.py");
+    let sym_x = right.symbol("x");
+    let right_ref = right.reference(file, 1, sym_x);
+    let right_def = right.definition(file, 2, sym_x);
+    right.add_edge(right_ref, right_def, 1);
+    right.source_info_mut(right_def).span = span(1, 0);
+
+    let diff = left.diff(&right);
+    assert!(diff.added_nodes.is_empty());
+    assert!(diff.removed_nodes.is_empty());
+    assert_eq!(
+        diff.changed_nodes,
+        vec![ChangedNode {
+            left: left_def,
+            right: right_def,
+            changes: vec![NodeChange::Span],
+        }]
+    );
+    assert_eq!(
+        diff.changed_edges,
+        vec![ChangedEdge {
+            left: Edge {
+                source: left_ref,
+                sink: left_def,
+                precedence: 0,
+            },
+            right: Edge {
+                source: right_ref,
+                sink: right_def,
+                precedence: 1,
+            },
+        }]
+    );
+    assert_eq!(
+        diff.display(&left, &right).to_string(),
+        "~ node [test.py(2) definition x] -> [test.py(2) definition x] at 2:1: span\n\
+         ~ edge [test.py(1) reference x] -> [test.py(2) definition x]: precedence 0 -> 1\n"
+    );
+}
+
+#[test]
+fn can_diff_files_missing_from_one_graph() {
+    let mut left = StackGraph::new();
+    let file = left.file("test.py");
+    let sym_x = left.symbol("x");
+    let left_x = left.definition(file, 1, sym_x);
+    left.add_edge(StackGraph::root_node(), left_x, 0);
+
+    let right = StackGraph::new();
+
+    let diff = left.diff(&right);
+    assert_eq!(diff.removed_nodes, vec![left_x]);
+    assert_eq!(diff.removed_edges.len(), 1);
+    let diff = right.diff(&left);
+    assert_eq!(diff.added_nodes, vec![left_x]);
+    assert_eq!(diff.added_edges.len(), 1);
+}
@@ -11,6 +11,7 @@
 pub mod test_graphs;
 
 mod arena;
+mod builder;
 mod c;
 mod can_create_graph;
 mod can_find_local_nodes;
@@ -20,8 +21,13 @@ mod can_find_root_partial_paths_in_database;
 mod can_jump_to_definition;
 mod can_jump_to_definition_with_forward_partial_path_stitching;
 mod cycles;
+mod diff;
 mod graph;
 mod partial;
 #[cfg(feature = "serde")]
 mod serde;
+mod stats;
+#[cfg(feature = "storage")]
+mod storage;
 mod util;
+mod validation;
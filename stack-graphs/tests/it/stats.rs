@@ -0,0 +1,77 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stats::GraphStats;
+use stack_graphs::stats::NodeKind;
+use stack_graphs::stitching::Database;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::NoCancellation;
+
+use crate::test_graphs;
+
+#[test]
+fn can_compute_graph_stats() {
+    let graph = test_graphs::simple::new();
+    let stats = graph.stats();
+    assert_eq!(stats.files, 1);
+    assert_eq!(stats.nodes.total(), 11);
+    assert_eq!(stats.nodes.count(&NodeKind::Root), 1);
+    assert_eq!(stats.nodes.count(&NodeKind::JumpTo), 1);
+    assert_eq!(stats.nodes.count(&NodeKind::Scope), 2);
+    assert_eq!(stats.nodes.count(&NodeKind::PushSymbol), 2);
+    assert_eq!(stats.nodes.count(&NodeKind::PopSymbol), 2);
+    assert_eq!(stats.edges, 10);
+    assert_eq!(stats.out_degrees.total(), 11);
+    assert_eq!(stats.out_degrees.count(&0), 3);
+    assert_eq!(stats.out_degrees.count(&2), 2);
+    assert_eq!(stats.symbols.unique(), 3);
+    assert_eq!(stats.symbols.count(&"x".to_string()), 2);
+}
+
+#[test]
+fn can_aggregate_file_stats() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut total = GraphStats::default();
+    for file in graph.iter_files() {
+        total += graph.file_stats(file);
+    }
+    let stats = graph.stats();
+    assert_eq!(total.files, stats.files);
+    // the file stats exclude the singleton root and jump to nodes
+    assert_eq!(total.nodes.total() + 2, stats.nodes.total());
+    assert_eq!(
+        total.edges
+            + graph.outgoing_edges(StackGraph::root_node()).count()
+            + graph.outgoing_edges(StackGraph::jump_to_node()).count(),
+        stats.edges
+    );
+}
+
+#[test]
+fn can_compute_database_stats() {
+    let graph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |g, ps, p| {
+                db.add_partial_path(g, ps, p.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+    let stats = db.stats(&graph);
+    assert_eq!(stats.count(), db.iter_partial_paths().count());
+    assert_eq!(stats.end_nodes.total(), stats.count());
+    assert_eq!(stats.precondition_lengths.total(), stats.count());
+}
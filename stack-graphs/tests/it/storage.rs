@@ -0,0 +1,154 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::storage::artifact::FileArtifact;
+use stack_graphs::storage::FileStatus;
+use stack_graphs::storage::SQLiteWriter;
+use stack_graphs::storage::StorageError;
+use stack_graphs::NoCancellation;
+
+use crate::test_graphs;
+
+fn artifacts(graph: &StackGraph) -> Vec<FileArtifact> {
+    let mut artifacts = Vec::new();
+    for file in graph.iter_files() {
+        let mut partials = PartialPaths::new();
+        let mut paths = Vec::new();
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            graph,
+            &mut partials,
+            file,
+            &NoCancellation,
+            |_, _, p| paths.push(p.clone()),
+        )
+        .expect("should never be cancelled");
+        artifacts.push(FileArtifact::from_result(
+            graph,
+            file,
+            "tag",
+            &mut partials,
+            &paths,
+        ));
+    }
+    artifacts
+}
+
+#[test]
+fn can_encode_and_decode_artifacts() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    for artifact in artifacts(&graph) {
+        let bytes = artifact.encode().expect("artifact should encode");
+        let decoded = FileArtifact::decode(&bytes).expect("artifact should decode");
+        assert_eq!(artifact, decoded);
+    }
+}
+
+#[test]
+fn cannot_decode_invalid_artifacts() {
+    let artifact = FileArtifact::from_error("test.py", "tag", "parsing failed");
+    let mut bytes = artifact.encode().expect("artifact should encode");
+    bytes[0] = b'X';
+    assert!(matches!(
+        FileArtifact::decode(&bytes),
+        Err(StorageError::InvalidArtifact)
+    ));
+    assert!(matches!(
+        FileArtifact::decode(&[]),
+        Err(StorageError::InvalidArtifact)
+    ));
+}
+
+#[test]
+fn can_import_and_export_artifacts() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut db = SQLiteWriter::open_in_memory().expect("database should open");
+    for artifact in artifacts(&graph) {
+        db.import_file_artifact(&artifact)
+            .expect("artifact should import");
+        let exported = db
+            .export_file_artifact(&artifact.file)
+            .expect("artifact should export")
+            .expect("file should exist");
+        assert_eq!(artifact.graph, exported.graph);
+        assert_eq!(artifact.paths.len(), exported.paths.len());
+        assert!(matches!(
+            db.status_for_file(&artifact.file, Some("tag")),
+            Ok(FileStatus::Indexed)
+        ));
+    }
+    assert!(matches!(db.export_file_artifact("missing.py"), Ok(None)));
+}
+
+#[test]
+fn can_import_error_artifacts() {
+    let mut db = SQLiteWriter::open_in_memory().expect("database should open");
+    let artifact = FileArtifact::from_error("test.py", "tag", "parsing failed");
+    db.import_file_artifact(&artifact)
+        .expect("artifact should import");
+    // importing again replaces the existing entry
+    db.import_file_artifact(&artifact)
+        .expect("artifact should import");
+    assert!(matches!(
+        db.status_for_file("test.py", Some("tag")),
+        Ok(FileStatus::Error(error)) if error == "parsing failed"
+    ));
+    let exported = db
+        .export_file_artifact("test.py")
+        .expect("artifact should export");
+    assert_eq!(Some(artifact), exported);
+}
+
+#[test]
+fn can_store_results_with_warnings() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut db = SQLiteWriter::open_in_memory().expect("database should open");
+    for mut artifact in artifacts(&graph) {
+        artifact.warnings = Some("test.py:1:1: unexpected syntax".to_string());
+        db.import_file_artifact(&artifact)
+            .expect("artifact should import");
+        assert!(matches!(
+            db.status_for_file(&artifact.file, Some("tag")),
+            Ok(FileStatus::IndexedWithWarnings(warnings)) if warnings == "test.py:1:1: unexpected syntax"
+        ));
+        assert!(matches!(
+            db.status_for_file(&artifact.file, None::<&str>),
+            Ok(FileStatus::IndexedWithWarnings(_))
+        ));
+        let exported = db
+            .export_file_artifact(&artifact.file)
+            .expect("artifact should export")
+            .expect("file should exist");
+        assert_eq!(artifact.warnings, exported.warnings);
+    }
+}
+
+#[test]
+fn can_import_renamed_artifacts() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut db = SQLiteWriter::open_in_memory().expect("database should open");
+    for mut artifact in artifacts(&graph) {
+        let file = format!("src/{}", artifact.file);
+        artifact.rename_file(&file);
+        assert_eq!(file, artifact.file);
+        let bytes = artifact.encode().expect("artifact should encode");
+        let decoded = FileArtifact::decode(&bytes).expect("artifact should decode");
+        db.import_file_artifact(&decoded)
+            .expect("artifact should import");
+        assert!(matches!(
+            db.status_for_file(&file, Some("tag")),
+            Ok(FileStatus::Indexed)
+        ));
+        let exported = db
+            .export_file_artifact(&file)
+            .expect("artifact should export")
+            .expect("file should exist");
+        assert_eq!(artifact.graph, exported.graph);
+    }
+}
@@ -0,0 +1,191 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use stack_graphs::graph::validation::ValidationIssue;
+use stack_graphs::graph::StackGraph;
+
+use crate::test_graphs;
+use crate::test_graphs::CreateStackGraph;
+
+#[test]
+fn well_formed_graph_has_no_issues() {
+    let graph = test_graphs::simple::new();
+    assert_eq!(graph.validate(), vec![]);
+}
+
+#[test]
+fn can_detect_unreachable_definitions() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let sym_x = graph.symbol("x");
+    let def_x = graph.definition(file, 1, sym_x);
+    let def_y = graph.definition(file, 2, sym_x);
+    graph.edge(def_x, def_y);
+    assert_eq!(
+        graph.validate_file(file),
+        vec![
+            ValidationIssue::UnreachableDefinition(def_x),
+            ValidationIssue::UnreachableDefinition(def_y),
+        ]
+    );
+}
+
+#[test]
+fn does_not_report_definitions_reachable_from_internal_scopes() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let sym_x = graph.symbol("x");
+    let scope = graph.internal_scope(file, 0);
+    let def_x = graph.definition(file, 1, sym_x);
+    graph.edge(scope, def_x);
+    assert_eq!(graph.validate_file(file), vec![]);
+}
+
+#[test]
+fn can_detect_push_nodes_without_outgoing_edges() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let sym_x = graph.symbol("x");
+    let ref_x = graph.reference(file, 1, sym_x);
+    assert_eq!(
+        graph.validate_file(file),
+        vec![ValidationIssue::PushWithoutOutgoingEdges(ref_x)]
+    );
+}
+
+#[test]
+fn can_detect_unreferenced_exported_scopes() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let scope = graph.exported_scope(file, 1);
+    assert_eq!(
+        graph.validate_file(file),
+        vec![ValidationIssue::UnreferencedExportedScope(scope)]
+    );
+}
+
+#[test]
+fn can_detect_root_edges_from_non_exported_positions() {
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let file = graph.file("test.py");
+    let sym_x = graph.symbol("x");
+    let def_x = graph.definition(file, 1, sym_x);
+    graph.edge(root, def_x);
+    graph.edge(def_x, root);
+    assert_eq!(
+        graph.validate_file(file),
+        vec![ValidationIssue::NonExportedRootEdge(def_x)]
+    );
+}
+
+#[test]
+fn can_detect_root_edges_from_drop_scopes_nodes() {
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let file = graph.file("test.py");
+    let sym_x = graph.symbol("x");
+    let ref_x = graph.reference(file, 1, sym_x);
+    let drop = graph.drop_scopes(file, 2);
+    graph.edge(ref_x, drop);
+    graph.edge(drop, root);
+    assert_eq!(
+        graph.validate_file(file),
+        vec![ValidationIssue::NonExportedRootEdge(drop)]
+    );
+}
+
+#[test]
+fn does_not_report_root_edges_from_scopes() {
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let file = graph.file("test.py");
+    let sym_x = graph.symbol("x");
+    let exported = graph.exported_scope(file, 0);
+    let internal = graph.internal_scope(file, 1);
+    let ref_x = graph.push_scoped_symbol(file, 2, sym_x, file, 0);
+    graph.edge(ref_x, internal);
+    graph.edge(exported, root);
+    graph.edge(internal, root);
+    assert_eq!(graph.validate_file(file), vec![]);
+}
+
+#[test]
+fn can_validate_several_files_at_once() {
+    let mut graph = StackGraph::new();
+    let file1 = graph.file("test1.py");
+    let file2 = graph.file("test2.py");
+    let file3 = graph.file("test3.py");
+    let sym_x = graph.symbol("x");
+    let ref1 = graph.reference(file1, 1, sym_x);
+    let ref2 = graph.reference(file2, 1, sym_x);
+    graph.reference(file3, 1, sym_x);
+    assert_eq!(
+        graph.validate_files(vec![file1, file2]),
+        vec![
+            ValidationIssue::PushWithoutOutgoingEdges(ref1),
+            ValidationIssue::PushWithoutOutgoingEdges(ref2),
+        ]
+    );
+}
+
+#[test]
+fn only_reports_issues_in_validated_file() {
+    let mut graph = StackGraph::new();
+    let file1 = graph.file("test1.py");
+    let file2 = graph.file("test2.py");
+    let sym_x = graph.symbol("x");
+    let ref_x = graph.reference(file1, 1, sym_x);
+    graph.reference(file2, 1, sym_x);
+    assert_eq!(
+        graph.validate_file(file1),
+        vec![ValidationIssue::PushWithoutOutgoingEdges(ref_x)]
+    );
+    assert_eq!(graph.validate().len(), 2);
+}
+
+#[test]
+fn can_display_issues_with_debug_info() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let sym_x = graph.symbol("x");
+    let ref_x = graph.reference(file, 1, sym_x);
+    let key = graph.add_string("name");
+    let value = graph.add_string("ref_x");
+    graph.node_debug_info_mut(ref_x).add(key, value);
+    let issues = graph.validate_file(file);
+    assert_eq!(
+        issues
+            .iter()
+            .map(|issue| issue.display(&graph).to_string())
+            .collect::<Vec<_>>(),
+        vec!["push node without outgoing edges [test.py(1) reference x] (name = ref_x)"]
+    );
+}
+
+#[test]
+fn can_display_debug_info_sorted_by_key() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let sym_x = graph.symbol("x");
+    let ref_x = graph.reference(file, 1, sym_x);
+    for (key, value) in [("tsg_line", "42"), ("name", "ref_x")] {
+        let key = graph.add_string(key);
+        let value = graph.add_string(value);
+        graph.node_debug_info_mut(ref_x).add(key, value);
+    }
+    let issues = graph.validate_file(file);
+    assert_eq!(
+        issues
+            .iter()
+            .map(|issue| issue.display(&graph).to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "push node without outgoing edges [test.py(1) reference x] (name = ref_x, tsg_line = 42)"
+        ]
+    );
+}
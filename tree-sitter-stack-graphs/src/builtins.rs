@@ -0,0 +1,164 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines precompiled builtins, which language crates can embed to avoid building the builtins
+//! stack graph from source every time the language is loaded.
+//!
+//! Precompiled builtins are typically generated by the build script of the language crate, which
+//! cannot use the language configuration of the crate itself, and therefore builds the builtins
+//! from the same sources with [`PrecompiledBuiltins::from_sources`][]:
+//!
+//! ``` ignore
+//! let precompiled = PrecompiledBuiltins::from_sources(
+//!     tree_sitter_java::language(),
+//!     &[("src/stack-graphs.tsg".into(), include_str!("../src/stack-graphs.tsg"))],
+//!     ("src/builtins.java".into(), include_str!("../src/builtins.java")),
+//!     Some(include_str!("../src/builtins.cfg")),
+//!     &NoCancellation,
+//! )?;
+//! std::fs::write(out_dir.join("builtins.bin"), precompiled.encode()?)?;
+//! ```
+//!
+//! The crate then embeds the result with `include_bytes!`.
+//!
+//! The embedded builtins are loaded with [`LanguageConfiguration::load_builtins_with_precompiled`][],
+//! which falls back to the builtins source if they were precompiled with an incompatible format.
+//!
+//! [`LanguageConfiguration::load_builtins_with_precompiled`]: crate::loader::LanguageConfiguration::load_builtins_with_precompiled
+
+use bincode::error::DecodeError;
+use bincode::error::EncodeError;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::CancellationError;
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::loader::FileAnalyzers;
+use crate::loader::LanguageConfiguration;
+use crate::CancellationFlag;
+
+/// The version of the precompiled builtins format. It must be incremented whenever the serialized
+/// types change, so that incompatible precompiled builtins are not loaded.
+pub const VERSION: u32 = 1;
+
+const MAGIC: &str = "tree-sitter-stack-graphs builtins";
+
+static BINCODE_CONFIG: bincode::config::Configuration = bincode::config::standard();
+
+#[derive(bincode::Decode, bincode::Encode)]
+struct Header {
+    magic: String,
+    version: u32,
+}
+
+/// A builtins stack graph, together with the minimal partial paths of its files.
+#[derive(bincode::Decode, bincode::Encode)]
+pub struct PrecompiledBuiltins {
+    pub graph: stack_graphs::serde::StackGraph,
+    pub paths: Vec<stack_graphs::serde::PartialPath>,
+}
+
+impl PrecompiledBuiltins {
+    /// Precompiles the given builtins graph, computing the minimal partial paths of all its files.
+    pub fn from_graph(
+        graph: &StackGraph,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Self, CancellationError> {
+        let mut partials = PartialPaths::new();
+        let mut paths = Vec::new();
+        for file in graph.iter_files() {
+            ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+                graph,
+                &mut partials,
+                file,
+                &cancellation_flag,
+                |graph, partials, path| {
+                    paths.push(stack_graphs::serde::PartialPath::from_partial_path(
+                        graph, partials, path,
+                    ));
+                },
+            )?;
+        }
+        Ok(Self {
+            graph: graph.to_serializable(),
+            paths,
+        })
+    }
+
+    /// Precompiles the builtins built from the given sources, in the same way as
+    /// [`LanguageConfiguration::from_sources`][] builds them.
+    pub fn from_sources(
+        language: tree_sitter::Language,
+        tsg_sources: &[(PathBuf, &str)],
+        builtins_source: (PathBuf, &str),
+        builtins_config: Option<&str>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Self, PrecompiledBuiltinsError> {
+        let lc = LanguageConfiguration::from_sources(
+            language,
+            None,
+            None,
+            Vec::new(),
+            tsg_sources,
+            Some(builtins_source),
+            builtins_config,
+            Vec::new(),
+            FileAnalyzers::new(),
+            cancellation_flag,
+        )
+        .map_err(|err| PrecompiledBuiltinsError::Build(err.to_string()))?;
+        Ok(Self::from_graph(&lc.builtins, cancellation_flag)?)
+    }
+
+    /// Encodes the precompiled builtins, preceded by the format version.
+    pub fn encode(&self) -> Result<Vec<u8>, EncodeError> {
+        let header = Header {
+            magic: MAGIC.to_string(),
+            version: VERSION,
+        };
+        let mut bytes = bincode::encode_to_vec(&header, BINCODE_CONFIG)?;
+        bytes.extend(bincode::encode_to_vec(self, BINCODE_CONFIG)?);
+        Ok(bytes)
+    }
+
+    /// Decodes precompiled builtins. Fails with [`PrecompiledBuiltinsError::VersionMismatch`][]
+    /// if they were encoded with a different format version.
+    pub fn decode(bytes: &[u8]) -> Result<Self, PrecompiledBuiltinsError> {
+        let (header, header_len): (Header, _) =
+            bincode::decode_from_slice(bytes, BINCODE_CONFIG)
+                .map_err(|_| PrecompiledBuiltinsError::InvalidFormat)?;
+        if header.magic != MAGIC {
+            return Err(PrecompiledBuiltinsError::InvalidFormat);
+        }
+        if header.version != VERSION {
+            return Err(PrecompiledBuiltinsError::VersionMismatch(header.version));
+        }
+        let (builtins, _) = bincode::decode_from_slice(&bytes[header_len..], BINCODE_CONFIG)?;
+        Ok(builtins)
+    }
+}
+
+/// An error that can occur while loading precompiled builtins
+#[derive(Debug, Error)]
+pub enum PrecompiledBuiltinsError {
+    #[error("Failed to build builtins: {0}")]
+    Build(String),
+    #[error(transparent)]
+    Cancelled(#[from] CancellationError),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    #[error("Invalid precompiled builtins")]
+    InvalidFormat,
+    #[error(transparent)]
+    Load(#[from] stack_graphs::serde::Error),
+    #[error("Precompiled builtins version {0} does not match supported version {VERSION}")]
+    VersionMismatch(u32),
+}
@@ -0,0 +1,663 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines a static checker for stack graph construction rules.
+//!
+//! Many mistakes in TSG rules only surface when a stack graph is built for a source file that
+//! triggers the faulty stanza. The checker analyses the rules without executing them, and reports
+//! problems with the use of the stack graph vocabulary:
+//!
+//!  - nodes that are given conflicting or unknown `type`s, possibly in different stanzas;
+//!  - attributes that are not expected on nodes of the given type;
+//!  - definitions and references without a `source_node`;
+//!  - `push_scoped_symbol` nodes whose `scope` is not marked `is_exported`.
+//!
+//! Nodes are identified by the variable they are stored in. Scoped variables, such as
+//! `@id.def`, are identified by their name and the node kinds the capture matches in the stanza
+//! query, so that attributes set in different stanzas on the same kind of syntax node are
+//! combined. Attributes whose node or value cannot be determined statically, such as a `type`
+//! computed by a function, are ignored, which means the checker may miss problems but should
+//! not report false ones.
+//!
+//! Queries referencing node kinds or fields that do not exist in the grammar are already rejected
+//! when the TSG file is parsed, and are not reported by the checker.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+
+use tree_sitter_graph::ast::AddGraphNodeAttribute;
+use tree_sitter_graph::ast::Attribute;
+use tree_sitter_graph::ast::Expression;
+use tree_sitter_graph::ast::File;
+use tree_sitter_graph::ast::Statement;
+use tree_sitter_graph::ast::Variable;
+use tree_sitter_graph::Location;
+
+use crate::is_common_attribute;
+use crate::DROP_SCOPES_TYPE;
+use crate::IS_DEFINITION_ATTR;
+use crate::IS_ENDPOINT_ATTR;
+use crate::IS_EXPORTED_ATTR;
+use crate::IS_REFERENCE_ATTR;
+use crate::POP_SCOPED_SYMBOL_ATTRS;
+use crate::POP_SCOPED_SYMBOL_TYPE;
+use crate::POP_SYMBOL_ATTRS;
+use crate::POP_SYMBOL_TYPE;
+use crate::PUSH_SCOPED_SYMBOL_ATTRS;
+use crate::PUSH_SCOPED_SYMBOL_TYPE;
+use crate::PUSH_SYMBOL_ATTRS;
+use crate::PUSH_SYMBOL_TYPE;
+use crate::SCOPE_ATTR;
+use crate::SCOPE_ATTRS;
+use crate::SCOPE_TYPE;
+use crate::SOURCE_NODE_ATTR;
+use crate::TYPE_ATTR;
+
+/// A problem found in the stack graph construction rules.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CheckDiagnostic {
+    /// Zero-based line of the TSG statement the problem was found in.
+    pub line: usize,
+    /// Zero-based column of the TSG statement the problem was found in.
+    pub column: usize,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl CheckDiagnostic {
+    fn new(location: Location, message: String) -> Self {
+        Self {
+            line: location.row,
+            column: location.column,
+            message,
+        }
+    }
+
+    /// Display the diagnostic, preceded by its location in the given TSG file.
+    pub fn display_with_tsg_path<'a>(&'a self, tsg_path: &'a Path) -> impl std::fmt::Display + 'a {
+        DisplayCheckDiagnostic {
+            diagnostic: self,
+            tsg_path,
+        }
+    }
+}
+
+impl std::fmt::Display for CheckDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+struct DisplayCheckDiagnostic<'a> {
+    diagnostic: &'a CheckDiagnostic,
+    tsg_path: &'a Path,
+}
+
+impl std::fmt::Display for DisplayCheckDiagnostic<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.tsg_path.display(),
+            self.diagnostic.line + 1,
+            self.diagnostic.column + 1,
+            self.diagnostic
+        )
+    }
+}
+
+/// Statically checks the given stack graph construction rules. The TSG source is used to determine
+/// the node kinds of query captures, and may be empty if it is not available. The returned
+/// diagnostics are ordered by their location in the TSG file.
+pub fn check_tsg(tsg: &File, tsg_source: &str) -> Vec<CheckDiagnostic> {
+    let mut checker = Checker {
+        tsg,
+        nodes: HashMap::new(),
+    };
+    for (index, stanza) in tsg.stanzas.iter().enumerate() {
+        let mut context = StanzaContext {
+            index,
+            capture_kinds: capture_kinds(tsg_source, stanza.range.start),
+            aliases: HashMap::new(),
+        };
+        checker.collect_statements(&mut context, &stanza.statements);
+    }
+    let mut diagnostics = checker.check();
+    diagnostics.sort();
+    diagnostics.dedup();
+    diagnostics
+}
+
+/// Identifies a graph node by the variable it is stored in.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum NodeKey {
+    /// A local variable in the stanza with the given index.
+    Local(usize, String),
+    /// A scoped variable on syntax nodes of the given kind.
+    Scoped(String, String),
+    /// A scoped variable on a capture of unknown kind in the stanza with the given index.
+    UnknownScoped(usize, String, String),
+}
+
+/// Everything that is statically known about a graph node.
+#[derive(Default)]
+struct NodeFacts {
+    /// Whether a `node` statement creates this node.
+    created: bool,
+    /// Constant `type` values, with the expression used to refer to the node.
+    types: Vec<(String, String, Location)>,
+    /// Whether the `type` is set to a value that is not a constant.
+    has_dynamic_type: bool,
+    /// Attributes set on the node, with the expression used to refer to the node.
+    attributes: Vec<(String, String, Location)>,
+    /// Nodes used as the `scope` of this node.
+    scopes: Vec<(NodeKey, String, Location)>,
+}
+
+struct StanzaContext {
+    index: usize,
+    /// Node kinds of the query captures, for captures where they are known.
+    capture_kinds: HashMap<String, BTreeSet<String>>,
+    /// Nodes stored in local variables.
+    aliases: HashMap<String, Vec<NodeKey>>,
+}
+
+struct Checker<'a> {
+    tsg: &'a File,
+    nodes: HashMap<NodeKey, NodeFacts>,
+}
+
+impl<'a> Checker<'a> {
+    fn collect_statements(&mut self, context: &mut StanzaContext, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::DeclareImmutable(stmt) => {
+                    Self::collect_alias(context, &stmt.variable, &stmt.value)
+                }
+                Statement::DeclareMutable(stmt) => {
+                    Self::collect_alias(context, &stmt.variable, &stmt.value)
+                }
+                Statement::Assign(stmt) => {
+                    // the value of a mutable variable cannot be determined statically
+                    if let Variable::Unscoped(variable) = &stmt.variable {
+                        context
+                            .aliases
+                            .insert(variable.name.to_string(), Vec::new());
+                    }
+                }
+                Statement::CreateGraphNode(stmt) => {
+                    for key in node_keys(context, &stmt.node) {
+                        self.nodes.entry(key).or_default().created = true;
+                    }
+                }
+                Statement::AddGraphNodeAttribute(stmt) => self.collect_attributes(context, stmt),
+                Statement::Scan(stmt) => {
+                    for arm in &stmt.arms {
+                        self.collect_statements(context, &arm.statements);
+                    }
+                }
+                Statement::If(stmt) => {
+                    for arm in &stmt.arms {
+                        self.collect_statements(context, &arm.statements);
+                    }
+                }
+                Statement::ForIn(stmt) => {
+                    self.collect_statements(context, &stmt.statements);
+                }
+                Statement::CreateEdge(_) | Statement::AddEdgeAttribute(_) | Statement::Print(_) => {
+                }
+            }
+        }
+    }
+
+    fn collect_alias(context: &mut StanzaContext, variable: &Variable, value: &Expression) {
+        if let Variable::Unscoped(variable) = variable {
+            let keys = expression_node_keys(context, value);
+            context.aliases.insert(variable.name.to_string(), keys);
+        }
+    }
+
+    fn collect_attributes(&mut self, context: &StanzaContext, stmt: &AddGraphNodeAttribute) {
+        let keys = expression_node_keys(context, &stmt.node);
+        if keys.is_empty() {
+            return;
+        }
+        let node = stmt.node.to_string();
+        let mut attributes = Vec::new();
+        for attribute in &stmt.attributes {
+            self.expand_shorthands(
+                attribute,
+                &attribute.value,
+                &mut Vec::new(),
+                &mut attributes,
+            );
+        }
+        for key in keys {
+            let facts = self.nodes.entry(key).or_default();
+            for (name, value) in &attributes {
+                if *name == TYPE_ATTR {
+                    match value {
+                        Expression::StringConstant(node_type) => {
+                            facts
+                                .types
+                                .push((node_type.value.clone(), node.clone(), stmt.location))
+                        }
+                        _ => facts.has_dynamic_type = true,
+                    }
+                } else if *name == SCOPE_ATTR {
+                    for scope in expression_node_keys(context, value) {
+                        facts.scopes.push((scope, value.to_string(), stmt.location));
+                    }
+                }
+                facts
+                    .attributes
+                    .push((name.to_string(), node.clone(), stmt.location));
+            }
+        }
+    }
+
+    /// Expands attribute shorthands into the attributes they stand for. The value given to a
+    /// shorthand is substituted for its variable, if that is used directly as an attribute value.
+    fn expand_shorthands<'b>(
+        &self,
+        attribute: &'b Attribute,
+        value: &'b Expression,
+        expanding: &mut Vec<&'b str>,
+        attributes: &mut Vec<(&'b str, &'b Expression)>,
+    ) where
+        'a: 'b,
+    {
+        let name = attribute.name.as_str();
+        let shorthand = match self.tsg.shorthands.get(&attribute.name) {
+            Some(shorthand) if !expanding.contains(&name) => shorthand,
+            _ => {
+                attributes.push((name, value));
+                return;
+            }
+        };
+        expanding.push(name);
+        for shorthand_attribute in &shorthand.attributes {
+            let shorthand_value = match &shorthand_attribute.value {
+                Expression::Variable(Variable::Unscoped(variable))
+                    if variable.name == shorthand.variable.name =>
+                {
+                    value
+                }
+                value => value,
+            };
+            self.expand_shorthands(shorthand_attribute, shorthand_value, expanding, attributes);
+        }
+        expanding.pop();
+    }
+
+    fn check(&self) -> Vec<CheckDiagnostic> {
+        let mut diagnostics = Vec::new();
+        for facts in self.nodes.values() {
+            let node_type = match self.check_type(facts, &mut diagnostics) {
+                Some(node_type) => node_type,
+                None => continue,
+            };
+            self.check_attributes(facts, node_type, &mut diagnostics);
+            if node_type == PUSH_SCOPED_SYMBOL_TYPE {
+                self.check_scopes(facts, &mut diagnostics);
+            }
+        }
+        diagnostics
+    }
+
+    /// Checks the type of the node, and returns it if it can be determined.
+    fn check_type(
+        &self,
+        facts: &NodeFacts,
+        diagnostics: &mut Vec<CheckDiagnostic>,
+    ) -> Option<&'static str> {
+        let mut first_type: Option<&(String, String, Location)> = None;
+        for node_type in &facts.types {
+            if known_node_type(&node_type.0).is_none() {
+                diagnostics.push(CheckDiagnostic::new(
+                    node_type.2,
+                    format!("Unknown type ‘{}’ for node {}", node_type.0, node_type.1),
+                ));
+                continue;
+            }
+            match first_type {
+                None => first_type = Some(node_type),
+                Some(first_type) if first_type.0 != node_type.0 => {
+                    diagnostics.push(CheckDiagnostic::new(
+                        node_type.2,
+                        format!(
+                            "Type ‘{}’ of node {} conflicts with type ‘{}’ set on line {}",
+                            node_type.0,
+                            node_type.1,
+                            first_type.0,
+                            first_type.2.row + 1,
+                        ),
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+        if facts.has_dynamic_type {
+            return None;
+        }
+        match first_type {
+            Some(node_type) => known_node_type(&node_type.0),
+            None if facts.types.is_empty() => Some(SCOPE_TYPE),
+            None => None,
+        }
+    }
+
+    fn check_attributes(
+        &self,
+        facts: &NodeFacts,
+        node_type: &'static str,
+        diagnostics: &mut Vec<CheckDiagnostic>,
+    ) {
+        let allowed_attributes = match expected_attributes(node_type) {
+            Some(allowed_attributes) => allowed_attributes,
+            None => return,
+        };
+        let has_source_node = facts
+            .attributes
+            .iter()
+            .any(|(name, _, _)| name == SOURCE_NODE_ATTR);
+        for (name, node, location) in &facts.attributes {
+            if !allowed_attributes.contains(name.as_str()) && !is_common_attribute(name) {
+                diagnostics.push(CheckDiagnostic::new(
+                    *location,
+                    format!(
+                        "Unexpected attribute ‘{}’ on node {} of type {}",
+                        name, node, node_type
+                    ),
+                ));
+            } else if (name == IS_DEFINITION_ATTR || name == IS_REFERENCE_ATTR) && !has_source_node
+            {
+                diagnostics.push(CheckDiagnostic::new(
+                    *location,
+                    format!(
+                        "Node {} is marked ‘{}’ but has no ‘{}’",
+                        node, name, SOURCE_NODE_ATTR
+                    ),
+                ));
+            }
+        }
+    }
+
+    fn check_scopes(&self, facts: &NodeFacts, diagnostics: &mut Vec<CheckDiagnostic>) {
+        for (scope, scope_expr, location) in &facts.scopes {
+            let scope_facts = match self.nodes.get(scope) {
+                Some(scope_facts) if scope_facts.created => scope_facts,
+                _ => continue,
+            };
+            let is_exported = scope_facts
+                .attributes
+                .iter()
+                .any(|(name, _, _)| name == IS_EXPORTED_ATTR || name == IS_ENDPOINT_ATTR);
+            if !is_exported {
+                diagnostics.push(CheckDiagnostic::new(
+                    *location,
+                    format!(
+                        "Scope {} of push_scoped_symbol node is never marked ‘{}’",
+                        scope_expr, IS_EXPORTED_ATTR
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// Returns the keys of the nodes that can be stored in the given variable.
+fn node_keys(context: &StanzaContext, variable: &Variable) -> Vec<NodeKey> {
+    match variable {
+        Variable::Unscoped(variable) => match context.aliases.get(variable.name.as_str()) {
+            Some(keys) => keys.clone(),
+            None => vec![NodeKey::Local(context.index, variable.name.to_string())],
+        },
+        Variable::Scoped(variable) => {
+            let capture = match &*variable.scope {
+                Expression::Capture(capture) => capture,
+                _ => return Vec::new(),
+            };
+            match context.capture_kinds.get(capture.name.as_str()) {
+                Some(kinds) => kinds
+                    .iter()
+                    .map(|kind| NodeKey::Scoped(kind.clone(), variable.name.to_string()))
+                    .collect(),
+                None => vec![NodeKey::UnknownScoped(
+                    context.index,
+                    capture.name.to_string(),
+                    variable.name.to_string(),
+                )],
+            }
+        }
+    }
+}
+
+/// Returns the keys of the nodes the given expression can evaluate to, if they can be determined.
+fn expression_node_keys(context: &StanzaContext, expression: &Expression) -> Vec<NodeKey> {
+    match expression {
+        Expression::Variable(variable) => node_keys(context, variable),
+        _ => Vec::new(),
+    }
+}
+
+fn known_node_type(node_type: &str) -> Option<&'static str> {
+    [
+        DROP_SCOPES_TYPE,
+        POP_SCOPED_SYMBOL_TYPE,
+        POP_SYMBOL_TYPE,
+        PUSH_SCOPED_SYMBOL_TYPE,
+        PUSH_SYMBOL_TYPE,
+        SCOPE_TYPE,
+    ]
+    .iter()
+    .find(|t| **t == node_type)
+    .copied()
+}
+
+fn expected_attributes(node_type: &str) -> Option<&'static HashSet<&'static str>> {
+    if node_type == POP_SCOPED_SYMBOL_TYPE {
+        Some(&POP_SCOPED_SYMBOL_ATTRS)
+    } else if node_type == POP_SYMBOL_TYPE {
+        Some(&POP_SYMBOL_ATTRS)
+    } else if node_type == PUSH_SCOPED_SYMBOL_TYPE {
+        Some(&PUSH_SCOPED_SYMBOL_ATTRS)
+    } else if node_type == PUSH_SYMBOL_TYPE {
+        Some(&PUSH_SYMBOL_ATTRS)
+    } else if node_type == SCOPE_TYPE {
+        Some(&SCOPE_ATTRS)
+    } else {
+        None
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Query captures
+
+/// Returns the node kinds matched by the captures of the stanza query starting at the given
+/// location. Captures on wildcards, or on patterns whose kind cannot be determined, are omitted.
+///
+/// The query is not available from the parsed TSG file in a form that allows inspecting its
+/// patterns, so the kinds are recovered from the query source instead.
+fn capture_kinds(tsg_source: &str, start: Location) -> HashMap<String, BTreeSet<String>> {
+    let line_offset: usize = tsg_source
+        .split_inclusive('\n')
+        .take(start.row)
+        .map(str::len)
+        .sum();
+    let line = &tsg_source[line_offset..];
+    let query = match line.char_indices().nth(start.column) {
+        Some((column_offset, _)) => &line[column_offset..],
+        None => return HashMap::new(),
+    };
+    let mut parser = QueryParser {
+        tokens: query_tokens(query),
+        pos: 0,
+        captures: HashMap::new(),
+    };
+    while parser.parse_pattern().is_some() {}
+    parser
+        .captures
+        .into_iter()
+        .filter_map(|(name, kinds)| kinds.map(|kinds| (name, kinds)))
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum QueryToken<'s> {
+    Open,
+    Close,
+    OpenAlternation,
+    CloseAlternation,
+    String(&'s str),
+    Word(&'s str),
+    Capture(&'s str),
+}
+
+/// Splits the query at the start of the given source into tokens, stopping at the `{` that
+/// starts the stanza body. Quantifiers, anchors, and field separators are dropped.
+fn query_tokens(source: &str) -> Vec<QueryToken<'_>> {
+    const WORD_DELIMITERS: &str = "()[]\"@;{}:";
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    let word_end = |start: usize| {
+        source[start..]
+            .find(|c: char| c.is_whitespace() || WORD_DELIMITERS.contains(c))
+            .map_or(source.len(), |end| start + end)
+    };
+    while let Some((offset, ch)) = chars.next() {
+        match ch {
+            '{' => break,
+            '(' => tokens.push(QueryToken::Open),
+            ')' => tokens.push(QueryToken::Close),
+            '[' => tokens.push(QueryToken::OpenAlternation),
+            ']' => tokens.push(QueryToken::CloseAlternation),
+            ';' => while chars.next_if(|(_, c)| *c != '\n').is_some() {},
+            '"' => {
+                let mut escaped = false;
+                let mut end = source.len();
+                for (o, c) in chars.by_ref() {
+                    if c == '"' && !escaped {
+                        end = o + 1;
+                        break;
+                    }
+                    escaped = c == '\\' && !escaped;
+                }
+                tokens.push(QueryToken::String(&source[offset..end]));
+            }
+            '@' => {
+                let end = word_end(offset + 1);
+                tokens.push(QueryToken::Capture(&source[offset + 1..end]));
+                while chars.next_if(|(o, _)| *o < end).is_some() {}
+            }
+            c if c.is_whitespace() || "?*+.:}".contains(c) => {}
+            _ => {
+                let end = word_end(offset);
+                tokens.push(QueryToken::Word(&source[offset..end]));
+                while chars.next_if(|(o, _)| *o < end).is_some() {}
+            }
+        }
+    }
+    tokens
+}
+
+/// Node kinds matched by a pattern, or `None` if they are unknown.
+type PatternKinds = Option<BTreeSet<String>>;
+
+struct QueryParser<'s> {
+    tokens: Vec<QueryToken<'s>>,
+    pos: usize,
+    captures: HashMap<String, PatternKinds>,
+}
+
+impl<'s> QueryParser<'s> {
+    /// Parses a pattern and the captures following it, and returns the node kinds it matches.
+    /// Returns `None`, without consuming anything, at a closing token or the end of the query.
+    fn parse_pattern(&mut self) -> Option<PatternKinds> {
+        let kinds = match self.tokens.get(self.pos)? {
+            QueryToken::Close | QueryToken::CloseAlternation => return None,
+            QueryToken::Open => {
+                self.pos += 1;
+                match self.tokens.get(self.pos) {
+                    Some(QueryToken::Word(word)) if word.starts_with('#') => {
+                        // predicate arguments refer to captures, they do not bind them
+                        self.skip_until_close();
+                        return Some(None);
+                    }
+                    Some(QueryToken::Word(word)) => {
+                        let kinds = (*word != "_").then(|| BTreeSet::from([word.to_string()]));
+                        self.pos += 1;
+                        self.parse_children();
+                        kinds
+                    }
+                    _ => {
+                        // grouped sequence of sibling patterns
+                        self.parse_children();
+                        None
+                    }
+                }
+            }
+            QueryToken::OpenAlternation => {
+                self.pos += 1;
+                let mut kinds = Some(BTreeSet::new());
+                while let Some(alternative) = self.parse_pattern() {
+                    kinds = match (kinds, alternative) {
+                        (Some(mut kinds), Some(alternative)) => {
+                            kinds.extend(alternative);
+                            Some(kinds)
+                        }
+                        _ => None,
+                    };
+                }
+                self.pos += 1;
+                kinds
+            }
+            QueryToken::String(string) => {
+                self.pos += 1;
+                Some(BTreeSet::from([string.to_string()]))
+            }
+            QueryToken::Word(_) | QueryToken::Capture(_) => {
+                // wildcards, field names, and negated fields
+                self.pos += 1;
+                None
+            }
+        };
+        while let Some(QueryToken::Capture(name)) = self.tokens.get(self.pos) {
+            let capture_kinds = self
+                .captures
+                .entry(name.to_string())
+                .or_insert_with(|| Some(BTreeSet::new()));
+            match (capture_kinds.as_mut(), &kinds) {
+                (Some(capture_kinds), Some(kinds)) => capture_kinds.extend(kinds.iter().cloned()),
+                _ => *capture_kinds = None,
+            }
+            self.pos += 1;
+        }
+        Some(kinds)
+    }
+
+    /// Parses child patterns up to and including the closing token.
+    fn parse_children(&mut self) {
+        while self.parse_pattern().is_some() {}
+        self.pos += 1;
+    }
+
+    fn skip_until_close(&mut self) {
+        let mut depth = 1;
+        while let Some(token) = self.tokens.get(self.pos) {
+            self.pos += 1;
+            match token {
+                QueryToken::Open => depth += 1,
+                QueryToken::Close if depth == 1 => return,
+                QueryToken::Close => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+}
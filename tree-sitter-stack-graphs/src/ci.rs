@@ -26,10 +26,21 @@
 //! ```
 //!
 //! By default tests time out after 60 seconds. Set `Tester::max_test_time` to change the timeout.
+//!
+//! By default tests are run in parallel on all available cores. Set `Tester::jobs` to change the
+//! number of tests that are run in parallel.
+//!
+//! Set `Tester::snapshots` to compare the graphs and paths of tests against snapshots stored next
+//! to the tests. Snapshots are created or updated, instead of compared, when `Tester::update_snapshots`
+//! is set, or when the `UPDATE_SNAPSHOTS` environment variable is set when running the tests.
+//!
+//! Add formats to `Tester::reports` to write JUnit XML or TAP reports of the test results, for
+//! example to show them in a CI system.
 
 use std::path::PathBuf;
 use std::time::Duration;
 
+use crate::cli::test::report::ReportFormat;
 use crate::cli::test::TestArgs;
 use crate::loader::{LanguageConfiguration, Loader};
 
@@ -39,6 +50,10 @@ pub struct Tester {
     configurations: Vec<LanguageConfiguration>,
     test_paths: Vec<PathBuf>,
     pub max_test_time: Option<Duration>,
+    pub jobs: usize,
+    pub snapshots: bool,
+    pub update_snapshots: bool,
+    pub reports: Vec<ReportFormat>,
 }
 
 impl Tester {
@@ -47,6 +62,10 @@ impl Tester {
             configurations,
             test_paths,
             max_test_time: Some(Duration::from_secs(60)),
+            jobs: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            snapshots: false,
+            update_snapshots: std::env::var_os("UPDATE_SNAPSHOTS").is_some(),
+            reports: Vec::new(),
         }
     }
 
@@ -70,6 +89,10 @@ impl Tester {
             .expect("Expected loader");
         let mut args = TestArgs::new(test_paths);
         args.max_test_time = self.max_test_time;
+        args.jobs = self.jobs;
+        args.snapshots = self.snapshots;
+        args.update_snapshots = self.snapshots && self.update_snapshots;
+        args.report = self.reports;
         args.run(loader)
     }
 }
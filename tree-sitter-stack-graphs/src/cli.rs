@@ -57,11 +57,15 @@
 //! }
 //! ```
 
+pub mod check;
 pub mod clean;
 pub mod database;
+pub mod diff;
+pub mod fuzz;
 pub mod index;
 pub mod init;
 pub mod load;
+pub mod loader;
 #[cfg(feature = "lsp")]
 pub mod lsp;
 pub mod r#match;
@@ -77,11 +81,16 @@ pub mod path_loading {
 
     use clap::Subcommand;
 
+    use crate::cli::check::CheckArgs;
     use crate::cli::clean::CleanArgs;
     use crate::cli::database::DatabaseArgs;
+    use crate::cli::database::DatabaseCommandArgs;
+    use crate::cli::diff::DiffArgs;
+    use crate::cli::fuzz::FuzzArgs;
     use crate::cli::index::IndexArgs;
     use crate::cli::init::InitArgs;
     use crate::cli::load::PathLoaderArgs;
+    use crate::cli::loader::LoaderCommandArgs;
     #[cfg(feature = "lsp")]
     use crate::cli::lsp::LspArgs;
     use crate::cli::parse::ParseArgs;
@@ -93,9 +102,14 @@ pub mod path_loading {
 
     #[derive(Subcommand)]
     pub enum Subcommands {
+        Check(Check),
         Clean(Clean),
+        Database(Database),
+        Diff(Diff),
+        Fuzz(Fuzz),
         Index(Index),
         Init(Init),
+        Loader(Loader),
         #[cfg(feature = "lsp")]
         Lsp(Lsp),
         Match(Match),
@@ -109,9 +123,14 @@ pub mod path_loading {
     impl Subcommands {
         pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
             match self {
+                Self::Check(cmd) => cmd.run(),
                 Self::Clean(cmd) => cmd.run(default_db_path),
+                Self::Database(cmd) => cmd.run(default_db_path),
+                Self::Diff(cmd) => cmd.run(),
+                Self::Fuzz(cmd) => cmd.run(),
                 Self::Index(cmd) => cmd.run(default_db_path),
                 Self::Init(cmd) => cmd.run(),
+                Self::Loader(cmd) => cmd.run(),
                 #[cfg(feature = "lsp")]
                 Self::Lsp(cmd) => cmd.run(default_db_path),
                 Self::Match(cmd) => cmd.run(),
@@ -124,6 +143,22 @@ pub mod path_loading {
         }
     }
 
+    /// Check the stack graph construction rules for common mistakes.
+    #[derive(clap::Parser)]
+    pub struct Check {
+        #[clap(flatten)]
+        load_args: PathLoaderArgs,
+        #[clap(flatten)]
+        check_args: CheckArgs,
+    }
+
+    impl Check {
+        pub fn run(self) -> anyhow::Result<()> {
+            let loader = self.load_args.get()?;
+            self.check_args.run(loader)
+        }
+    }
+
     /// Clean the indexing database.
     #[derive(clap::Parser)]
     pub struct Clean {
@@ -140,6 +175,54 @@ pub mod path_loading {
         }
     }
 
+    /// Manage the indexing database.
+    #[derive(clap::Parser)]
+    pub struct Database {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        database_args: DatabaseCommandArgs,
+    }
+
+    impl Database {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.database_args.run(&db_path)
+        }
+    }
+
+    /// Compare the stack graphs of a source file for two versions of the TSG.
+    #[derive(clap::Parser)]
+    pub struct Diff {
+        #[clap(flatten)]
+        load_args: PathLoaderArgs,
+        #[clap(flatten)]
+        diff_args: DiffArgs,
+    }
+
+    impl Diff {
+        pub fn run(self) -> anyhow::Result<()> {
+            let loader = self.load_args.get()?;
+            self.diff_args.run(loader)
+        }
+    }
+
+    /// Fuzz the stack graph construction rules with mutated source files.
+    #[derive(clap::Parser)]
+    pub struct Fuzz {
+        #[clap(flatten)]
+        load_args: PathLoaderArgs,
+        #[clap(flatten)]
+        fuzz_args: FuzzArgs,
+    }
+
+    impl Fuzz {
+        pub fn run(self) -> anyhow::Result<()> {
+            let loader = self.load_args.get()?;
+            self.fuzz_args.run(loader)
+        }
+    }
+
     /// Index source files into the database.
     #[derive(clap::Parser)]
     pub struct Index {
@@ -172,6 +255,22 @@ pub mod path_loading {
         }
     }
 
+    /// Inspect how languages are selected for source files.
+    #[derive(clap::Parser)]
+    pub struct Loader {
+        #[clap(flatten)]
+        load_args: PathLoaderArgs,
+        #[clap(flatten)]
+        loader_args: LoaderCommandArgs,
+    }
+
+    impl Loader {
+        pub fn run(self) -> anyhow::Result<()> {
+            let loader = self.load_args.get()?;
+            self.loader_args.run(loader)
+        }
+    }
+
     /// Run an LSP server.
     #[cfg(feature = "lsp")]
     #[derive(clap::Parser)]
@@ -295,11 +394,16 @@ pub mod provided_languages {
 
     use clap::Subcommand;
 
+    use crate::cli::check::CheckArgs;
     use crate::cli::clean::CleanArgs;
     use crate::cli::database::DatabaseArgs;
+    use crate::cli::database::DatabaseCommandArgs;
+    use crate::cli::diff::DiffArgs;
+    use crate::cli::fuzz::FuzzArgs;
     use crate::cli::index::IndexArgs;
     use crate::cli::init::InitArgs;
     use crate::cli::load::LanguageConfigurationsLoaderArgs;
+    use crate::cli::loader::LoaderCommandArgs;
     #[cfg(feature = "lsp")]
     use crate::cli::lsp::LspArgs;
     use crate::cli::parse::ParseArgs;
@@ -312,9 +416,14 @@ pub mod provided_languages {
 
     #[derive(Subcommand)]
     pub enum Subcommands {
+        Check(Check),
         Clean(Clean),
+        Database(Database),
+        Diff(Diff),
+        Fuzz(Fuzz),
         Index(Index),
         Init(Init),
+        Loader(Loader),
         #[cfg(feature = "lsp")]
         Lsp(Lsp),
         Match(Match),
@@ -332,9 +441,14 @@ pub mod provided_languages {
             configurations: Vec<LanguageConfiguration>,
         ) -> anyhow::Result<()> {
             match self {
+                Self::Check(cmd) => cmd.run(configurations),
                 Self::Clean(cmd) => cmd.run(default_db_path),
+                Self::Database(cmd) => cmd.run(default_db_path),
+                Self::Diff(cmd) => cmd.run(configurations),
+                Self::Fuzz(cmd) => cmd.run(configurations),
                 Self::Index(cmd) => cmd.run(default_db_path, configurations),
                 Self::Init(cmd) => cmd.run(),
+                Self::Loader(cmd) => cmd.run(configurations),
                 #[cfg(feature = "lsp")]
                 Self::Lsp(cmd) => cmd.run(default_db_path, configurations),
                 Self::Match(cmd) => cmd.run(configurations),
@@ -347,6 +461,22 @@ pub mod provided_languages {
         }
     }
 
+    /// Check the stack graph construction rules for common mistakes.
+    #[derive(clap::Parser)]
+    pub struct Check {
+        #[clap(flatten)]
+        load_args: LanguageConfigurationsLoaderArgs,
+        #[clap(flatten)]
+        check_args: CheckArgs,
+    }
+
+    impl Check {
+        pub fn run(self, configurations: Vec<LanguageConfiguration>) -> anyhow::Result<()> {
+            let loader = self.load_args.get(configurations)?;
+            self.check_args.run(loader)
+        }
+    }
+
     /// Clean the indexing database.
     #[derive(clap::Parser)]
     pub struct Clean {
@@ -363,6 +493,54 @@ pub mod provided_languages {
         }
     }
 
+    /// Manage the indexing database.
+    #[derive(clap::Parser)]
+    pub struct Database {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        database_args: DatabaseCommandArgs,
+    }
+
+    impl Database {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.database_args.run(&db_path)
+        }
+    }
+
+    /// Compare the stack graphs of a source file for two versions of the TSG.
+    #[derive(clap::Parser)]
+    pub struct Diff {
+        #[clap(flatten)]
+        load_args: LanguageConfigurationsLoaderArgs,
+        #[clap(flatten)]
+        diff_args: DiffArgs,
+    }
+
+    impl Diff {
+        pub fn run(self, configurations: Vec<LanguageConfiguration>) -> anyhow::Result<()> {
+            let loader = self.load_args.get(configurations)?;
+            self.diff_args.run(loader)
+        }
+    }
+
+    /// Fuzz the stack graph construction rules with mutated source files.
+    #[derive(clap::Parser)]
+    pub struct Fuzz {
+        #[clap(flatten)]
+        load_args: LanguageConfigurationsLoaderArgs,
+        #[clap(flatten)]
+        fuzz_args: FuzzArgs,
+    }
+
+    impl Fuzz {
+        pub fn run(self, configurations: Vec<LanguageConfiguration>) -> anyhow::Result<()> {
+            let loader = self.load_args.get(configurations)?;
+            self.fuzz_args.run(loader)
+        }
+    }
+
     /// Index source files into the database.
     #[derive(clap::Parser)]
     pub struct Index {
@@ -399,6 +577,22 @@ pub mod provided_languages {
         }
     }
 
+    /// Inspect how languages are selected for source files.
+    #[derive(clap::Parser)]
+    pub struct Loader {
+        #[clap(flatten)]
+        load_args: LanguageConfigurationsLoaderArgs,
+        #[clap(flatten)]
+        loader_args: LoaderCommandArgs,
+    }
+
+    impl Loader {
+        pub fn run(self, configurations: Vec<LanguageConfiguration>) -> anyhow::Result<()> {
+            let loader = self.load_args.get(configurations)?;
+            self.loader_args.run(loader)
+        }
+    }
+
     /// Run an LSP server.
     #[cfg(feature = "lsp")]
     #[derive(clap::Parser)]
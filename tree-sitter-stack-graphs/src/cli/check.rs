@@ -0,0 +1,81 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use anyhow::anyhow;
+use clap::Args;
+use clap::ValueHint;
+use std::path::PathBuf;
+
+use crate::check::check_tsg;
+use crate::check::CheckDiagnostic;
+use crate::cli::util::ExistingPathBufValueParser;
+use crate::loader::FileReader;
+use crate::loader::LanguageConfiguration;
+use crate::loader::Loader;
+use crate::NoCancellation;
+
+/// Check stack graph construction rules
+#[derive(Args)]
+pub struct CheckArgs {
+    /// Source file paths used to select the languages to check.
+    /// If omitted, all provided languages are checked.
+    #[clap(
+        value_name = "SOURCE_PATH",
+        value_hint = ValueHint::AnyPath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub source_paths: Vec<PathBuf>,
+}
+
+impl CheckArgs {
+    pub fn run(self, mut loader: Loader) -> anyhow::Result<()> {
+        let mut file_reader = FileReader::new();
+        for source_path in &self.source_paths {
+            match loader.load_for_file(source_path, &mut file_reader, &NoCancellation) {
+                Ok(lcs) if lcs.primary.is_none() => {
+                    return Err(anyhow!(
+                        "No stack graph language found for {}",
+                        source_path.display()
+                    ))
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    eprintln!("{}", err.display_pretty());
+                    return Err(anyhow!(
+                        "Failed to load language for {}",
+                        source_path.display()
+                    ));
+                }
+            }
+        }
+        let lcs = loader.loaded_configurations();
+        if lcs.is_empty() {
+            return Err(anyhow!("No stack graph language to check"));
+        }
+        let mut diagnostic_count = 0;
+        for lc in lcs {
+            diagnostic_count += check_language(lc);
+        }
+        if diagnostic_count > 0 {
+            return Err(anyhow!("{} problems found", diagnostic_count));
+        }
+        Ok(())
+    }
+}
+
+fn check_language(lc: &LanguageConfiguration) -> usize {
+    let diagnostics = check_tsg(lc.sgl.tsg(), lc.sgl.tsg_source());
+    for diagnostic in &diagnostics {
+        let (tsg_path, line) = lc.sgl.tsg_location(diagnostic.line);
+        let diagnostic = CheckDiagnostic {
+            line,
+            ..diagnostic.clone()
+        };
+        println!("{}", diagnostic.display_with_tsg_path(tsg_path));
+    }
+    diagnostics.len()
+}
@@ -7,8 +7,20 @@
 
 use anyhow::anyhow;
 use clap::Args;
+use clap::Parser;
+use clap::Subcommand;
 use clap::ValueHint;
+use stack_graphs::storage::artifact::FileArtifact;
+use stack_graphs::storage::artifact::ARTIFACT_EXTENSION;
+use stack_graphs::storage::SQLiteWriter;
+use std::path::Path;
 use std::path::PathBuf;
+use walkdir::WalkDir;
+
+use crate::cli::util::reporter::ConsoleReporter;
+use crate::cli::util::reporter::Level;
+use crate::cli::util::reporter::Reporter;
+use crate::cli::util::ExistingPathBufValueParser;
 
 #[derive(Args)]
 pub struct DatabaseArgs {
@@ -38,3 +50,119 @@ pub fn default_user_database_path_for_crate(crate_name: &str) -> anyhow::Result<
         )),
     }
 }
+
+#[derive(Args)]
+pub struct DatabaseCommandArgs {
+    #[clap(subcommand)]
+    command: DatabaseCommand,
+}
+
+impl DatabaseCommandArgs {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        self.command.run(db_path)
+    }
+}
+
+#[derive(Subcommand)]
+pub enum DatabaseCommand {
+    Import(Import),
+}
+
+impl DatabaseCommand {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        match self {
+            Self::Import(cmd) => cmd.run(db_path),
+        }
+    }
+}
+
+/// Import file artifacts, as emitted by `index --emit-artifacts`, into the database.
+#[derive(Parser)]
+pub struct Import {
+    /// Directory containing the file artifacts. Artifacts in subdirectories are imported as well.
+    #[clap(
+        value_name = "ARTIFACT_DIR",
+        required = true,
+        value_hint = ValueHint::DirPath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub artifact_dir: PathBuf,
+
+    /// Directory the files named in the artifacts are relative to. Artifacts name files relative
+    /// to the indexed directory, so this should be the location of that directory on this machine.
+    /// If omitted, files are imported with the relative names from the artifacts.
+    #[clap(
+        long,
+        value_name = "SOURCE_DIR",
+        value_hint = ValueHint::DirPath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub source_root: Option<PathBuf>,
+
+    #[clap(long, short = 'v')]
+    pub verbose: bool,
+}
+
+impl Import {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        let reporter = self.get_reporter();
+        let mut db = SQLiteWriter::open(db_path)?;
+        let mut failed = 0usize;
+        for entry in WalkDir::new(&self.artifact_dir)
+            .follow_links(true)
+            .sort_by_file_name()
+        {
+            let entry = entry?;
+            let path = entry.path();
+            if !entry.file_type().is_file()
+                || path.extension().and_then(|ext| ext.to_str()) != Some(ARTIFACT_EXTENSION)
+            {
+                continue;
+            }
+            reporter.started(path);
+            match Self::import(&mut db, path, self.source_root.as_deref()) {
+                Ok(artifact) => reporter.succeeded(
+                    path,
+                    "imported",
+                    Some(&format!("imported {}", artifact.file)),
+                ),
+                Err(err) => {
+                    failed += 1;
+                    reporter.failed(path, "failed to import", Some(&err.to_string()));
+                }
+            }
+        }
+        if failed > 0 {
+            return Err(anyhow!("{} artifacts failed to import", failed));
+        }
+        Ok(())
+    }
+
+    fn import(
+        db: &mut SQLiteWriter,
+        path: &Path,
+        source_root: Option<&Path>,
+    ) -> anyhow::Result<FileArtifact> {
+        let bytes = std::fs::read(path)?;
+        let mut artifact = FileArtifact::decode(&bytes)?;
+        if let Some(source_root) = source_root {
+            let file = source_root.canonicalize()?.join(&artifact.file);
+            artifact.rename_file(&file.to_string_lossy());
+        }
+        db.import_file_artifact(&artifact)?;
+        Ok(artifact)
+    }
+
+    fn get_reporter(&self) -> ConsoleReporter {
+        ConsoleReporter {
+            skipped_level: Level::None,
+            succeeded_level: if self.verbose {
+                Level::Details
+            } else {
+                Level::None
+            },
+            failed_level: Level::Details,
+            canceled_level: Level::Details,
+        }
+    }
+}
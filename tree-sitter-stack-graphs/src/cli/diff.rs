@@ -0,0 +1,107 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use anyhow::anyhow;
+use clap::Args;
+use clap::ValueHint;
+use stack_graphs::graph::StackGraph;
+use std::path::Path;
+use std::path::PathBuf;
+use tree_sitter_graph::Variables;
+
+use crate::cli::util::ExistingPathBufValueParser;
+use crate::loader::FileReader;
+use crate::loader::Loader;
+use crate::NoCancellation;
+use crate::StackGraphLanguage;
+
+/// Compare the stack graphs constructed for a source file by two versions of the TSG rules
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Path of the TSG file to compare against. Removed nodes and edges are the ones that only
+    /// appear in the graph constructed by these rules.
+    #[clap(
+        value_name = "OLD_TSG_PATH",
+        required = true,
+        value_hint = ValueHint::FilePath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub old_tsg_path: PathBuf,
+
+    /// Source file path.
+    #[clap(
+        value_name = "SOURCE_PATH",
+        required = true,
+        value_hint = ValueHint::FilePath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub source_path: PathBuf,
+}
+
+impl DiffArgs {
+    pub fn new(old_tsg_path: PathBuf, source_path: PathBuf) -> Self {
+        Self {
+            old_tsg_path,
+            source_path,
+        }
+    }
+
+    pub fn run(self, mut loader: Loader) -> anyhow::Result<()> {
+        let mut file_reader = FileReader::new();
+        let lc = match loader
+            .load_for_file(&self.source_path, &mut file_reader, &NoCancellation)?
+            .primary
+        {
+            Some(lc) => lc,
+            None => return Err(anyhow!("No stack graph language found")),
+        };
+        let source = file_reader.get(&self.source_path)?;
+
+        let old_tsg_source = std::fs::read_to_string(&self.old_tsg_path)?;
+        let old_sgl = StackGraphLanguage::from_source(
+            lc.language,
+            self.old_tsg_path.clone(),
+            &old_tsg_source,
+        )
+        .map_err(|err| {
+            anyhow!(
+                "{}",
+                err.display_pretty(&self.old_tsg_path, &old_tsg_source)
+            )
+        })?;
+
+        let old_graph = build_stack_graph(&old_sgl, &self.source_path, source)?;
+        let new_graph = build_stack_graph(&lc.sgl, &self.source_path, source)?;
+        let diff = old_graph.diff(&new_graph);
+        if diff.is_empty() {
+            println!("No differences");
+        } else {
+            print!("{}", diff.display(&old_graph, &new_graph));
+        }
+        Ok(())
+    }
+}
+
+fn build_stack_graph(
+    sgl: &StackGraphLanguage,
+    source_path: &Path,
+    source: &str,
+) -> anyhow::Result<StackGraph> {
+    let mut graph = StackGraph::new();
+    let file = graph
+        .add_file(&source_path.to_string_lossy())
+        .expect("file not present in empty graph");
+    let globals = Variables::new();
+    sgl.build_stack_graph_into(&mut graph, file, source, &globals, &NoCancellation)
+        .map_err(|err| {
+            anyhow!(
+                "{}",
+                err.display_pretty(source_path, source, sgl.tsg_path(), sgl.tsg_source())
+            )
+        })?;
+    Ok(graph)
+}
@@ -0,0 +1,584 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use anyhow::anyhow;
+use clap::Args;
+use clap::ValueHint;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Once;
+use std::time::Duration;
+use std::time::Instant;
+use tree_sitter_graph::Variables;
+
+use crate::cli::util::duration_from_seconds_str;
+use crate::cli::util::iter_files_and_directories;
+use crate::cli::util::ExistingPathBufValueParser;
+use crate::loader::FileReader;
+use crate::loader::Loader;
+use crate::BuildError;
+use crate::CancelAfterDuration;
+use crate::CancellationError;
+use crate::CancellationFlag;
+use crate::NoCancellation;
+use crate::StackGraphLanguage;
+
+/// Maximum number of inputs that are checked while minimizing a single finding.
+const MAX_MINIMIZATION_CHECKS: usize = 500;
+
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+thread_local! {
+    /// Whether panics on this thread are caught by a [`Checker`].
+    static CATCHING_PANICS: Cell<bool> = const { Cell::new(false) };
+    /// The message and location of the last panic caught on this thread.
+    static CAUGHT_PANIC: RefCell<Option<(String, Option<String>)>> = const { RefCell::new(None) };
+}
+
+/// Fuzz the stack graph construction rules, by mutating source files and checking that graphs
+/// and partial paths can be computed for the mutated files
+#[derive(Args)]
+pub struct FuzzArgs {
+    /// Seed corpus of source files or directories. Every file is mutated independently.
+    #[clap(
+        value_name = "SOURCE_PATH",
+        required = true,
+        value_hint = ValueHint::AnyPath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub source_paths: Vec<PathBuf>,
+
+    /// Number of mutated inputs to check per seed file.
+    #[clap(long, short = 'n', value_name = "N", default_value_t = 100)]
+    pub iterations: usize,
+
+    /// Seed for the random mutations. Runs with the same seed and corpus check the same inputs.
+    #[clap(long, value_name = "SEED", default_value_t = 0)]
+    pub seed: u64,
+
+    /// Maximum number of syntax node mutations applied to a seed file to create an input.
+    #[clap(long, value_name = "N", default_value_t = 3)]
+    pub max_mutations: usize,
+
+    /// Maximum time in seconds to build the graph and partial paths of a single input. Inputs
+    /// that take longer are reported as timeouts.
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        value_parser = duration_from_seconds_str,
+        default_value = "5",
+    )]
+    pub max_input_time: Duration,
+
+    /// Maximum number of partial paths of a single input. Inputs with more partial paths are
+    /// reported as path explosions.
+    #[clap(long, value_name = "N", default_value_t = 100_000)]
+    pub max_paths: usize,
+
+    /// Maximum time in seconds to minimize the input of a single finding. When the time is up,
+    /// the smallest input found so far is reported.
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        value_parser = duration_from_seconds_str,
+        default_value = "60",
+    )]
+    pub max_minimization_time: Duration,
+
+    /// Directory to write minimized inputs of findings to. If not given, minimized inputs are
+    /// printed.
+    #[clap(
+        long,
+        value_name = "DIR",
+        value_hint = ValueHint::DirPath,
+    )]
+    pub output: Option<PathBuf>,
+}
+
+impl FuzzArgs {
+    pub fn new(source_paths: Vec<PathBuf>) -> Self {
+        Self {
+            source_paths,
+            iterations: 100,
+            seed: 0,
+            max_mutations: 3,
+            max_input_time: Duration::from_secs(5),
+            max_paths: 100_000,
+            max_minimization_time: Duration::from_secs(60),
+            output: None,
+        }
+    }
+
+    pub fn run(self, mut loader: Loader) -> anyhow::Result<()> {
+        let mut stats = FuzzStats::default();
+        let mut finding_count = 0;
+        for (_, source_path, _) in iter_files_and_directories(self.source_paths.clone()) {
+            finding_count += self.fuzz_file(&source_path, &mut loader, &mut stats)?;
+        }
+
+        println!(
+            "Checked {} inputs from {} seed files: {} findings, {} inputs with parse errors, {} inputs rejected",
+            stats.input_count,
+            stats.seed_count,
+            finding_count,
+            stats.parse_error_count,
+            stats.rejected_count,
+        );
+        if finding_count > 0 {
+            return Err(anyhow!("{} findings", finding_count));
+        }
+        Ok(())
+    }
+
+    /// Fuzz a single seed file, and return the number of findings.
+    fn fuzz_file(
+        &self,
+        source_path: &Path,
+        loader: &mut Loader,
+        stats: &mut FuzzStats,
+    ) -> anyhow::Result<usize> {
+        let mut file_reader = FileReader::new();
+        let lc = match loader
+            .load_for_file(source_path, &mut file_reader, &NoCancellation)?
+            .primary
+        {
+            Some(lc) => lc,
+            None => return Ok(0),
+        };
+        let seed_source = file_reader.get(source_path)?;
+        stats.seed_count += 1;
+
+        let checker = Checker::new(&lc.sgl, source_path, self.max_input_time, self.max_paths);
+        let mut rng = Rng::new(self.seed ^ hash_path(source_path));
+        let mut signatures = HashSet::new();
+        let mut finding_count = 0;
+        for _ in 0..self.iterations {
+            let mutation_count = 1 + rng.below(self.max_mutations.max(1));
+            let mut input = seed_source.to_string();
+            for _ in 0..mutation_count {
+                input = mutate(&lc.sgl, &input, &mut rng)?;
+            }
+            stats.input_count += 1;
+            let finding = match checker.check(&input) {
+                Outcome::Ok => continue,
+                Outcome::OkWithParseErrors => {
+                    stats.parse_error_count += 1;
+                    continue;
+                }
+                Outcome::Rejected => {
+                    stats.rejected_count += 1;
+                    continue;
+                }
+                Outcome::Finding(finding) => finding,
+            };
+            if !signatures.insert(finding.signature()) {
+                continue;
+            }
+            finding_count += 1;
+            let (input, finding) = minimize(
+                &checker,
+                &lc.sgl,
+                input,
+                finding,
+                self.max_minimization_time,
+            )?;
+            self.report(source_path, finding_count, &finding, &input)?;
+        }
+        Ok(finding_count)
+    }
+
+    fn report(
+        &self,
+        source_path: &Path,
+        index: usize,
+        finding: &Finding,
+        input: &str,
+    ) -> anyhow::Result<()> {
+        println!("{}: {}", source_path.display(), finding);
+        if let Finding::BuildError { details, .. } = finding {
+            for line in details.lines() {
+                println!("  {}", line);
+            }
+        }
+        match &self.output {
+            Some(output) => {
+                let mut file_name = source_path
+                    .file_stem()
+                    .map(|s| s.to_os_string())
+                    .unwrap_or_default();
+                file_name.push(format!("-{}", index));
+                if let Some(extension) = source_path.extension() {
+                    file_name.push(".");
+                    file_name.push(extension);
+                }
+                let repro_path = output.join(file_name);
+                std::fs::create_dir_all(output)?;
+                std::fs::write(&repro_path, input)?;
+                println!("  minimized input at {}", repro_path.display());
+            }
+            None => {
+                println!("  minimized input:");
+                for line in input.lines() {
+                    println!("    {}", line);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct FuzzStats {
+    seed_count: usize,
+    input_count: usize,
+    parse_error_count: usize,
+    rejected_count: usize,
+}
+
+/// A problem found while building the graph or partial paths of an input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Finding {
+    Panic {
+        message: String,
+        location: Option<String>,
+    },
+    BuildError {
+        message: String,
+        details: String,
+    },
+    Timeout(&'static str),
+    PathExplosion(usize),
+}
+
+impl Finding {
+    /// Returns a signature that is used to deduplicate findings, and to check that a minimized
+    /// input still exhibits the same problem. The signature does not contain source locations,
+    /// because those change when inputs are mutated. Panics are identified by the location of the
+    /// panic in the code, because panic messages often contain values that depend on the input.
+    pub fn signature(&self) -> String {
+        match self {
+            Self::Panic {
+                location: Some(location),
+                ..
+            } => format!("panic at {}", location),
+            Self::Panic { message, .. } => format!("panic: {}", strip_locations(message)),
+            Self::BuildError { message, .. } => {
+                format!("build error: {}", strip_locations(message))
+            }
+            Self::Timeout(at) => format!("timeout: {}", at),
+            Self::PathExplosion(_) => "path explosion".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Panic { message, location } => {
+                write!(f, "panic: {}", message)?;
+                if let Some(location) = location {
+                    write!(f, " at {}", location)?;
+                }
+                Ok(())
+            }
+            Self::BuildError { message, .. } => write!(f, "build error: {}", message),
+            Self::Timeout(at) => write!(f, "timeout: {}", at),
+            Self::PathExplosion(count) => {
+                write!(f, "path explosion: more than {} partial paths", count)
+            }
+        }
+    }
+}
+
+/// Removes anything that looks like a source position from a message.
+pub fn strip_locations(message: &str) -> String {
+    message
+        .split(|c: char| c == '(' || c == ')' || c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty() && !part.chars().all(|c| c.is_ascii_digit()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The outcome of checking an input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Ok,
+    /// The input has parse errors, and the rules were executed for the well-formed parts of it.
+    OkWithParseErrors,
+    /// The input could not be parsed at all, so the rules were not executed.
+    Rejected,
+    Finding(Finding),
+}
+
+/// Checks that the graph and partial paths of inputs can be computed without problems.
+pub struct Checker<'a> {
+    sgl: &'a StackGraphLanguage,
+    source_path: &'a Path,
+    max_input_time: Duration,
+    max_paths: usize,
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(
+        sgl: &'a StackGraphLanguage,
+        source_path: &'a Path,
+        max_input_time: Duration,
+        max_paths: usize,
+    ) -> Self {
+        Self {
+            sgl,
+            source_path,
+            max_input_time,
+            max_paths,
+        }
+    }
+
+    /// Checks the input. Panics are caught and reported as findings. The panic output is only
+    /// suppressed for the current thread, and only while checking.
+    pub fn check(&self, source: &str) -> Outcome {
+        install_panic_hook();
+        CATCHING_PANICS.with(|catching| catching.set(true));
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| self.check_inner(source)));
+        CATCHING_PANICS.with(|catching| catching.set(false));
+        match result {
+            Ok(outcome) => outcome,
+            Err(payload) => {
+                let (message, location) = CAUGHT_PANIC
+                    .with(|caught| caught.borrow_mut().take())
+                    .unwrap_or_else(|| (panic_message(payload.as_ref()), None));
+                Outcome::Finding(Finding::Panic { message, location })
+            }
+        }
+    }
+
+    fn check_inner(&self, source: &str) -> Outcome {
+        let cancellation_flag = CancelAfterDuration::new(self.max_input_time);
+        let mut graph = StackGraph::new();
+        let file = graph
+            .add_file(&self.source_path.to_string_lossy())
+            .expect("file not present in empty graph");
+        let globals = Variables::new();
+        // parse errors are tolerated, so that mutations that break the syntax still exercise the
+        // rules for the rest of the input
+        let has_parse_errors = match self
+            .sgl
+            .builder_into_stack_graph(&mut graph, file, source)
+            .build_tolerant(&globals, &cancellation_flag)
+        {
+            Ok(parse_errors) => parse_errors.is_some(),
+            Err(BuildError::ParseErrors(_)) | Err(BuildError::ParseError) => {
+                return Outcome::Rejected
+            }
+            Err(BuildError::Cancelled(at)) => return Outcome::Finding(Finding::Timeout(at)),
+            Err(err) => {
+                return Outcome::Finding(Finding::BuildError {
+                    message: err.to_string(),
+                    details: err
+                        .display_pretty(
+                            self.source_path,
+                            source,
+                            self.sgl.tsg_path(),
+                            self.sgl.tsg_source(),
+                        )
+                        .to_string(),
+                })
+            }
+        };
+
+        let path_count = AtomicUsize::new(0);
+        let path_limit = PathLimit {
+            count: &path_count,
+            max: self.max_paths,
+        };
+        let cancellation_flag =
+            &cancellation_flag as &dyn CancellationFlag | &path_limit as &dyn CancellationFlag;
+        let mut partials = PartialPaths::new();
+        let result = ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            &(&cancellation_flag as &dyn CancellationFlag),
+            |_, _, _| {
+                path_count.fetch_add(1, Ordering::Relaxed);
+            },
+        );
+        if path_count.load(Ordering::Relaxed) > self.max_paths {
+            return Outcome::Finding(Finding::PathExplosion(self.max_paths));
+        }
+        match result {
+            Ok(_) if has_parse_errors => Outcome::OkWithParseErrors,
+            Ok(_) => Outcome::Ok,
+            Err(err) => Outcome::Finding(Finding::Timeout(err.0)),
+        }
+    }
+}
+
+/// Installs a panic hook that records the message and location of panics on threads that are
+/// checking an input, instead of printing them. Other panics are passed to the previous hook.
+fn install_panic_hook() {
+    INSTALL_PANIC_HOOK.call_once(|| {
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !CATCHING_PANICS.with(|catching| catching.get()) {
+                return previous_hook(info);
+            }
+            let message = panic_message(info.payload());
+            let location = info.location().map(|location| location.to_string());
+            CAUGHT_PANIC.with(|caught| *caught.borrow_mut() = Some((message, location)));
+        }));
+    });
+}
+
+/// Returns the message of a panic payload.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Cancellation flag that cancels path finding when too many paths were found.
+struct PathLimit<'a> {
+    count: &'a AtomicUsize,
+    max: usize,
+}
+
+impl CancellationFlag for PathLimit<'_> {
+    fn check(&self, at: &'static str) -> Result<(), CancellationError> {
+        if self.count.load(Ordering::Relaxed) > self.max {
+            return Err(CancellationError(at));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the byte ranges of all named syntax nodes in the source, in pre-order.
+fn named_node_ranges(sgl: &StackGraphLanguage, source: &str) -> anyhow::Result<Vec<Range<usize>>> {
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(sgl.language())?;
+    let tree = parser
+        .parse(source, None)
+        .ok_or_else(|| anyhow!("Failed to parse source"))?;
+    let mut ranges = Vec::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        if node.is_named() && node.parent().is_some() && !node.byte_range().is_empty() {
+            ranges.push(node.byte_range());
+        }
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        loop {
+            if !cursor.goto_parent() {
+                return Ok(ranges);
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+}
+
+/// Mutates the source by deleting or duplicating a random named syntax node.
+pub fn mutate(sgl: &StackGraphLanguage, source: &str, rng: &mut Rng) -> anyhow::Result<String> {
+    let ranges = named_node_ranges(sgl, source)?;
+    if ranges.is_empty() {
+        return Ok(source.to_string());
+    }
+    let range = ranges[rng.below(ranges.len())].clone();
+    let mut result = String::with_capacity(source.len() + range.len());
+    if rng.below(2) == 0 {
+        result.push_str(&source[..range.start]);
+        result.push_str(&source[range.end..]);
+    } else {
+        result.push_str(&source[..range.end]);
+        result.push(' ');
+        result.push_str(&source[range.clone()]);
+        result.push_str(&source[range.end..]);
+    }
+    Ok(result)
+}
+
+/// Minimizes an input by repeatedly deleting syntax nodes, as long as the input still has a
+/// finding with the same signature. Minimization stops after a fixed number of checks, or when the
+/// given time is up. Returns the minimized input and its finding.
+pub fn minimize(
+    checker: &Checker,
+    sgl: &StackGraphLanguage,
+    mut input: String,
+    mut finding: Finding,
+    max_time: Duration,
+) -> anyhow::Result<(String, Finding)> {
+    let signature = finding.signature();
+    let start = Instant::now();
+    let mut checks = 0;
+    'minimize: loop {
+        for range in named_node_ranges(sgl, &input)? {
+            if checks >= MAX_MINIMIZATION_CHECKS || start.elapsed() >= max_time {
+                break 'minimize;
+            }
+            checks += 1;
+            let candidate = format!("{}{}", &input[..range.start], &input[range.end..]);
+            if let Outcome::Finding(candidate_finding) = checker.check(&candidate) {
+                if candidate_finding.signature() == signature {
+                    input = candidate;
+                    finding = candidate_finding;
+                    continue 'minimize;
+                }
+            }
+        }
+        break;
+    }
+    Ok((input, finding))
+}
+
+/// Hashes the path with FNV-1a, which is stable across platforms and compiler versions, so that
+/// runs are reproducible.
+fn hash_path(path: &Path) -> u64 {
+    path.to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+/// A small, deterministic pseudo-random number generator (xorshift64*).
+pub struct Rng(u64);
+
+impl Rng {
+    /// Creates a generator for the given seed. Generators with the same seed produce the same
+    /// sequence of numbers.
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    /// Returns a number in the range `0..n`, which must not be empty.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
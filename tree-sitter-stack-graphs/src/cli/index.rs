@@ -7,18 +7,26 @@
 
 use clap::Args;
 use clap::ValueHint;
+use itertools::Itertools;
 use stack_graphs::arena::Handle;
 use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::stats::GraphStats;
+use stack_graphs::stats::PartialPathStats;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::storage::artifact::ARTIFACT_EXTENSION;
 use stack_graphs::storage::FileStatus;
 use stack_graphs::storage::SQLiteWriter;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
 use std::time::Duration;
 use thiserror::Error;
+use tree_sitter_graph::parse_error::ParseError;
+use tree_sitter_graph::parse_error::TreeWithParseErrorVec;
 use tree_sitter_graph::Variables;
 
 use crate::cli::util::duration_from_seconds_str;
@@ -31,14 +39,28 @@ use crate::cli::util::wait_for_input;
 use crate::cli::util::BuildErrorWithSource;
 use crate::cli::util::CLIFileReporter;
 use crate::cli::util::ExistingPathBufValueParser;
+use crate::loader::builtins_selection;
 use crate::loader::FileLanguageConfigurations;
 use crate::loader::FileReader;
+use crate::loader::LanguageConfiguration;
 use crate::loader::Loader;
+use crate::loader::BUILTINS_VAR;
+use crate::profile::BuildProfile;
+use crate::util::DisplayParseErrorsPretty;
 use crate::BuildError;
 use crate::CancelAfterDuration;
 use crate::CancellationFlag;
 use crate::NoCancellation;
 
+/// The name of the project configuration file, which is read from the root of the indexed source
+/// directories. Its `[globals]` section defines the globals used to build the stack graphs of the
+/// project's files. The [`BUILTINS_VAR`][] global selects the builtins variants of the project.
+pub const PROJECT_CONFIG_FILE: &str = "stack-graphs.cfg";
+
+/// The tag of builtins files in the database. Builtins are stored again whenever files of their
+/// language are first indexed in a run, so the tag is not used to detect changes.
+const BUILTINS_TAG: &str = "builtins";
+
 #[derive(Args)]
 pub struct IndexArgs {
     /// Source file or directory paths to index.
@@ -66,6 +88,36 @@ pub struct IndexArgs {
     #[clap(long, short = 'f')]
     pub force: bool,
 
+    /// Check the constructed graphs for structural problems, such as unreachable definitions.
+    #[clap(long)]
+    pub lint: bool,
+
+    /// Index files with parse errors, by building graphs for the well-formed parts of the source
+    /// only. Such files are stored as indexed with warnings.
+    #[clap(long)]
+    pub tolerate_parse_errors: bool,
+
+    /// Fail on attributes that are not expected on stack graph nodes, instead of reporting them
+    /// as warnings.
+    #[clap(long)]
+    pub strict_attributes: bool,
+
+    /// Builtins variants to index in addition to the default builtins, as a comma-separated list
+    /// of names. Overrides the `BUILTINS` global of the project configuration. The builtins are
+    /// stored in the database, so that queries resolve references to them.
+    #[clap(long, value_name = "NAMES")]
+    pub builtins_variants: Option<String>,
+
+    /// Write a portable artifact with the indexing result of every file to the given directory.
+    /// Files are named relative to the indexed directory in the artifacts, which can be imported
+    /// into another database with `database import`.
+    #[clap(
+        long,
+        value_name = "ARTIFACT_DIR",
+        value_hint = ValueHint::DirPath,
+    )]
+    pub emit_artifacts: Option<PathBuf>,
+
     /// Hide details of indexing errors on files.
     #[clap(long)]
     pub hide_error_details: bool,
@@ -81,6 +133,11 @@ pub struct IndexArgs {
     /// Wait for user input before starting analysis. Useful for profiling.
     #[clap(long)]
     pub wait_at_start: bool,
+
+    /// Print a profile of the execution of the TSG rules, with match counts, match times, and
+    /// created nodes and edges per stanza. Stanzas are sorted by the time spent matching them.
+    #[clap(long)]
+    pub profile: bool,
 }
 
 impl IndexArgs {
@@ -88,11 +145,17 @@ impl IndexArgs {
         Self {
             source_paths,
             force: false,
+            lint: false,
+            tolerate_parse_errors: false,
+            strict_attributes: false,
+            builtins_variants: None,
+            emit_artifacts: None,
             continue_from: None,
             verbose: false,
             hide_error_details: false,
             max_file_time: None,
             wait_at_start: false,
+            profile: false,
         }
     }
 
@@ -104,6 +167,13 @@ impl IndexArgs {
         let reporter = self.get_reporter();
         let mut indexer = Indexer::new(&mut db, &mut loader, &reporter);
         indexer.force = self.force;
+        indexer.lint = self.lint;
+        indexer.tolerate_parse_errors = self.tolerate_parse_errors;
+        indexer.strict_attributes = self.strict_attributes;
+        indexer.builtins_selection = self.builtins_variants.as_deref().map(builtins_selection);
+        indexer.emit_artifacts = self.emit_artifacts;
+        indexer.collect_stats = self.verbose;
+        indexer.collect_profile = self.profile;
         indexer.max_file_time = self.max_file_time;
 
         let source_paths = self
@@ -112,6 +182,15 @@ impl IndexArgs {
             .map(|p| p.canonicalize())
             .collect::<std::result::Result<Vec<_>, _>>()?;
         indexer.index_all(source_paths, self.continue_from, &NoCancellation)?;
+        if self.verbose {
+            println!();
+            println!("{}", indexer.graph_stats());
+            println!("{}", indexer.path_stats());
+        }
+        if self.profile {
+            println!();
+            print!("{}", indexer.profile());
+        }
         Ok(())
     }
 
@@ -147,8 +226,31 @@ pub struct Indexer<'a> {
     reporter: &'a dyn Reporter,
     /// Index files, even if they already exist in the database.
     pub force: bool,
+    /// Report structural problems in the graphs of indexed files.
+    pub lint: bool,
+    /// Build graphs for the well-formed parts of files with parse errors.
+    pub tolerate_parse_errors: bool,
+    /// Fail on unexpected node attributes, instead of reporting them as warnings.
+    pub strict_attributes: bool,
+    /// Builtins variants to index in addition to the default builtins of every language. If not
+    /// set, the variants are selected by the [`BUILTINS_VAR`][] global of the project
+    /// configuration, see [`PROJECT_CONFIG_FILE`][].
+    pub builtins_selection: Option<Vec<String>>,
+    /// The globals of the project configurations of the source roots indexed so far.
+    projects: HashMap<PathBuf, Variables<'static>>,
+    /// The builtins indexed so far, by language and selection.
+    indexed_builtins: HashSet<(PathBuf, Vec<String>)>,
+    /// Directory to write file artifacts to.
+    pub emit_artifacts: Option<PathBuf>,
     /// Maximum time per file.
     pub max_file_time: Option<Duration>,
+    /// Collect statistics about the graphs and partial paths of indexed files.
+    pub collect_stats: bool,
+    graph_stats: GraphStats,
+    path_stats: PartialPathStats,
+    /// Collect a profile of the execution of the TSG rules for indexed files.
+    pub collect_profile: bool,
+    profile: BuildProfile,
 }
 
 impl<'a> Indexer<'a> {
@@ -162,10 +264,40 @@ impl<'a> Indexer<'a> {
             loader,
             reporter,
             force: false,
+            lint: false,
+            tolerate_parse_errors: false,
+            strict_attributes: false,
+            builtins_selection: None,
+            projects: HashMap::new(),
+            indexed_builtins: HashSet::new(),
+            emit_artifacts: None,
             max_file_time: None,
+            collect_stats: false,
+            graph_stats: GraphStats::default(),
+            path_stats: PartialPathStats::default(),
+            collect_profile: false,
+            profile: BuildProfile::new(),
         }
     }
 
+    /// Returns statistics about the graphs of the files indexed so far. Only collected if
+    /// `collect_stats` is set.
+    pub fn graph_stats(&self) -> &GraphStats {
+        &self.graph_stats
+    }
+
+    /// Returns statistics about the partial paths of the files indexed so far. Only collected if
+    /// `collect_stats` is set.
+    pub fn path_stats(&self) -> &PartialPathStats {
+        &self.path_stats
+    }
+
+    /// Returns the profile of the TSG rules for the files indexed so far. Only collected if
+    /// `collect_profile` is set.
+    pub fn profile(&self) -> &BuildProfile {
+        &self.profile
+    }
+
     pub fn index_all<P, IP, Q>(
         &mut self,
         source_paths: IP,
@@ -233,9 +365,9 @@ impl<'a> Indexer<'a> {
             cancellation_flag,
             file_status,
         ) {
-            ok @ Ok(_) => {
+            Ok(_) => {
                 file_status.assert_reported();
-                ok
+                self.emit_artifact(source_root, source_path)
             }
             err @ Err(_) => {
                 file_status.failure_if_processing("error", Some(&format!("Error analyzing file {}. To continue analysis from this file later, add: --continue-from {}", source_path.display(), source_path.display())));
@@ -280,6 +412,26 @@ impl<'a> Indexer<'a> {
             Err(e) => return Err(IndexError::LoadError(e)),
         };
 
+        let globals = Self::project_globals(&mut self.projects, source_root)?;
+        if let Some(lc) = lcs.primary {
+            let selection = match &self.builtins_selection {
+                Some(selection) => selection.clone(),
+                None => globals
+                    .get(&BUILTINS_VAR.into())
+                    .and_then(|value| value.as_str().ok())
+                    .map(builtins_selection)
+                    .unwrap_or_default(),
+            };
+            Self::index_builtins(
+                self.db,
+                &mut self.indexed_builtins,
+                lc,
+                &selection,
+                cancellation_flag,
+                file_status,
+            )?;
+        }
+
         let source = file_reader.get(source_path)?;
         let tag = sha1(source);
 
@@ -288,7 +440,7 @@ impl<'a> Indexer<'a> {
             .status_for_file(&source_path.to_string_lossy(), Some(&tag))?
         {
             FileStatus::Missing => "indexed",
-            FileStatus::Indexed => {
+            FileStatus::Indexed | FileStatus::IndexedWithWarnings(_) => {
                 if self.force {
                     "reindexed"
                 } else {
@@ -323,10 +475,21 @@ impl<'a> Indexer<'a> {
             source_path,
             &source,
             lcs,
+            BuildOptions {
+                globals,
+                profile: if self.collect_profile {
+                    Some(&mut self.profile)
+                } else {
+                    None
+                },
+                tolerate_parse_errors: self.tolerate_parse_errors,
+                strict_attributes: self.strict_attributes,
+            },
             &cancellation_flag,
         );
-        if let Err(err) = result {
-            match err.inner {
+        let build_warnings = match result {
+            Ok(build_warnings) => build_warnings,
+            Err(err) => match err.inner {
                 BuildError::Cancelled(_) => {
                     file_status.warning("parsing timed out", None);
                     self.db
@@ -346,7 +509,7 @@ impl<'a> Indexer<'a> {
                     file_status.failure("failed to build stack graph", Some(&err.display_pretty()));
                     return Err(IndexError::StackGraph);
                 }
-            }
+            },
         };
 
         let mut partials = PartialPaths::new();
@@ -372,11 +535,108 @@ impl<'a> Indexer<'a> {
             }
         }
 
-        self.db
-            .store_result_for_file(&graph, file, &tag, &mut partials, &paths)?;
+        let mut stored_warnings = Vec::new();
+        if let Some(parse_errors) = &build_warnings.parse_errors {
+            stored_warnings.push(parse_warnings(parse_errors, source_path));
+        }
+        stored_warnings.extend(build_warnings.attributes.iter().cloned());
+        let stored_warnings = if stored_warnings.is_empty() {
+            None
+        } else {
+            Some(stored_warnings.join("\n"))
+        };
+        self.db.store_result_with_warnings_for_file(
+            &graph,
+            file,
+            &tag,
+            stored_warnings.as_deref(),
+            &mut partials,
+            &paths,
+        )?;
 
-        file_status.success(success_status, None);
+        if self.collect_stats {
+            self.graph_stats += graph.file_stats(file);
+            for path in &paths {
+                self.path_stats.add_partial_path(&graph, path);
+            }
+        }
+
+        let lint_issues = if self.lint {
+            graph.validate_file(file)
+        } else {
+            Vec::default()
+        };
+        let mut warnings = Vec::new();
+        let mut details = Vec::new();
+        if let Some(parse_errors) = &build_warnings.parse_errors {
+            warnings.push(format!("{} parse errors", parse_errors.errors().len()));
+            details.push(
+                DisplayParseErrorsPretty {
+                    parse_errors,
+                    path: source_path,
+                    source,
+                    max_errors: crate::MAX_PARSE_ERRORS,
+                }
+                .to_string(),
+            );
+        }
+        if !build_warnings.attributes.is_empty() {
+            warnings.push(format!(
+                "{} unexpected attributes",
+                build_warnings.attributes.len()
+            ));
+            details.extend(build_warnings.attributes.iter().cloned());
+        }
+        if !lint_issues.is_empty() {
+            warnings.push(format!("{} lint issues", lint_issues.len()));
+            details.extend(
+                lint_issues
+                    .iter()
+                    .map(|issue| issue.display(&graph).to_string()),
+            );
+        }
+        if warnings.is_empty() {
+            file_status.success(success_status, None);
+        } else {
+            file_status.warning(
+                &format!("{} with {}", success_status, warnings.join(" and ")),
+                Some(&details.join("\n")),
+            );
+        }
+
+        Ok(())
+    }
 
+    /// Write the artifact for the file, if artifacts are requested and the file is in the
+    /// database. The artifact names the file relative to the source root, so that artifacts
+    /// do not depend on where the sources were checked out.
+    fn emit_artifact(&mut self, source_root: &Path, source_path: &Path) -> Result<()> {
+        let artifact_dir = match &self.emit_artifacts {
+            Some(artifact_dir) => artifact_dir,
+            None => return Ok(()),
+        };
+        let mut artifact = match self
+            .db
+            .export_file_artifact(&source_path.to_string_lossy())?
+        {
+            Some(artifact) => artifact,
+            None => return Ok(()),
+        };
+        let relative_path = source_path
+            .strip_prefix(source_root)
+            .unwrap_or(source_path)
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(c) => Some(c.to_string_lossy()),
+                _ => None,
+            })
+            .join("/");
+        artifact.rename_file(&relative_path);
+        let artifact_path = artifact_path(artifact_dir, Path::new(&relative_path));
+        if let Some(parent) = artifact_path.parent() {
+            std::fs::create_dir_all(parent).map_err(IndexError::ArtifactError)?;
+        }
+        std::fs::write(&artifact_path, artifact.encode()?).map_err(IndexError::ArtifactError)?;
         Ok(())
     }
 
@@ -387,20 +647,41 @@ impl<'a> Indexer<'a> {
         source_path: &Path,
         source: &'b str,
         lcs: FileLanguageConfigurations<'b>,
+        options: BuildOptions,
         cancellation_flag: &dyn CancellationFlag,
-    ) -> std::result::Result<(), BuildErrorWithSource<'b>> {
+    ) -> std::result::Result<BuildWarnings, BuildErrorWithSource<'b>> {
         let relative_source_path = source_path.strip_prefix(source_root).unwrap();
+        let mut warnings = BuildWarnings::default();
         if let Some(lc) = lcs.primary {
-            let globals = Variables::new();
-            lc.sgl
-                .build_stack_graph_into(graph, file, source, &globals, cancellation_flag)
-                .map_err(|inner| BuildErrorWithSource {
-                    inner,
-                    source_path: source_path.to_path_buf(),
-                    source_str: source,
-                    tsg_path: lc.sgl.tsg_path().to_path_buf(),
-                    tsg_str: &lc.sgl.tsg_source(),
-                })?;
+            let mut attribute_diagnostics = Vec::new();
+            let mut builder = lc.sgl.builder_into_stack_graph(graph, file, source);
+            if let Some(profile) = options.profile {
+                builder.set_profile(profile);
+            }
+            builder.set_attribute_diagnostics(&mut attribute_diagnostics);
+            builder.set_strict_attributes(options.strict_attributes);
+            let result = if options.tolerate_parse_errors {
+                builder.build_tolerant(options.globals, cancellation_flag)
+            } else {
+                builder
+                    .build(options.globals, cancellation_flag)
+                    .map(|_| None)
+            };
+            warnings.parse_errors = result.map_err(|inner| BuildErrorWithSource {
+                inner,
+                source_path: source_path.to_path_buf(),
+                source_str: source,
+                tsg_path: lc.sgl.tsg_path().to_path_buf(),
+                tsg_str: &lc.sgl.tsg_source(),
+            })?;
+            warnings.attributes = attribute_diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    diagnostic
+                        .display_with_tsg_path(lc.sgl.tsg_path())
+                        .to_string()
+                })
+                .collect();
         }
         for (_, fa) in lcs.secondary {
             fa.build_stack_graph_into(
@@ -420,6 +701,122 @@ impl<'a> Indexer<'a> {
                 tsg_str: "",
             })?;
         }
+        Ok(warnings)
+    }
+
+    /// Returns the globals of the project configuration of the given source root. The
+    /// configuration is read when the source root is first seen.
+    fn project_globals<'p>(
+        projects: &'p mut HashMap<PathBuf, Variables<'static>>,
+        source_root: &Path,
+    ) -> Result<&'p Variables<'static>> {
+        if !projects.contains_key(source_root) {
+            let mut globals = Variables::new();
+            let config_path = source_root.join(PROJECT_CONFIG_FILE);
+            if config_path.is_file() {
+                let config = std::fs::read_to_string(&config_path)?;
+                Loader::load_globals_from_config_str(&config, &mut globals)
+                    .map_err(IndexError::ProjectConfigError)?;
+            }
+            projects.insert(source_root.to_path_buf(), globals);
+        }
+        Ok(&projects[source_root])
+    }
+
+    /// Stores the default builtins and the selected builtins variants of the language in the
+    /// database, unless they were stored for the same selection before. Selected variants that
+    /// the language does not have are ignored. The partial paths of precompiled builtins are
+    /// stored as they are, instead of being computed again.
+    fn index_builtins(
+        db: &mut SQLiteWriter,
+        indexed_builtins: &mut HashSet<(PathBuf, Vec<String>)>,
+        lc: &LanguageConfiguration,
+        selection: &[String],
+        cancellation_flag: &dyn CancellationFlag,
+        file_status: &mut CLIFileReporter,
+    ) -> Result<()> {
+        let selection = selection
+            .iter()
+            .filter(|name| lc.builtins_variants.contains(name))
+            .cloned()
+            .sorted()
+            .dedup()
+            .collect::<Vec<_>>();
+        if !indexed_builtins.insert((lc.sgl.tsg_path().to_path_buf(), selection.clone())) {
+            return Ok(());
+        }
+
+        let mut graph = StackGraph::new();
+        graph
+            .add_from_graph(&lc.builtins)
+            .expect("builtins not present in empty graph");
+        let variants = match lc.load_builtins_variants(&selection, cancellation_flag) {
+            Ok(variants) => variants,
+            Err(err) => {
+                file_status.failure(
+                    "failed to load builtins",
+                    Some(&err.display_pretty().to_string()),
+                );
+                return Err(IndexError::Builtins);
+            }
+        };
+        if let Err(file) = graph.add_from_graph(&variants) {
+            file_status.failure(
+                "failed to load builtins",
+                Some(&format!("Duplicate builtins file {}", graph[file])),
+            );
+            return Err(IndexError::Builtins);
+        }
+
+        let mut partials = PartialPaths::new();
+        let mut precompiled_paths = HashMap::new();
+        if let Some(builtins_paths) = &lc.builtins_paths {
+            // builtins files are named after their path, and file names are unique in the graph
+            for file in lc.builtins.iter_files() {
+                if let Some(file) = graph.get_file(lc.builtins[file].name()) {
+                    precompiled_paths.insert(file, Vec::new());
+                }
+            }
+            for path in builtins_paths {
+                let path = match path.to_partial_path(&mut graph, &mut partials) {
+                    Ok(path) => path,
+                    Err(err) => {
+                        file_status.failure(
+                            "failed to load builtins",
+                            Some(&format!("Invalid precompiled builtins path: {}", err)),
+                        );
+                        return Err(IndexError::Builtins);
+                    }
+                };
+                let file = graph[path.start_node]
+                    .file()
+                    .or_else(|| graph[path.end_node].file());
+                if let Some(paths) = file.and_then(|file| precompiled_paths.get_mut(&file)) {
+                    paths.push(path);
+                }
+            }
+        }
+
+        let files = graph.iter_files().collect::<Vec<_>>();
+        for file in files {
+            let paths = match precompiled_paths.remove(&file) {
+                Some(paths) => paths,
+                None => {
+                    let mut paths = Vec::new();
+                    ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+                        &graph,
+                        &mut partials,
+                        file,
+                        &cancellation_flag,
+                        |_g, _ps, p| {
+                            paths.push(p.clone());
+                        },
+                    )?;
+                    paths
+                }
+            };
+            db.store_result_for_file(&graph, file, BUILTINS_TAG, &mut partials, &paths)?;
+        }
         Ok(())
     }
 
@@ -440,14 +837,69 @@ impl<'a> Indexer<'a> {
     }
 }
 
+/// Options for building the stack graph of a file.
+struct BuildOptions<'p> {
+    /// The globals of the file's project.
+    globals: &'p Variables<'static>,
+    profile: Option<&'p mut BuildProfile>,
+    tolerate_parse_errors: bool,
+    strict_attributes: bool,
+}
+
+/// The warnings that occurred while building the stack graph of a file.
+#[derive(Default)]
+struct BuildWarnings {
+    /// The tolerated parse errors, if any.
+    parse_errors: Option<TreeWithParseErrorVec>,
+    /// The unexpected node attributes, with their TSG locations.
+    attributes: Vec<String>,
+}
+
+/// Returns a plain description of tolerated parse errors, which is stored as the warnings of the
+/// file in the database.
+fn parse_warnings(parse_errors: &TreeWithParseErrorVec, source_path: &Path) -> String {
+    parse_errors
+        .errors()
+        .iter()
+        .map(|error| {
+            let position = error.node().start_position();
+            format!(
+                "{}:{}:{}: {}",
+                source_path.display(),
+                position.row + 1,
+                position.column + 1,
+                match error {
+                    ParseError::Missing(_) => "missing syntax",
+                    ParseError::Unexpected(_) => "unexpected syntax",
+                }
+            )
+        })
+        .join("\n")
+}
+
+/// Returns the artifact path for a source file, which mirrors the source path relative to the
+/// source root inside the artifact directory.
+fn artifact_path(artifact_dir: &Path, relative_path: &Path) -> PathBuf {
+    let mut artifact_path = artifact_dir.join(relative_path).into_os_string();
+    artifact_path.push(".");
+    artifact_path.push(ARTIFACT_EXTENSION);
+    artifact_path.into()
+}
+
 #[derive(Debug, Error)]
 pub enum IndexError {
     #[error("cancelled at {0}")]
     Cancelled(&'static str),
     #[error("failed to load language")]
     LoadError(#[source] crate::loader::LoadError<'static>),
+    #[error("failed to load builtins")]
+    Builtins,
+    #[error("failed to read project configuration")]
+    ProjectConfigError(#[source] crate::loader::LoadError<'static>),
     #[error("failed to read file")]
     ReadError(#[from] std::io::Error),
+    #[error("failed to write artifact")]
+    ArtifactError(#[source] std::io::Error),
     #[error("failed to build stank graph")]
     StackGraph,
     #[error(transparent)]
@@ -460,4 +912,10 @@ impl From<crate::CancellationError> for IndexError {
     }
 }
 
+impl From<stack_graphs::CancellationError> for IndexError {
+    fn from(value: stack_graphs::CancellationError) -> Self {
+        Self::Cancelled(value.0)
+    }
+}
+
 type Result<T> = std::result::Result<T, IndexError>;
@@ -750,13 +750,13 @@ impl ProjectSettings<'_> {
                     Some(String::from("source.{}")),
                     None,
                     vec![String::from("{}")],
-                    STACK_GRAPHS_TSG_PATH.into(),
-                    STACK_GRAPHS_TSG_SOURCE,
+                    &[(STACK_GRAPHS_TSG_PATH.into(), STACK_GRAPHS_TSG_SOURCE)],
                     Some((
                         STACK_GRAPHS_BUILTINS_PATH.into(),
                         STACK_GRAPHS_BUILTINS_SOURCE,
                     )),
                     Some(STACK_GRAPHS_BUILTINS_CONFIG),
+                    vec![],
                     FileAnalyzers::new(),
                     cancellation_flag,
                 )
@@ -20,11 +20,15 @@ use crate::loader::DEFAULT_TSG_PATHS;
 pub struct PathLoaderArgs {
     /// The TSG file to use for stack graph construction.
     /// If the file extension is omitted, `.tsg` is implicitly added.
+    /// If the path is a directory, the rules are composed from all `.tsg` files in it, in file name order.
+    /// File detection settings are read from the `.cfg` file with the same path, if it exists.
     #[clap(long, value_name = "TSG_PATH")]
     pub tsg: Option<PathBuf>,
 
     /// The builtins file to use for stack graph construction.
     /// If the file extension is omitted, the file extension of the language is implicitly added.
+    /// Builtins variants, which tests and projects select with the `BUILTINS` global, are loaded
+    /// from the directory with the same path, without the file extension.
     #[clap(long, value_name = "BUILTINS_PATH")]
     pub builtins: Option<PathBuf>,
 
@@ -0,0 +1,85 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use clap::Args;
+use clap::Parser;
+use clap::Subcommand;
+use clap::ValueHint;
+use std::path::PathBuf;
+
+use crate::cli::util::ExistingPathBufValueParser;
+use crate::loader::FileLanguageExplanation;
+use crate::loader::FileReader;
+use crate::loader::Loader;
+
+#[derive(Args)]
+pub struct LoaderCommandArgs {
+    #[clap(subcommand)]
+    command: LoaderCommand,
+}
+
+impl LoaderCommandArgs {
+    pub fn run(self, loader: Loader) -> anyhow::Result<()> {
+        self.command.run(loader)
+    }
+}
+
+#[derive(Subcommand)]
+pub enum LoaderCommand {
+    Explain(Explain),
+}
+
+impl LoaderCommand {
+    pub fn run(self, loader: Loader) -> anyhow::Result<()> {
+        match self {
+            Self::Explain(cmd) => cmd.run(loader),
+        }
+    }
+}
+
+/// Explain which language is selected for a source file, and why other languages are not.
+#[derive(Parser)]
+pub struct Explain {
+    /// Source file path to explain language selection for.
+    #[clap(
+        value_name = "SOURCE_PATH",
+        required = true,
+        value_hint = ValueHint::AnyPath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub source_path: PathBuf,
+}
+
+impl Explain {
+    pub fn run(self, mut loader: Loader) -> anyhow::Result<()> {
+        let mut file_reader = FileReader::new();
+        let explanations = loader.explain_file(&self.source_path, &mut file_reader)?;
+        for explanation in &explanations {
+            println!("{}", Self::display(explanation));
+        }
+        if !explanations.iter().any(|e| e.selected) {
+            println!("No language selected for {}", self.source_path.display());
+        }
+        Ok(())
+    }
+
+    fn display(explanation: &FileLanguageExplanation) -> String {
+        let mut name = explanation
+            .scope
+            .clone()
+            .unwrap_or_else(|| format!("[{}]", explanation.file_types.join(", ")));
+        if let Some(language_path) = &explanation.language_path {
+            name = format!("{} at {}", name, language_path.display());
+        }
+        format!(
+            "{} {}: {}",
+            if explanation.selected { "*" } else { " " },
+            name,
+            explanation.file_match
+        )
+    }
+}
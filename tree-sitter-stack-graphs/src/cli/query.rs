@@ -156,7 +156,7 @@ impl<'a> Querier<'a> {
             .db
             .status_for_file(&reference.path.to_string_lossy(), tag.as_ref())?
         {
-            FileStatus::Indexed => {}
+            FileStatus::Indexed | FileStatus::IndexedWithWarnings(_) => {}
             _ => {
                 self.reporter.started(&log_path);
                 self.reporter.failed(&log_path, "file not indexed", None);
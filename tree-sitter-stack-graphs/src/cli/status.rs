@@ -81,6 +81,9 @@ impl StatusArgs {
                 FileStatus::Indexed => {
                     reporter.succeeded(&entry.path, "indexed", None);
                 }
+                FileStatus::IndexedWithWarnings(warnings) => {
+                    reporter.cancelled(&entry.path, "indexed with warnings", Some(warnings));
+                }
                 FileStatus::Error(error) => {
                     reporter.failed(&entry.path, "failed", Some(error));
                 }
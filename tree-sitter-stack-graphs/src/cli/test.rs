@@ -15,29 +15,59 @@ use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::PartialPaths;
 use stack_graphs::serde::Filter;
+use stack_graphs::stats::GraphStats;
+use stack_graphs::stats::PartialPathStats;
 use stack_graphs::stitching::Database;
 use stack_graphs::stitching::DatabaseCandidates;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
 use std::time::Duration;
+use std::time::SystemTime;
 use tree_sitter_graph::Variables;
 
 use crate::cli::util::duration_from_seconds_str;
 use crate::cli::util::iter_files_and_directories;
+use crate::cli::util::reporter::BufferedReporter;
 use crate::cli::util::reporter::ConsoleReporter;
 use crate::cli::util::reporter::Level;
+use crate::cli::util::reporter::Reporter;
 use crate::cli::util::CLIFileReporter;
 use crate::cli::util::ExistingPathBufValueParser;
 use crate::cli::util::PathSpec;
+use crate::loader::builtins_selection;
 use crate::loader::ContentProvider;
 use crate::loader::FileReader;
 use crate::loader::LanguageConfiguration;
 use crate::loader::Loader;
+use crate::loader::BUILTINS_VAR;
+use crate::profile::BuildProfile;
 use crate::test::Test;
+use crate::test::TestCase;
+use crate::test::TestCaseOutcome;
+use crate::test::TestFailure;
+use crate::test::TestFragment;
 use crate::test::TestResult;
 use crate::CancelAfterDuration;
 use crate::CancellationFlag;
+use crate::NoCancellation;
+
+use self::coverage::CoverageFormat;
+use self::coverage::TsgCoverage;
+use self::report::ReportFormat;
+use self::report::TestReport;
+
+pub mod coverage;
+pub mod report;
+
+/// The interval at which files are checked for changes in watch mode.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Args)]
 #[clap(after_help = r#"PATH SPECIFICATIONS:
@@ -131,6 +161,71 @@ pub struct TestArgs {
     #[clap(long)]
     pub no_builtins: bool,
 
+    /// Check the constructed graphs for structural problems, such as unreachable definitions.
+    #[clap(long)]
+    pub lint: bool,
+
+    /// Fail tests on attributes that are not expected on stack graph nodes, instead of reporting
+    /// them as warnings after all tests have run.
+    #[clap(long)]
+    pub strict_attributes: bool,
+
+    /// Print statistics about the graphs and partial paths of all tests.
+    #[clap(long)]
+    pub stats: bool,
+
+    /// Print which TSG stanzas matched, and which statements were executed, while building the
+    /// graphs of all tests. Uncovered stanzas and statements are listed with their line numbers.
+    /// Statements in conditional blocks without node or edge statements are always reported as
+    /// not executed, because their execution cannot be observed.
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text"
+    )]
+    pub tsg_coverage: Option<CoverageFormat>,
+
+    /// Print a profile of the execution of the TSG rules over all tests, with match counts, match
+    /// times, and created nodes and edges per stanza. Stanzas are sorted by the time spent matching
+    /// them.
+    #[clap(long)]
+    pub profile: bool,
+
+    /// Compare the graphs and minimal partial paths of tests against snapshots stored next to
+    /// the tests. The snapshot of a test is stored in a file with the name of the test and an
+    /// additional .snap extension. Snapshots refer to nodes by their file-local IDs, which depend
+    /// on the order in which the TSG rules create nodes, so snapshots are expected to be updated
+    /// with --update-snapshots after changing the rules.
+    #[clap(long)]
+    pub snapshots: bool,
+
+    /// Create or update the snapshots of tests, instead of comparing against them.
+    #[clap(long)]
+    pub update_snapshots: bool,
+
+    /// Number of tests to run in parallel. Results are reported in the same order as when tests
+    /// are run sequentially.
+    #[clap(long, short = 'j', value_name = "N", default_value_t = 1)]
+    pub jobs: usize,
+
+    /// Keep running, and rerun tests when test files, stack graph definitions, or builtins change.
+    /// Only tests that are affected by a change are rerun.
+    #[clap(
+        long,
+        conflicts_with_all = ["jobs", "report", "stats", "tsg_coverage", "profile"],
+    )]
+    pub watch: bool,
+
+    /// Write a report of the test results. Every test file is reported as a suite, and every
+    /// assertion as a test case. Supported formats are junit=PATH for JUnit XML, and tap or
+    /// tap=PATH for TAP. A TAP report without a path is written to standard output instead of
+    /// the normal output. Can be given multiple times.
+    #[clap(long, value_name = "FORMAT")]
+    pub report: Vec<ReportFormat>,
+
     /// Maximum runtime per test in seconds.
     #[clap(
         long,
@@ -140,6 +235,16 @@ pub struct TestArgs {
     pub max_test_time: Option<Duration>,
 }
 
+/// Statistics collected over all tests
+#[derive(Default)]
+struct TestStats {
+    graph: GraphStats,
+    paths: PartialPathStats,
+    profile: BuildProfile,
+    /// Unexpected node attributes, with their TSG locations.
+    attribute_warnings: BTreeSet<String>,
+}
+
 /// Flag to control output
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum OutputMode {
@@ -168,27 +273,375 @@ impl TestArgs {
             save_visualization: None,
             output_mode: OutputMode::OnFailure,
             no_builtins: false,
+            lint: false,
+            strict_attributes: false,
+            stats: false,
+            tsg_coverage: None,
+            profile: false,
+            snapshots: false,
+            update_snapshots: false,
+            jobs: 1,
+            watch: false,
+            report: Vec::new(),
             max_test_time: None,
         }
     }
 
     pub fn run(self, mut loader: Loader) -> anyhow::Result<()> {
+        if self.watch {
+            return self.run_watch(loader);
+        }
         let reporter = self.get_reporter();
+        let mut report = TestReport::new();
         let mut total_result = TestResult::new();
-        for (test_root, test_path, _) in iter_files_and_directories(self.test_paths.clone()) {
-            let mut file_status = CLIFileReporter::new(&reporter, &test_path);
-            let test_result =
-                self.run_test(&test_root, &test_path, &mut loader, &mut file_status)?;
+        let mut total_stats = TestStats::default();
+        let tests = iter_files_and_directories(self.test_paths.clone())
+            .map(|(test_root, test_path, _)| (test_root, test_path))
+            .collect::<Vec<_>>();
+        let outcome = if self.jobs > 1 {
+            self.run_tests_in_parallel(
+                &tests,
+                &mut loader,
+                &reporter,
+                &mut report,
+                &mut total_result,
+                &mut total_stats,
+            )
+        } else {
+            self.run_tests(
+                &tests,
+                &mut loader,
+                &reporter,
+                &mut report,
+                &mut total_result,
+                &mut total_stats,
+            )
+        };
+        // reports are written even if a test could not be run, so that the error is included
+        for format in &self.report {
+            report.write(format)?;
+        }
+        // attribute warnings are printed even if a test could not be run, because they may explain
+        // the error
+        if !total_stats.attribute_warnings.is_empty() {
+            println!();
+            for warning in &total_stats.attribute_warnings {
+                println!("Warning: {}", warning);
+            }
+        }
+        outcome?;
+        if self.stats {
+            println!();
+            println!("{}", total_stats.graph);
+            println!("{}", total_stats.paths);
+        }
+        if let Some(format) = self.tsg_coverage {
+            println!();
+            TsgCoverage::new(&total_stats.profile).write(format, &mut std::io::stdout().lock())?;
+        }
+        if self.profile {
+            println!();
+            print!("{}", total_stats.profile);
+        }
+        if total_result.failure_count() > 0 || total_result.unexpected_success_count() > 0 {
+            return Err(anyhow!(total_result.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Run tests sequentially.
+    fn run_tests(
+        &self,
+        tests: &[(PathBuf, PathBuf)],
+        loader: &mut Loader,
+        reporter: &dyn Reporter,
+        report: &mut TestReport,
+        total_result: &mut TestResult,
+        total_stats: &mut TestStats,
+    ) -> anyhow::Result<()> {
+        for (test_root, test_path) in tests {
+            let mut file_status = CLIFileReporter::new(reporter, test_path);
+            let test = parse_test(test_root, test_path);
+            let test_result = match self.run_test(
+                test_root,
+                test_path,
+                test,
+                loader,
+                total_stats,
+                &mut file_status,
+            ) {
+                Ok(test_result) => test_result,
+                Err(err) => {
+                    report.add_error(test_path, &err);
+                    return Err(err);
+                }
+            };
             file_status.assert_reported();
+            report.add_result(test_path, &test_result);
             total_result.absorb(test_result);
         }
-        if total_result.failure_count() > 0 {
-            return Err(anyhow!(total_result.to_string()));
+        Ok(())
+    }
+
+    /// Run tests on multiple threads. Test files are parsed and their languages are loaded up front,
+    /// because the loader cannot be shared between threads. The statuses of tests are buffered and reported in test order, and
+    /// the first error in test order is returned, so that the outcome is the same as when running
+    /// tests sequentially.
+    fn run_tests_in_parallel(
+        &self,
+        tests: &[(PathBuf, PathBuf)],
+        loader: &mut Loader,
+        reporter: &dyn Reporter,
+        report: &mut TestReport,
+        total_result: &mut TestResult,
+        total_stats: &mut TestStats,
+    ) -> anyhow::Result<()> {
+        let mut parsed_tests = Vec::new();
+        let mut languages = Vec::new();
+        for (test_root, test_path) in tests {
+            let test = parse_test(test_root, test_path);
+            languages.push(self.load_language_indices(
+                test_path,
+                test.as_ref().ok(),
+                loader,
+                &NoCancellation,
+            ));
+            parsed_tests.push(Mutex::new(Some(test)));
+        }
+        let configurations = loader.loaded_configurations();
+
+        let next_test = AtomicUsize::new(0);
+        let mut outcomes = std::thread::scope(|scope| {
+            let workers = (0..self.jobs)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut outcomes = Vec::new();
+                        loop {
+                            let index = next_test.fetch_add(1, Ordering::Relaxed);
+                            if index >= tests.len() {
+                                break;
+                            }
+                            let lcs = match &languages[index] {
+                                Ok(Some(lc_indices)) => lc_indices
+                                    .iter()
+                                    .map(|lc_index| configurations[*lc_index])
+                                    .collect::<Vec<_>>(),
+                                _ => continue,
+                            };
+                            let (test_root, test_path) = &tests[index];
+                            let test = parsed_tests[index]
+                                .lock()
+                                .unwrap()
+                                .take()
+                                .expect("test taken twice");
+                            let buffer = BufferedReporter::new();
+                            let mut stats = TestStats::default();
+                            let mut file_status = CLIFileReporter::new(&buffer, test_path);
+                            let result = self.run_test_with_languages(
+                                test_root,
+                                test_path,
+                                test,
+                                &lcs,
+                                &mut stats,
+                                &mut file_status,
+                            );
+                            if result.is_err() {
+                                file_status.failure_if_processing("error", None);
+                            } else {
+                                file_status.assert_reported();
+                            }
+                            outcomes.push((index, (result, buffer, stats)));
+                        }
+                        outcomes
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("test thread panicked"))
+                .collect::<HashMap<_, _>>()
+        });
+
+        for (index, language) in languages.into_iter().enumerate() {
+            let test_path = &tests[index].1;
+            match language {
+                Ok(Some(_)) => {}
+                Ok(None) => continue,
+                Err(err) => {
+                    report.add_error(test_path, &err);
+                    return Err(err);
+                }
+            }
+            let (result, buffer, stats) = outcomes.remove(&index).expect("missing test outcome");
+            buffer.replay(reporter);
+            total_stats.graph += stats.graph;
+            total_stats.paths += stats.paths;
+            total_stats.profile += stats.profile;
+            total_stats
+                .attribute_warnings
+                .extend(stats.attribute_warnings);
+            let result = match result {
+                Ok(result) => result,
+                Err(err) => {
+                    report.add_error(test_path, &err);
+                    return Err(err);
+                }
+            };
+            report.add_result(test_path, &result);
+            total_result.absorb(result);
         }
         Ok(())
     }
 
+    /// Loads the languages for a test file, and returns the positions of their configurations in
+    /// the loaded configurations of the loader, or `None` if the test file is not supported. The
+    /// first language is the language of the test file. It is followed by the languages of test
+    /// fragments that are not supported by any of the preceding languages. If the test file could
+    /// not be parsed, only the language of the test file is returned, and the error is reported
+    /// when the test is run.
+    fn load_language_indices(
+        &self,
+        test_path: &Path,
+        test: Option<&Test>,
+        loader: &mut Loader,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> anyhow::Result<Option<Vec<usize>>> {
+        let load_path = load_path(test_path);
+        let mut file_reader = MappingFileReader::new(&load_path, test_path);
+        let lc = match loader
+            .load_for_file(&load_path, &mut file_reader, cancellation_flag)?
+            .primary
+        {
+            Some(lc) => lc as *const LanguageConfiguration,
+            None => return Ok(None),
+        };
+        let mut lc_indices = Vec::new();
+        lc_indices.extend(loaded_configuration_index(loader, lc));
+
+        let fragments = match test {
+            Some(test) => &test.fragments,
+            None => return Ok(Some(lc_indices)),
+        };
+        for fragment in fragments {
+            let configurations = loader.loaded_configurations();
+            let mut supported = false;
+            for lc_index in &lc_indices {
+                supported |= supports_fragment(configurations[*lc_index], fragment)?;
+            }
+            if supported {
+                continue;
+            }
+            let lcs = loader.load_for_file(
+                &fragment.path,
+                &mut Some(fragment.source.as_str()),
+                cancellation_flag,
+            )?;
+            let lc = match lcs.primary.or(lcs.secondary.first().map(|(lc, _)| *lc)) {
+                Some(lc) => lc as *const LanguageConfiguration,
+                None => continue,
+            };
+            lc_indices.extend(loaded_configuration_index(loader, lc));
+        }
+        Ok(Some(lc_indices))
+    }
+
+    /// Run tests, and rerun affected tests whenever test files or loaded languages change. Does not
+    /// return unless an error occurs.
+    fn run_watch(self, mut loader: Loader) -> anyhow::Result<()> {
+        let reporter = self.get_reporter();
+        let mut stats = TestStats::default();
+        let mut watched = HashMap::<PathBuf, WatchedTest>::new();
+        loop {
+            let changed_languages = loader.invalidate_changed();
+            let mut test_paths = HashSet::new();
+            let mut run_count = 0;
+            let mut error_count = 0;
+            for (test_root, test_path, _) in iter_files_and_directories(self.test_paths.clone()) {
+                test_paths.insert(test_path.clone());
+                let modified = file_modified(&test_path);
+                if let Some(test) = watched.get(&test_path) {
+                    let language_changed =
+                        test.languages.iter().any(|l| changed_languages.contains(l));
+                    if test.modified == modified && !language_changed {
+                        continue;
+                    }
+                }
+                run_count += 1;
+                let mut file_status = CLIFileReporter::new(&reporter, &test_path);
+                let test = parse_test(&test_root, &test_path);
+                let languages = self.watched_languages(&test_path, test.as_ref().ok(), &mut loader);
+                let result = self.run_test_inner(
+                    &test_root,
+                    &test_path,
+                    test,
+                    &mut loader,
+                    &mut stats,
+                    &mut file_status,
+                );
+                let result = match result {
+                    Ok(result) => result,
+                    Err(err) => {
+                        error_count += 1;
+                        file_status.failure_if_unreported("error", Some(&format!("{:#}", err)));
+                        TestResult::new()
+                    }
+                };
+                file_status.assert_reported();
+                watched.insert(
+                    test_path,
+                    WatchedTest {
+                        modified,
+                        languages,
+                        result,
+                    },
+                );
+            }
+            watched.retain(|test_path, _| test_paths.contains(test_path));
+
+            if run_count > 0 || !changed_languages.is_empty() {
+                let mut total_result = TestResult::new();
+                for test in watched.values() {
+                    total_result.absorb(test.result.clone());
+                }
+                print!(
+                    "Ran {} of {} test files: {}",
+                    run_count,
+                    watched.len(),
+                    total_result
+                );
+                if error_count > 0 {
+                    print!(", {} errors", error_count);
+                }
+                println!();
+                println!("Watching for changes...");
+            }
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+    }
+
+    /// Returns the languages of a test file, so that the test can be rerun when one of them changes.
+    fn watched_languages(
+        &self,
+        test_path: &Path,
+        test: Option<&Test>,
+        loader: &mut Loader,
+    ) -> Vec<tree_sitter::Language> {
+        let lc_indices = match self.load_language_indices(test_path, test, loader, &NoCancellation)
+        {
+            Ok(Some(lc_indices)) => lc_indices,
+            _ => return Vec::new(),
+        };
+        let configurations = loader.loaded_configurations();
+        lc_indices
+            .into_iter()
+            .map(|lc_index| configurations[lc_index].language)
+            .collect()
+    }
+
     fn get_reporter(&self) -> ConsoleReporter {
+        if self.report.iter().any(|format| format.is_stdout()) {
+            return ConsoleReporter::none();
+        }
         return ConsoleReporter {
             skipped_level: if self.show_skipped {
                 Level::Summary
@@ -214,10 +667,12 @@ impl TestArgs {
         &self,
         test_root: &Path,
         test_path: &Path,
+        test: anyhow::Result<Test>,
         loader: &mut Loader,
+        stats: &mut TestStats,
         file_status: &mut CLIFileReporter,
     ) -> anyhow::Result<TestResult> {
-        match self.run_test_inner(test_root, test_path, loader, file_status) {
+        match self.run_test_inner(test_root, test_path, test, loader, stats, file_status) {
             ok @ Ok(_) => ok,
             err @ Err(_) => {
                 file_status.failure_if_processing("error", None);
@@ -230,26 +685,40 @@ impl TestArgs {
         &self,
         test_root: &Path,
         test_path: &Path,
+        test: anyhow::Result<Test>,
         loader: &mut Loader,
+        stats: &mut TestStats,
         file_status: &mut CLIFileReporter,
     ) -> anyhow::Result<TestResult> {
-        let cancellation_flag = CancelAfterDuration::from_option(self.max_test_time);
-
-        // If the file is skipped (ending in .skip) we construct the non-skipped path to see if we would support it.
-        let load_path = if test_path.extension().map_or(false, |e| e == "skip") {
-            test_path.with_extension("")
-        } else {
-            test_path.to_path_buf()
-        };
-        let mut file_reader = MappingFileReader::new(&load_path, test_path);
-        let lc = match loader
-            .load_for_file(&load_path, &mut file_reader, cancellation_flag.as_ref())?
-            .primary
-        {
-            Some(lc) => lc,
+        let lc_indices = match self.load_language_indices(
+            test_path,
+            test.as_ref().ok(),
+            loader,
+            &NoCancellation,
+        )? {
+            Some(lc_indices) => lc_indices,
             None => return Ok(TestResult::new()),
         };
+        let configurations = loader.loaded_configurations();
+        let lcs = lc_indices
+            .iter()
+            .map(|lc_index| configurations[*lc_index])
+            .collect::<Vec<_>>();
+        self.run_test_with_languages(test_root, test_path, test, &lcs, stats, file_status)
+    }
 
+    /// Run parsed test file with the given languages. Every test fragment is built with the first
+    /// language that supports it. Errors from parsing the test file are returned unless the test is
+    /// skipped.
+    fn run_test_with_languages(
+        &self,
+        test_root: &Path,
+        test_path: &Path,
+        test: anyhow::Result<Test>,
+        lcs: &[&LanguageConfiguration],
+        stats: &mut TestStats,
+        file_status: &mut CLIFileReporter,
+    ) -> anyhow::Result<TestResult> {
         if test_path.components().any(|c| match c {
             std::path::Component::Normal(name) => (name.as_ref() as &Path)
                 .extension()
@@ -262,19 +731,56 @@ impl TestArgs {
 
         file_status.processing();
 
-        let source = file_reader.get(test_path)?;
-        let default_fragment_path = test_path.strip_prefix(test_root).unwrap();
-        let mut test = Test::from_source(test_path, source, default_fragment_path)?;
+        let cancellation_flag = CancelAfterDuration::from_option(self.max_test_time);
+        let cancellation_flag = cancellation_flag.as_ref();
+        let mut test = test?;
         if !self.no_builtins {
-            self.load_builtins_into(&lc, &mut test.graph)?;
+            let selection = test
+                .fragments
+                .iter()
+                .filter_map(|f| f.globals.get(BUILTINS_VAR))
+                .flat_map(|value| builtins_selection(value))
+                .unique()
+                .collect::<Vec<_>>();
+            if let Some(name) = selection
+                .iter()
+                .find(|name| !lcs.iter().any(|lc| lc.builtins_variants.contains(name)))
+            {
+                return Err(anyhow!(
+                    "Unknown builtins variant ‘{}’ in test file {}",
+                    name,
+                    test_path.display()
+                ));
+            }
+            for lc in lcs {
+                self.load_builtins_into(lc, &selection, &mut test.graph, cancellation_flag)?;
+            }
         }
         let mut globals = Variables::new();
+        let mut attribute_diagnostics = Vec::new();
         for test_fragment in &test.fragments {
-            let result = if let Some(fa) = test_fragment
+            let mut lc = None;
+            for candidate in lcs {
+                if supports_fragment(candidate, test_fragment)? {
+                    lc = Some(candidate);
+                    break;
+                }
+            }
+            let lc = match lc {
+                Some(lc) => lc,
+                None => {
+                    return Err(anyhow!(
+                        "Test fragment {} not supported by any language of test file {}",
+                        test_fragment.path.display(),
+                        test.path.display()
+                    ))
+                }
+            };
+            let special_file = test_fragment
                 .path
                 .file_name()
-                .and_then(|file_name| lc.special_files.get(&file_name.to_string_lossy()))
-            {
+                .and_then(|file_name| lc.special_files.get(&file_name.to_string_lossy()));
+            let result = if let Some(fa) = special_file {
                 let mut all_paths = test.fragments.iter().map(|f| f.path.as_path());
                 fa.build_stack_graph_into(
                     &mut test.graph,
@@ -283,28 +789,30 @@ impl TestArgs {
                     &test_fragment.source,
                     &mut all_paths,
                     &test_fragment.globals,
-                    cancellation_flag.as_ref(),
+                    cancellation_flag,
                 )
-            } else if lc.matches_file(
-                &test_fragment.path,
-                &mut Some(test_fragment.source.as_ref()),
-            )? {
+            } else {
                 globals.clear();
                 test_fragment.add_globals_to(&mut globals);
-                lc.sgl.build_stack_graph_into(
+                let mut builder = lc.sgl.builder_into_stack_graph(
                     &mut test.graph,
                     test_fragment.file,
                     &test_fragment.source,
-                    &globals,
-                    cancellation_flag.as_ref(),
-                )
-            } else {
-                return Err(anyhow!(
-                    "Test fragment {} not supported by language of test file {}",
-                    test_fragment.path.display(),
-                    test.path.display()
-                ));
+                );
+                if self.profile || self.tsg_coverage.is_some() {
+                    builder.set_profile(&mut stats.profile);
+                }
+                builder.set_attribute_diagnostics(&mut attribute_diagnostics);
+                builder.set_strict_attributes(self.strict_attributes);
+                builder.build(&globals, cancellation_flag)
             };
+            stats
+                .attribute_warnings
+                .extend(attribute_diagnostics.drain(..).map(|diagnostic| {
+                    diagnostic
+                        .display_with_tsg_path(lc.sgl.tsg_path())
+                        .to_string()
+                }));
             match result {
                 Err(err) => {
                     file_status.failure(
@@ -313,7 +821,7 @@ impl TestArgs {
                             "{}",
                             err.display_pretty(
                                 &test.path,
-                                source,
+                                &test_fragment.source,
                                 lc.sgl.tsg_path(),
                                 lc.sgl.tsg_source(),
                             )
@@ -324,22 +832,64 @@ impl TestArgs {
                 Ok(_) => {}
             }
         }
+        let lint_issues = if self.lint {
+            self.lint(&test)
+        } else {
+            Vec::default()
+        };
         let mut partials = PartialPaths::new();
         let mut db = Database::new();
+        let mut precompiled_files = HashSet::new();
+        if !self.no_builtins {
+            for lc in lcs {
+                let builtins_paths = match &lc.builtins_paths {
+                    Some(builtins_paths) => builtins_paths,
+                    None => continue,
+                };
+                for path in builtins_paths {
+                    let path = path
+                        .to_partial_path(&mut test.graph, &mut partials)
+                        .map_err(|err| anyhow!("Invalid precompiled builtins path: {}", err))?;
+                    db.add_partial_path(&test.graph, &mut partials, path);
+                }
+                // builtins files are named after their path, and file names are unique in the
+                // test graph, so the files of the precompiled builtins are found by name
+                precompiled_files.extend(
+                    lc.builtins
+                        .iter_files()
+                        .filter_map(|file| test.graph.get_file(lc.builtins[file].name())),
+                );
+            }
+        }
         for file in test.graph.iter_files() {
+            if precompiled_files.contains(&file) {
+                continue;
+            }
             ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
                 &test.graph,
                 &mut partials,
                 file,
-                &cancellation_flag.as_ref(),
+                &cancellation_flag,
                 |g, ps, p| {
                     db.add_partial_path(g, ps, p.clone());
                 },
             )?;
         }
-        let result = test.run(&mut partials, &mut db, cancellation_flag.as_ref())?;
-        let success = result.failure_count() == 0;
-        let outputs = if self.output_mode.test(!success) {
+        if self.stats {
+            self.collect_stats(&test, &db, stats);
+        }
+        let snapshot = if self.snapshots || self.update_snapshots {
+            Some(test.snapshot(&mut partials, &db))
+        } else {
+            None
+        };
+        let mut result = test.run(&mut partials, &mut db, cancellation_flag)?;
+        let snapshot_output = match snapshot {
+            Some(snapshot) => self.check_snapshot(test_path, &snapshot, &mut result)?,
+            None => None,
+        };
+        let success = result.failure_count() == 0 && result.unexpected_success_count() == 0;
+        let mut outputs = if self.output_mode.test(!success) {
             let files = test.fragments.iter().map(|f| f.file).collect::<Vec<_>>();
             self.save_output(
                 test_root,
@@ -349,42 +899,153 @@ impl TestArgs {
                 &mut db,
                 &|_: &StackGraph, h: &Handle<File>| files.contains(h),
                 success,
-                cancellation_flag.as_ref(),
+                cancellation_flag,
             )?
         } else {
             Vec::default()
         };
+        outputs.extend(snapshot_output);
 
-        if success {
+        if success && lint_issues.is_empty() {
             let details = outputs.join("\n");
             file_status.success("success", Some(&details));
+        } else if success {
+            let details = lint_issues.iter().chain(outputs.iter()).join("\n");
+            file_status.warning(
+                &format!("success with {} lint issues", lint_issues.len()),
+                Some(&details),
+            );
         } else {
             let details = result
                 .failures_iter()
                 .map(|f| f.to_string())
+                .chain(result.unexpected_successes_iter().map(|s| s.to_string()))
+                .chain(lint_issues)
                 .chain(outputs)
                 .join("\n");
-            file_status.failure(
-                &format!(
+            let mut summary = Vec::new();
+            if result.failure_count() > 0 {
+                summary.push(format!(
                     "{}/{} assertions failed",
                     result.failure_count(),
                     result.count(),
-                ),
-                Some(&details),
-            );
+                ));
+            }
+            if result.unexpected_success_count() > 0 {
+                summary.push(format!(
+                    "{}/{} assertions passed unexpectedly",
+                    result.unexpected_success_count(),
+                    result.count(),
+                ));
+            }
+            file_status.failure(&summary.join(", "), Some(&details));
         }
 
         Ok(result)
     }
 
+    /// Compares the snapshot of a test against the stored snapshot, and records the outcome in the
+    /// test result. If snapshots are updated, the stored snapshot is overwritten instead, and a
+    /// message is returned if it changed.
+    fn check_snapshot(
+        &self,
+        test_path: &Path,
+        snapshot: &str,
+        result: &mut TestResult,
+    ) -> anyhow::Result<Option<String>> {
+        let snapshot_path = snapshot_path(test_path);
+        let expected = match std::fs::read_to_string(&snapshot_path) {
+            Ok(expected) => Some(expected),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        if self.update_snapshots {
+            if expected.as_deref() == Some(snapshot) {
+                return Ok(None);
+            }
+            std::fs::write(&snapshot_path, snapshot)?;
+            return Ok(Some(format!(
+                "{}: snapshot updated at {}",
+                test_path.display(),
+                snapshot_path.display()
+            )));
+        }
+        let outcome = match expected {
+            None => TestCaseOutcome::Failure(TestFailure::MissingSnapshot {
+                path: test_path.to_path_buf(),
+                snapshot_path,
+            }),
+            Some(expected) if expected == snapshot => TestCaseOutcome::Success,
+            Some(expected) => TestCaseOutcome::Failure(TestFailure::SnapshotMismatch {
+                path: test_path.to_path_buf(),
+                snapshot_path,
+                diff: snapshot_diff(&expected, snapshot),
+            }),
+        };
+        result.add_case(TestCase {
+            name: "snapshot".to_string(),
+            fragment_path: None,
+            position: None,
+            source_line: None,
+            outcome,
+        });
+        Ok(None)
+    }
+
+    /// Returns the structural problems found in the graphs of the test fragments.
+    fn lint(&self, test: &Test) -> Vec<String> {
+        test.graph
+            .validate_files(test.fragments.iter().map(|fragment| fragment.file))
+            .into_iter()
+            .map(|issue| {
+                let file = test.graph[issue.node()].file();
+                let fragment = test.fragments.iter().find(|f| Some(f.file) == file);
+                format!(
+                    "{}: {}",
+                    fragment.map_or(String::new(), |f| f.path.display().to_string()),
+                    issue.display(&test.graph)
+                )
+            })
+            .collect()
+    }
+
+    /// Adds statistics about the graphs and partial paths of the test fragments, excluding any
+    /// builtins, to the given statistics.
+    fn collect_stats(&self, test: &Test, db: &Database, stats: &mut TestStats) {
+        let files = test.fragments.iter().map(|f| f.file).collect::<Vec<_>>();
+        for file in &files {
+            stats.graph += test.graph.file_stats(*file);
+        }
+        for handle in db.iter_partial_paths() {
+            let path = &db[handle];
+            if [path.start_node, path.end_node].iter().any(|node| {
+                test.graph[*node]
+                    .file()
+                    .is_some_and(|file| files.contains(&file))
+            }) {
+                stats.paths.add_partial_path(&test.graph, path);
+            }
+        }
+    }
+
+    /// Adds the default builtins of the language, and those of the selected builtins variants
+    /// that the language has, to the graph.
     fn load_builtins_into(
         &self,
         lc: &LanguageConfiguration,
+        selection: &[String],
         graph: &mut StackGraph,
+        cancellation_flag: &dyn CancellationFlag,
     ) -> anyhow::Result<()> {
         if let Err(h) = graph.add_from_graph(&lc.builtins) {
             return Err(anyhow!("Duplicate builtin file {}", &graph[h]));
         }
+        let variants = lc
+            .load_builtins_variants(selection, cancellation_flag)
+            .map_err(|err| anyhow!("{}", err.display_pretty()))?;
+        if let Err(h) = graph.add_from_graph(&variants) {
+            return Err(anyhow!("Duplicate builtin file {}", &graph[h]));
+        }
         Ok(())
     }
 
@@ -531,6 +1192,126 @@ impl TestArgs {
     }
 }
 
+/// Returns whether the language supports the test fragment, either with a special file analyzer or
+/// with its stack graph construction rules.
+fn supports_fragment(lc: &LanguageConfiguration, fragment: &TestFragment) -> anyhow::Result<bool> {
+    if let Some(file_name) = fragment.path.file_name() {
+        if lc.special_files.get(&file_name.to_string_lossy()).is_some() {
+            return Ok(true);
+        }
+    }
+    Ok(lc.matches_file(&fragment.path, &mut Some(fragment.source.as_ref()))?)
+}
+
+/// Returns the position of the language configuration in the loaded configurations of the loader.
+fn loaded_configuration_index(loader: &Loader, lc: *const LanguageConfiguration) -> Option<usize> {
+    loader
+        .loaded_configurations()
+        .into_iter()
+        .position(|loaded| std::ptr::eq(loaded, lc))
+}
+
+/// Parses a test file. Fragments without an explicit path are named after the test file relative
+/// to its test root.
+fn parse_test(test_root: &Path, test_path: &Path) -> anyhow::Result<Test> {
+    let mut file_reader = FileReader::new();
+    let source = file_reader.get(test_path)?;
+    let default_fragment_path = test_path.strip_prefix(test_root).unwrap();
+    Ok(Test::from_source(test_path, source, default_fragment_path)?)
+}
+
+/// Returns the path that is used to select the language of a test. If the file is skipped (ending in
+/// .skip) we construct the non-skipped path to see if we would support it.
+fn load_path(test_path: &Path) -> PathBuf {
+    if test_path.extension().map_or(false, |e| e == "skip") {
+        test_path.with_extension("")
+    } else {
+        test_path.to_path_buf()
+    }
+}
+
+/// The state of a test in watch mode.
+struct WatchedTest {
+    modified: Option<SystemTime>,
+    languages: Vec<tree_sitter::Language>,
+    result: TestResult,
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Returns the path of the snapshot file for a test.
+fn snapshot_path(test_path: &Path) -> PathBuf {
+    let mut snapshot_path = test_path.as_os_str().to_owned();
+    snapshot_path.push(".snap");
+    snapshot_path.into()
+}
+
+/// Number of unchanged lines shown before and after each change in a snapshot diff.
+const SNAPSHOT_DIFF_CONTEXT: usize = 3;
+
+/// Returns the lines that were removed from or added to the expected snapshot, grouped in hunks
+/// with a few unchanged lines of context around them.
+fn snapshot_diff(expected: &str, actual: &str) -> String {
+    let lines = diff::lines(expected, actual);
+
+    // Compute the ranges of lines that are part of a hunk, merging hunks whose context overlaps.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        if matches!(line, diff::Result::Both(_, _)) {
+            continue;
+        }
+        let start = index.saturating_sub(SNAPSHOT_DIFF_CONTEXT);
+        let end = (index + SNAPSHOT_DIFF_CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    // Line numbers in the expected and actual snapshot at the start of every diff line.
+    let mut line_numbers = Vec::with_capacity(lines.len());
+    let (mut expected_line, mut actual_line) = (1, 1);
+    for line in &lines {
+        line_numbers.push((expected_line, actual_line));
+        match line {
+            diff::Result::Left(_) => expected_line += 1,
+            diff::Result::Right(_) => actual_line += 1,
+            diff::Result::Both(_, _) => {
+                expected_line += 1;
+                actual_line += 1;
+            }
+        }
+    }
+
+    let mut output = Vec::new();
+    for (start, end) in hunks {
+        let hunk = &lines[start..end];
+        let expected_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, diff::Result::Right(_)))
+            .count();
+        let actual_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, diff::Result::Left(_)))
+            .count();
+        let (expected_start, actual_start) = line_numbers[start];
+        output.push(format!(
+            "@@ -{},{} +{},{} @@",
+            expected_start, expected_count, actual_start, actual_count
+        ));
+        for line in hunk {
+            output.push(match line {
+                diff::Result::Left(line) => format!("- {}", line),
+                diff::Result::Right(line) => format!("+ {}", line),
+                diff::Result::Both(line, _) => format!("  {}", line),
+            });
+        }
+    }
+    output.join("\n")
+}
+
 struct MappingFileReader<'a> {
     inner: FileReader,
     instead_of: &'a Path,
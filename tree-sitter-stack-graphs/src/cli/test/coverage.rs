@@ -0,0 +1,142 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Coverage of TSG stanzas by test graphs.
+//!
+//! Coverage is derived from the [profile][crate::profile] that is collected while building the
+//! test graphs. A stanza is covered if its query matched in at least one test fragment, and a
+//! statement is covered if it is known to have been executed. See the profile documentation for
+//! how executed statements are determined, and the limitations of that. Coverage is reported for
+//! every TSG file of the rules of every language, so rules composed from several files are
+//! reported per file.
+
+use serde_json::json;
+
+use crate::profile::BuildProfile;
+
+/// Output format for TSG coverage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum CoverageFormat {
+    Text,
+    Json,
+}
+
+/// Coverage of the TSG files of all languages used by tests.
+pub struct TsgCoverage<'a> {
+    profile: &'a BuildProfile,
+}
+
+impl<'a> TsgCoverage<'a> {
+    /// Creates the coverage for the given profile of building the test graphs.
+    pub fn new(profile: &'a BuildProfile) -> Self {
+        Self { profile }
+    }
+
+    /// Write the coverage in the given format.
+    pub fn write(&self, format: CoverageFormat, w: &mut dyn std::io::Write) -> anyhow::Result<()> {
+        match format {
+            CoverageFormat::Text => self.write_text(w)?,
+            CoverageFormat::Json => {
+                serde_json::to_writer_pretty(&mut *w, &self.to_json())?;
+                writeln!(w)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_text(&self, w: &mut dyn std::io::Write) -> std::io::Result<()> {
+        for (_, language) in self.profile.languages() {
+            for coverage in language.files() {
+                let tsg_path = coverage.tsg_path;
+                writeln!(
+                    w,
+                    "{}: {}/{} stanzas matched, {}/{} statements executed",
+                    tsg_path.display(),
+                    coverage.matched_stanza_count(),
+                    coverage.stanzas.len(),
+                    coverage.executed_statement_count(),
+                    coverage.statement_count(),
+                )?;
+                for stanza in coverage.stanzas {
+                    if stanza.match_count == 0 {
+                        writeln!(
+                            w,
+                            "  {}:{}: stanza never matched: {}",
+                            tsg_path.display(),
+                            stanza.line + 1,
+                            stanza.query
+                        )?;
+                        continue;
+                    }
+                    for statement in stanza.statements.iter().filter(|s| !s.executed) {
+                        writeln!(
+                            w,
+                            "  {}:{}: statement never executed",
+                            tsg_path.display(),
+                            statement.line + 1,
+                        )?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let languages = self
+            .profile
+            .languages()
+            .map(|(tsg_path, language)| {
+                let files = language
+                    .files()
+                    .into_iter()
+                    .map(|coverage| {
+                        let stanzas = coverage
+                            .stanzas
+                            .iter()
+                            .map(|stanza| {
+                                let statements = stanza
+                                    .statements
+                                    .iter()
+                                    .map(|statement| {
+                                        json!({
+                                            "line": statement.line + 1,
+                                            "executed": statement.executed,
+                                        })
+                                    })
+                                    .collect::<Vec<_>>();
+                                json!({
+                                    "line": stanza.line + 1,
+                                    "query": stanza.query,
+                                    "matches": stanza.match_count,
+                                    "statements": statements,
+                                })
+                            })
+                            .collect::<Vec<_>>();
+                        json!({
+                            "tsg_path": coverage.tsg_path,
+                            "stanzas_total": coverage.stanzas.len(),
+                            "stanzas_matched": coverage.matched_stanza_count(),
+                            "statements_total": coverage.statement_count(),
+                            "statements_executed": coverage.executed_statement_count(),
+                            "stanzas": stanzas,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                json!({
+                    "tsg_path": tsg_path,
+                    "stanzas_total": language.stanzas.len(),
+                    "stanzas_matched": language.matched_stanza_count(),
+                    "statements_total": language.statement_count(),
+                    "statements_executed": language.executed_statement_count(),
+                    "files": files,
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({ "languages": languages })
+    }
+}
@@ -0,0 +1,298 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Machine-readable reports of test runs, in JUnit XML or TAP format.
+//!
+//! Every test file is reported as a suite, and every assertion in the file as a test case.
+//! Expected failures are reported as skipped test cases, and unexpected successes as failures.
+
+use anyhow::anyhow;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::test::TestCase;
+use crate::test::TestCaseOutcome;
+use crate::test::TestResult;
+
+/// Format and destination of a test report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// JUnit XML written to the given path.
+    Junit(PathBuf),
+    /// TAP written to the given path, or to standard output.
+    Tap(Option<PathBuf>),
+}
+
+impl ReportFormat {
+    /// Returns whether the report is written to standard output.
+    pub fn is_stdout(&self) -> bool {
+        matches!(self, Self::Tap(None))
+    }
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (format, path) = match s.split_once('=') {
+            Some((format, path)) => (format, Some(PathBuf::from(path))),
+            None => (s, None),
+        };
+        match (format, path) {
+            ("junit", Some(path)) => Ok(Self::Junit(path)),
+            ("junit", None) => Err(anyhow!("JUnit report requires a path, e.g. junit=PATH")),
+            ("tap", path) => Ok(Self::Tap(path)),
+            (format, _) => Err(anyhow!("Unknown report format {}", format)),
+        }
+    }
+}
+
+/// Collects the results of test files for reporting.
+#[derive(Default)]
+pub struct TestReport {
+    suites: Vec<TestSuite>,
+}
+
+/// The results of a single test file.
+struct TestSuite {
+    path: PathBuf,
+    cases: Vec<TestCase>,
+    error: Option<String>,
+}
+
+impl TestSuite {
+    fn failure_count(&self) -> usize {
+        self.cases.iter().filter(|c| c.is_failure()).count()
+    }
+
+    fn skipped_count(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|c| matches!(c.outcome, TestCaseOutcome::ExpectedFailure(_)))
+            .count()
+    }
+
+    fn is_failure(&self) -> bool {
+        self.error.is_some() || self.failure_count() > 0
+    }
+}
+
+impl TestReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the result of a test file. Files without any test cases, such as skipped files, are
+    /// not reported.
+    pub fn add_result(&mut self, path: &Path, result: &TestResult) {
+        if result.count() == 0 {
+            return;
+        }
+        self.suites.push(TestSuite {
+            path: path.to_path_buf(),
+            cases: result.cases_iter().cloned().collect(),
+            error: None,
+        });
+    }
+
+    /// Add a test file that could not be run because of an error.
+    pub fn add_error(&mut self, path: &Path, error: &anyhow::Error) {
+        self.suites.push(TestSuite {
+            path: path.to_path_buf(),
+            cases: Vec::new(),
+            error: Some(format!("{:#}", error)),
+        });
+    }
+
+    /// Write the report in the given format.
+    pub fn write(&self, format: &ReportFormat) -> anyhow::Result<()> {
+        match format {
+            ReportFormat::Junit(path) => {
+                let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                self.write_junit(&mut file)?;
+                file.flush()?;
+            }
+            ReportFormat::Tap(Some(path)) => {
+                let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                self.write_tap(&mut file)?;
+                file.flush()?;
+            }
+            ReportFormat::Tap(None) => {
+                self.write_tap(&mut std::io::stdout().lock())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the report as JUnit XML.
+    pub fn write_junit(&self, w: &mut dyn Write) -> std::io::Result<()> {
+        let tests = self.suites.iter().map(|s| s.cases.len()).sum::<usize>();
+        let failures = self.suites.iter().map(|s| s.failure_count()).sum::<usize>();
+        let errors = self.suites.iter().filter(|s| s.error.is_some()).count();
+        let skipped = self.suites.iter().map(|s| s.skipped_count()).sum::<usize>();
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            w,
+            r#"<testsuites tests="{}" failures="{}" errors="{}" skipped="{}">"#,
+            tests + errors,
+            failures,
+            errors,
+            skipped
+        )?;
+        for suite in &self.suites {
+            let name = xml_escape(&suite.path.to_string_lossy());
+            writeln!(
+                w,
+                r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}" skipped="{}">"#,
+                name,
+                suite.cases.len() + suite.error.iter().count(),
+                suite.failure_count(),
+                suite.error.iter().count(),
+                suite.skipped_count()
+            )?;
+            for case in &suite.cases {
+                write!(
+                    w,
+                    r#"    <testcase name="{}" classname="{}""#,
+                    xml_escape(&case.name),
+                    name
+                )?;
+                let (element, message, details) = match &case.outcome {
+                    TestCaseOutcome::Success => {
+                        writeln!(w, "/>")?;
+                        continue;
+                    }
+                    TestCaseOutcome::Failure(failure) => {
+                        ("failure", failure.to_string(), details(case, failure))
+                    }
+                    TestCaseOutcome::ExpectedFailure(failure) => (
+                        "skipped",
+                        format!("expected failure: {}", failure),
+                        details(case, failure),
+                    ),
+                    TestCaseOutcome::UnexpectedSuccess(success) => {
+                        ("failure", success.to_string(), details(case, success))
+                    }
+                };
+                writeln!(w, ">")?;
+                writeln!(
+                    w,
+                    r#"      <{} message="{}">{}</{}>"#,
+                    element,
+                    xml_escape(message.lines().next().unwrap_or_default()),
+                    xml_escape(&details),
+                    element
+                )?;
+                writeln!(w, "    </testcase>")?;
+            }
+            if let Some(error) = &suite.error {
+                writeln!(w, r#"    <testcase name="test file" classname="{}">"#, name)?;
+                writeln!(
+                    w,
+                    r#"      <error message="{}">{}</error>"#,
+                    xml_escape(error.lines().next().unwrap_or_default()),
+                    xml_escape(error)
+                )?;
+                writeln!(w, "    </testcase>")?;
+            }
+            writeln!(w, "  </testsuite>")?;
+        }
+        writeln!(w, "</testsuites>")?;
+        Ok(())
+    }
+
+    /// Write the report in the Test Anything Protocol format. Every test file is reported as a
+    /// subtest, expected failures are reported with a `TODO` directive, and unexpected successes
+    /// are reported as failures.
+    pub fn write_tap(&self, w: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(w, "TAP version 14")?;
+        for (index, suite) in self.suites.iter().enumerate() {
+            let name = suite.path.display();
+            if !suite.cases.is_empty() {
+                writeln!(w, "# Subtest: {}", name)?;
+                writeln!(w, "    1..{}", suite.cases.len())?;
+                for (index, case) in suite.cases.iter().enumerate() {
+                    match &case.outcome {
+                        TestCaseOutcome::Success => {
+                            writeln!(w, "    ok {} - {}", index + 1, case.name)?;
+                        }
+                        TestCaseOutcome::Failure(failure) => {
+                            writeln!(w, "    not ok {} - {}", index + 1, case.name)?;
+                            write_tap_diagnostics(w, "      ", &details(case, failure))?;
+                        }
+                        TestCaseOutcome::ExpectedFailure(failure) => {
+                            writeln!(
+                                w,
+                                "    not ok {} - {} # TODO expected failure",
+                                index + 1,
+                                case.name
+                            )?;
+                            write_tap_diagnostics(w, "      ", &details(case, failure))?;
+                        }
+                        TestCaseOutcome::UnexpectedSuccess(success) => {
+                            writeln!(w, "    not ok {} - {}", index + 1, case.name)?;
+                            write_tap_diagnostics(w, "      ", &details(case, success))?;
+                        }
+                    }
+                }
+            }
+            if suite.is_failure() {
+                writeln!(w, "not ok {} - {}", index + 1, name)?;
+                if let Some(error) = &suite.error {
+                    write_tap_diagnostics(w, "  ", error)?;
+                }
+            } else {
+                writeln!(w, "ok {} - {}", index + 1, name)?;
+            }
+        }
+        writeln!(w, "1..{}", self.suites.len())?;
+        Ok(())
+    }
+}
+
+/// Returns the details of a test case outcome, including the fragment path and source line of
+/// the assertion.
+fn details(case: &TestCase, outcome: &dyn std::fmt::Display) -> String {
+    let mut details = outcome.to_string();
+    if let (Some(fragment_path), Some(position)) = (&case.fragment_path, &case.position) {
+        details.push_str(&format!(
+            "\nin fragment {} on line {}",
+            fragment_path.display(),
+            position.line + 1
+        ));
+        if let Some(source_line) = &case.source_line {
+            details.push_str(&format!(": {}", source_line));
+        }
+    }
+    details
+}
+
+/// Write diagnostics as a YAML block with a single message.
+fn write_tap_diagnostics(w: &mut dyn Write, indent: &str, message: &str) -> std::io::Result<()> {
+    writeln!(w, "{}---", indent)?;
+    writeln!(w, "{}message: |", indent)?;
+    for line in message.lines() {
+        writeln!(w, "{}  {}", indent, line)?;
+    }
+    writeln!(w, "{}...", indent)?;
+    Ok(())
+}
+
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
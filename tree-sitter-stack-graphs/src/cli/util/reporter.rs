@@ -7,8 +7,10 @@
 
 use colored::ColoredString;
 use colored::Colorize;
+use std::cell::RefCell;
 use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
 
 /// Trait that supports reporting file processing status.
 ///
@@ -176,3 +178,91 @@ impl Reporter for ConsoleReporter {
         )
     }
 }
+
+/// A reporter that records all reported statuses, so that they can be replayed on another reporter
+/// later. This allows files to be processed concurrently, while their statuses are still reported
+/// in a deterministic order.
+#[derive(Default)]
+pub struct BufferedReporter {
+    events: RefCell<Vec<ReportedEvent>>,
+}
+
+enum ReportedEvent {
+    Skipped(PathBuf, String, Option<String>),
+    Started(PathBuf),
+    Succeeded(PathBuf, String, Option<String>),
+    Failed(PathBuf, String, Option<String>),
+    Cancelled(PathBuf, String, Option<String>),
+}
+
+impl BufferedReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report all recorded statuses on the given reporter, in the order they were recorded.
+    pub fn replay(self, reporter: &dyn Reporter) {
+        fn details(details: &Option<String>) -> Option<&dyn std::fmt::Display> {
+            details.as_ref().map(|d| d as &dyn std::fmt::Display)
+        }
+        for event in self.events.into_inner() {
+            match &event {
+                ReportedEvent::Skipped(path, summary, d) => {
+                    reporter.skipped(path, summary, details(d))
+                }
+                ReportedEvent::Started(path) => reporter.started(path),
+                ReportedEvent::Succeeded(path, summary, d) => {
+                    reporter.succeeded(path, summary, details(d))
+                }
+                ReportedEvent::Failed(path, summary, d) => {
+                    reporter.failed(path, summary, details(d))
+                }
+                ReportedEvent::Cancelled(path, summary, d) => {
+                    reporter.cancelled(path, summary, details(d))
+                }
+            }
+        }
+    }
+
+    fn record(&self, event: ReportedEvent) {
+        self.events.borrow_mut().push(event);
+    }
+}
+
+impl Reporter for BufferedReporter {
+    fn skipped(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.record(ReportedEvent::Skipped(
+            path.to_path_buf(),
+            summary.to_string(),
+            details.map(|d| d.to_string()),
+        ));
+    }
+
+    fn started(&self, path: &Path) {
+        self.record(ReportedEvent::Started(path.to_path_buf()));
+    }
+
+    fn succeeded(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.record(ReportedEvent::Succeeded(
+            path.to_path_buf(),
+            summary.to_string(),
+            details.map(|d| d.to_string()),
+        ));
+    }
+
+    fn failed(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.record(ReportedEvent::Failed(
+            path.to_path_buf(),
+            summary.to_string(),
+            details.map(|d| d.to_string()),
+        ));
+    }
+
+    fn cancelled(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.record(ReportedEvent::Cancelled(
+            path.to_path_buf(),
+            summary.to_string(),
+            details.map(|d| d.to_string()),
+        ));
+    }
+}
@@ -7,7 +7,10 @@
 
 //! Define tree-sitter-graph functions
 
+pub use self::regex::add_regex_functions;
 pub use path::add_path_functions;
+pub use string::add_string_functions;
+pub use symbols::add_symbol_functions;
 
 pub mod path {
     use std::path::Component;
@@ -38,6 +41,7 @@ pub mod path {
             path_fn(|p| p.file_stem().map(|s| s.to_os_string())),
         );
         functions.add("path-join".into(), PathJoin);
+        functions.add("path-module-name".into(), PathModuleName);
         functions.add(
             "path-normalize".into(),
             path_fn(|p| normalize(p).map(|p| p.as_os_str().to_os_string())),
@@ -118,6 +122,57 @@ pub mod path {
         }
     }
 
+    struct PathModuleName;
+
+    impl Function for PathModuleName {
+        fn call(
+            &self,
+            _graph: &mut Graph,
+            _source: &str,
+            parameters: &mut dyn Parameters,
+        ) -> Result<Value, ExecutionError> {
+            let path = PathBuf::from(parameters.param()?.into_string()?);
+            let separator = parameters.param()?.into_string()?;
+            let mut roots = Vec::new();
+            while let Ok(root) = parameters.param() {
+                roots.push(PathBuf::from(root.into_string()?));
+            }
+
+            let path = match normalize(&path) {
+                Some(path) => path,
+                None => return Ok(Value::Null),
+            };
+            let relative_path = if roots.is_empty() {
+                Some(path)
+            } else {
+                roots
+                    .iter()
+                    .filter_map(|root| normalize(root))
+                    .find_map(|root| path.strip_prefix(root).ok().map(Path::to_path_buf))
+            };
+            Ok(relative_path
+                .map(|p| module_name(&p, &separator))
+                .unwrap_or(Value::Null))
+        }
+    }
+
+    /// Joins the components of the path, without the extension of its file name, with the given
+    /// separator. Returns `#null` if the path has no normal components.
+    fn module_name(path: &Path, separator: &str) -> Value {
+        let components = path
+            .with_extension("")
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(c) => Some(c.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if components.is_empty() {
+            return Value::Null;
+        }
+        components.join(separator).into()
+    }
+
     /// Normalize a path, removing things like `.` and `..` wherever possible.
     // Based on the following code from Cargo:
     // https://github.com/rust-lang/cargo/blob/e515c3277bf0681bfc79a9e763861bfe26bb05db/crates/cargo-util/src/paths.rs#L73-L106
@@ -160,3 +215,270 @@ pub mod path {
         Some(ret)
     }
 }
+
+pub mod string {
+    use tree_sitter_graph::functions::Function;
+    use tree_sitter_graph::functions::Functions;
+    use tree_sitter_graph::functions::Parameters;
+    use tree_sitter_graph::graph::Graph;
+    use tree_sitter_graph::graph::Value;
+    use tree_sitter_graph::ExecutionError;
+
+    pub fn add_string_functions(functions: &mut Functions) {
+        functions.add("string-lowercase".into(), string_fn(|s| s.to_lowercase()));
+        functions.add("string-split".into(), StringSplit);
+        functions.add("string-strip-prefix".into(), StringStripPrefix);
+        functions.add("string-strip-suffix".into(), StringStripSuffix);
+        functions.add("string-uppercase".into(), string_fn(|s| s.to_uppercase()));
+    }
+
+    pub fn string_fn<F>(f: F) -> impl Function
+    where
+        F: Fn(&str) -> String,
+    {
+        StringFn(f)
+    }
+
+    struct StringFn<F>(F)
+    where
+        F: Fn(&str) -> String;
+
+    impl<F> Function for StringFn<F>
+    where
+        F: Fn(&str) -> String,
+    {
+        fn call(
+            &self,
+            _graph: &mut Graph,
+            _source: &str,
+            parameters: &mut dyn Parameters,
+        ) -> Result<Value, ExecutionError> {
+            let string = parameters.param()?.into_string()?;
+            parameters.finish()?;
+
+            Ok(self.0(&string).into())
+        }
+    }
+
+    struct StringSplit;
+
+    impl Function for StringSplit {
+        fn call(
+            &self,
+            _graph: &mut Graph,
+            _source: &str,
+            parameters: &mut dyn Parameters,
+        ) -> Result<Value, ExecutionError> {
+            let string = parameters.param()?.into_string()?;
+            let separator = parameters.param()?.into_string()?;
+            parameters.finish()?;
+
+            if separator.is_empty() {
+                return Err(ExecutionError::FunctionFailed(
+                    "string-split".into(),
+                    "Separator must not be empty".into(),
+                ));
+            }
+            let parts = string
+                .split(separator.as_str())
+                .map(|p| p.into())
+                .collect::<Vec<_>>();
+            Ok(parts.into())
+        }
+    }
+
+    struct StringStripPrefix;
+
+    impl Function for StringStripPrefix {
+        fn call(
+            &self,
+            _graph: &mut Graph,
+            _source: &str,
+            parameters: &mut dyn Parameters,
+        ) -> Result<Value, ExecutionError> {
+            let string = parameters.param()?.into_string()?;
+            let prefix = parameters.param()?.into_string()?;
+            parameters.finish()?;
+
+            Ok(string
+                .strip_prefix(prefix.as_str())
+                .map(|s| s.into())
+                .unwrap_or(Value::Null))
+        }
+    }
+
+    struct StringStripSuffix;
+
+    impl Function for StringStripSuffix {
+        fn call(
+            &self,
+            _graph: &mut Graph,
+            _source: &str,
+            parameters: &mut dyn Parameters,
+        ) -> Result<Value, ExecutionError> {
+            let string = parameters.param()?.into_string()?;
+            let suffix = parameters.param()?.into_string()?;
+            parameters.finish()?;
+
+            Ok(string
+                .strip_suffix(suffix.as_str())
+                .map(|s| s.into())
+                .unwrap_or(Value::Null))
+        }
+    }
+}
+
+pub mod regex {
+    use regex::Regex;
+    use tree_sitter_graph::functions::Function;
+    use tree_sitter_graph::functions::Functions;
+    use tree_sitter_graph::functions::Parameters;
+    use tree_sitter_graph::graph::Graph;
+    use tree_sitter_graph::graph::Value;
+    use tree_sitter_graph::ExecutionError;
+
+    pub fn add_regex_functions(functions: &mut Functions) {
+        functions.add("regex-captures".into(), RegexCaptures);
+        functions.add("regex-match".into(), RegexMatch);
+    }
+
+    fn regex_param(name: &str, parameters: &mut dyn Parameters) -> Result<Regex, ExecutionError> {
+        let pattern = parameters.param()?.into_string()?;
+        Regex::new(&pattern).map_err(|e| ExecutionError::FunctionFailed(name.into(), e.to_string()))
+    }
+
+    struct RegexCaptures;
+
+    impl Function for RegexCaptures {
+        fn call(
+            &self,
+            _graph: &mut Graph,
+            _source: &str,
+            parameters: &mut dyn Parameters,
+        ) -> Result<Value, ExecutionError> {
+            let string = parameters.param()?.into_string()?;
+            let regex = regex_param("regex-captures", parameters)?;
+            parameters.finish()?;
+
+            let captures = match regex.captures(&string) {
+                Some(captures) => captures,
+                None => return Ok(Value::Null),
+            };
+            let groups = captures
+                .iter()
+                .skip(1)
+                .map(|m| m.map(|m| m.as_str().into()).unwrap_or(Value::Null))
+                .collect::<Vec<_>>();
+            Ok(groups.into())
+        }
+    }
+
+    struct RegexMatch;
+
+    impl Function for RegexMatch {
+        fn call(
+            &self,
+            _graph: &mut Graph,
+            _source: &str,
+            parameters: &mut dyn Parameters,
+        ) -> Result<Value, ExecutionError> {
+            let string = parameters.param()?.into_string()?;
+            let regex = regex_param("regex-match", parameters)?;
+            parameters.finish()?;
+
+            Ok(regex.is_match(&string).into())
+        }
+    }
+}
+
+pub mod symbols {
+    use tree_sitter_graph::functions::Function;
+    use tree_sitter_graph::functions::Functions;
+    use tree_sitter_graph::functions::Parameters;
+    use tree_sitter_graph::graph::Graph;
+    use tree_sitter_graph::graph::GraphNodeRef;
+    use tree_sitter_graph::graph::Value;
+    use tree_sitter_graph::ExecutionError;
+
+    use crate::POP_SYMBOL_TYPE;
+    use crate::PUSH_SYMBOL_TYPE;
+    use crate::SYMBOL_ATTR;
+    use crate::TYPE_ATTR;
+
+    pub fn add_symbol_functions(functions: &mut Functions) {
+        functions.add("pop-symbols".into(), SymbolChain::pop());
+        functions.add("push-symbols".into(), SymbolChain::push());
+    }
+
+    /// Creates a chain of symbol nodes for a list of symbols, connected to the given node.
+    /// Pop chains start at the given node and pop the symbols in order. Push chains end at
+    /// the given node and push the symbols in reverse order, so that they are popped in order.
+    /// Returns the last node of a pop chain, or the first node of a push chain.
+    struct SymbolChain {
+        name: &'static str,
+        node_type: &'static str,
+        is_push: bool,
+    }
+
+    impl SymbolChain {
+        fn pop() -> Self {
+            Self {
+                name: "pop-symbols",
+                node_type: POP_SYMBOL_TYPE,
+                is_push: false,
+            }
+        }
+
+        fn push() -> Self {
+            Self {
+                name: "push-symbols",
+                node_type: PUSH_SYMBOL_TYPE,
+                is_push: true,
+            }
+        }
+
+        fn add_symbol_node(
+            &self,
+            graph: &mut Graph,
+            symbol: Value,
+        ) -> Result<GraphNodeRef, ExecutionError> {
+            let symbol = symbol.into_string()?;
+            let node = graph.add_graph_node();
+            // the node is new, so adding the attributes cannot fail
+            let attributes = &mut graph[node].attributes;
+            attributes.add(TYPE_ATTR.into(), self.node_type).ok();
+            attributes.add(SYMBOL_ATTR.into(), symbol).ok();
+            Ok(node)
+        }
+    }
+
+    impl Function for SymbolChain {
+        fn call(
+            &self,
+            graph: &mut Graph,
+            _source: &str,
+            parameters: &mut dyn Parameters,
+        ) -> Result<Value, ExecutionError> {
+            let mut prev = parameters.param()?.into_graph_node_ref()?;
+            let symbols = parameters.param()?.into_list()?;
+            parameters.finish()?;
+
+            if symbols.is_empty() {
+                return Err(ExecutionError::FunctionFailed(
+                    self.name.into(),
+                    "Expected at least one symbol".into(),
+                ));
+            }
+            for symbol in symbols {
+                let node = self.add_symbol_node(graph, symbol)?;
+                if self.is_push {
+                    graph[node].add_edge(prev).ok();
+                } else {
+                    graph[prev].add_edge(node).ok();
+                }
+                prev = node;
+            }
+            Ok(prev.into())
+        }
+    }
+}
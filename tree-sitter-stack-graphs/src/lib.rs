@@ -160,6 +160,28 @@
 //! }
 //! ```
 //!
+//! ### Annotating nodes with source information
+//!
+//! Besides its location, you can attach additional source information to any stack graph node,
+//! which can be used for features like hovers, outlines, or exports.  The `syntax_type` attribute
+//! describes the kind of syntax entity that the node represents, such as `function` or `class`.
+//! Its value must be a string.
+//!
+//! Definition nodes — nodes of type `pop_symbol` or `pop_scoped_symbol` — also allow a
+//! `definiens_node` attribute and a `fully_qualified_name` attribute.  The value of
+//! `definiens_node` must be a syntax node capture, whose span is used as the definiens of the
+//! definition, such as the body of a function.  The value of `fully_qualified_name` must be a
+//! string, representing the name of the definition together with its context:
+//!
+//! ``` skip
+//! (function_definition name: (identifier) @id body: (_) @body) @func {
+//!   node def
+//!   attr (def) type = "pop_symbol", symbol = (source-text @id), source_node = @func, is_definition
+//!   attr (def) syntax_type = "function", definiens_node = @body
+//!   attr (def) fully_qualified_name = (format "{}.{}" FILE_PATH (source-text @id))
+//! }
+//! ```
+//!
 //! ### Connecting stack graph nodes with edges
 //!
 //! To connect two stack graph nodes, use the `edge` statement to add an edge between them:
@@ -230,6 +252,9 @@
 //! - `path-filename`: get the last component of the argument path, or `#null` if it has no final component
 //! - `path-filestem`: get the file stem of the argument path, i.e., everything before the extension, or `#null` if it has no file name
 //! - `path-join`: join all argument paths together
+//! - `path-module-name`: get a module name for the first argument path, by removing the first of the optional root
+//!   arguments that is a prefix of the path, and joining the remaining components without file extension with the
+//!   separator given as second argument, or `#null` if none of the roots is a prefix of the path
 //! - `path-normalize`: normalize the argument path by eliminating `.` and `..` components where possible
 //! - `path-split`: split the argument path into a list of its components
 //!
@@ -264,6 +289,66 @@
 //! }
 //! ```
 //!
+//! The following example computes a dotted module name for a file in one of the source roots `src` or `lib`:
+//!
+//! ``` skip
+//! global FILE_PATH
+//!
+//! (program)@prog {
+//!   ; ...
+//!   let mod_name = (path-module-name FILE_PATH "." "src" "lib")
+//!   ; ...
+//! }
+//! ```
+//!
+//! ### Working with strings
+//!
+//! Built-in string and regular expression functions are available in addition to the [standard
+//! functions][] of the graph DSL, such as `format`, `join`, and `replace`.
+//!
+//! The following string functions are available:
+//! - `string-lowercase`: convert the argument string to lowercase
+//! - `string-split`: split the first argument string into a list of parts separated by the second argument string
+//! - `string-strip-prefix`: remove the second argument string from the start of the first argument string, or `#null`
+//!   if it does not start with it
+//! - `string-strip-suffix`: remove the second argument string from the end of the first argument string, or `#null`
+//!   if it does not end with it
+//! - `string-uppercase`: convert the argument string to uppercase
+//!
+//! The following regular expression functions are available:
+//! - `regex-captures`: get the list of capture groups of the first match of the regular expression given as second
+//!   argument in the first argument string, with `#null` for groups that did not participate, or `#null` if there is
+//!   no match
+//! - `regex-match`: check if the regular expression given as second argument matches the first argument string
+//!
+//! Lists returned by functions, such as the result of `string-split`, cannot be iterated over with `for`, but can
+//! be passed to other functions, such as the symbol functions below.
+//!
+//! ### Creating chains of symbols
+//!
+//! Built-in functions are available to create chains of symbol nodes for qualified names:
+//! - `pop-symbols`: create `pop_symbol` nodes for the symbols in the list given as second argument, connected by
+//!   edges starting at the node given as first argument, and return the last node
+//! - `push-symbols`: create `push_symbol` nodes for the symbols in the list given as second argument, connected by
+//!   edges ending at the node given as first argument, and return the first node
+//!
+//! In both cases the symbols are popped in the order in which they appear in the list. The following example
+//! defines and references dotted names, such that `a.b.c` is resolved as `c` in the scope of `b` in the scope of `a`:
+//!
+//! ``` skip
+//! (definition name:(dotted_name)@name)@def {
+//!   let def = (pop-symbols @def.scope (string-split (source-text @name) "."))
+//!   attr (def) is_definition, source_node = @name
+//! }
+//!
+//! (reference name:(dotted_name)@name)@ref {
+//!   let ref = (push-symbols @ref.scope (string-split (source-text @name) "."))
+//!   attr (ref) is_reference, source_node = @name
+//! }
+//! ```
+//!
+//! [standard functions]: https://docs.rs/tree-sitter-graph/*/tree_sitter_graph/reference/functions/index.html
+//!
 //! ## Using this crate from Rust
 //!
 //! If you need very fine-grained control over how to use the resulting stack graphs, you can
@@ -327,7 +412,10 @@ use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 use thiserror::Error;
+use tree_sitter::InputEdit;
 use tree_sitter::Parser;
+use tree_sitter_graph::ast::AddGraphNodeAttribute;
+use tree_sitter_graph::ast::Statement;
 use tree_sitter_graph::functions::Functions;
 use tree_sitter_graph::graph::Edge;
 use tree_sitter_graph::graph::Graph;
@@ -337,15 +425,23 @@ use tree_sitter_graph::graph::Value;
 use tree_sitter_graph::parse_error::ParseError;
 use tree_sitter_graph::parse_error::TreeWithParseErrorVec;
 use tree_sitter_graph::ExecutionConfig;
+use tree_sitter_graph::Location;
 use util::DisplayParseErrorsPretty;
 use util::TreeSitterCancellationFlag;
 
+use crate::profile::nested_blocks;
+use crate::profile::BuildProfile;
+
+#[cfg(feature = "bincode")]
+pub mod builtins;
+pub mod check;
 #[cfg(feature = "cli")]
 pub mod ci;
 #[cfg(feature = "cli")]
 pub mod cli;
 pub mod functions;
 pub mod loader;
+pub mod profile;
 pub mod test;
 mod util;
 
@@ -364,7 +460,9 @@ static SCOPE_TYPE: &'static str = "scope";
 
 // Node attribute names
 static DEBUG_ATTR_PREFIX: &'static str = "debug_";
+static DEFINIENS_NODE_ATTR: &'static str = "definiens_node";
 static EMPTY_SOURCE_SPAN_ATTR: &'static str = "empty_source_span";
+static FULLY_QUALIFIED_NAME_ATTR: &'static str = "fully_qualified_name";
 static IS_DEFINITION_ATTR: &'static str = "is_definition";
 static IS_ENDPOINT_ATTR: &'static str = "is_endpoint";
 static IS_EXPORTED_ATTR: &'static str = "is_exported";
@@ -372,13 +470,28 @@ static IS_REFERENCE_ATTR: &'static str = "is_reference";
 static SCOPE_ATTR: &'static str = "scope";
 static SOURCE_NODE_ATTR: &'static str = "source_node";
 static SYMBOL_ATTR: &'static str = "symbol";
+static SYNTAX_TYPE_ATTR: &'static str = "syntax_type";
 static TYPE_ATTR: &'static str = "type";
 
 // Expected attributes per node type
-static POP_SCOPED_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> =
-    Lazy::new(|| HashSet::from([TYPE_ATTR, SYMBOL_ATTR, IS_DEFINITION_ATTR]));
-static POP_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> =
-    Lazy::new(|| HashSet::from([TYPE_ATTR, SYMBOL_ATTR, IS_DEFINITION_ATTR]));
+static POP_SCOPED_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    HashSet::from([
+        TYPE_ATTR,
+        SYMBOL_ATTR,
+        IS_DEFINITION_ATTR,
+        DEFINIENS_NODE_ATTR,
+        FULLY_QUALIFIED_NAME_ATTR,
+    ])
+});
+static POP_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    HashSet::from([
+        TYPE_ATTR,
+        SYMBOL_ATTR,
+        IS_DEFINITION_ATTR,
+        DEFINIENS_NODE_ATTR,
+        FULLY_QUALIFIED_NAME_ATTR,
+    ])
+});
 static PUSH_SCOPED_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> =
     Lazy::new(|| HashSet::from([TYPE_ATTR, SYMBOL_ATTR, SCOPE_ATTR, IS_REFERENCE_ATTR]));
 static PUSH_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> =
@@ -386,6 +499,14 @@ static PUSH_SYMBOL_ATTRS: Lazy<HashSet<&'static str>> =
 static SCOPE_ATTRS: Lazy<HashSet<&'static str>> =
     Lazy::new(|| HashSet::from([TYPE_ATTR, IS_EXPORTED_ATTR, IS_ENDPOINT_ATTR]));
 
+/// Returns whether the attribute is expected on nodes of any type.
+fn is_common_attribute(id: &str) -> bool {
+    id == SOURCE_NODE_ATTR
+        || id == EMPTY_SOURCE_SPAN_ATTR
+        || id == SYNTAX_TYPE_ATTR
+        || id.starts_with(DEBUG_ATTR_PREFIX)
+}
+
 // Edge attribute names
 static PRECEDENCE_ATTR: &'static str = "precedence";
 
@@ -400,9 +521,18 @@ pub struct StackGraphLanguage {
     tsg: tree_sitter_graph::ast::File,
     tsg_path: PathBuf,
     tsg_source: std::borrow::Cow<'static, str>,
+    tsg_files: Vec<TsgSourceFile>,
     functions: Functions,
 }
 
+/// A TSG file that is part of rules composed from several files, and the part of the combined
+/// TSG source that it occupies.
+struct TsgSourceFile {
+    path: PathBuf,
+    first_row: usize,
+    bytes: std::ops::Range<usize>,
+}
+
 impl StackGraphLanguage {
     /// Creates a new stack graph language for the given language and
     /// TSG stack graph construction rules.
@@ -416,6 +546,7 @@ impl StackGraphLanguage {
             tsg,
             tsg_path: PathBuf::from("<tsg>"),
             tsg_source: Cow::from(String::new()),
+            tsg_files: Vec::new(),
             functions: Self::default_functions(),
         }
     }
@@ -433,6 +564,7 @@ impl StackGraphLanguage {
             tsg,
             tsg_path: PathBuf::from("<missing tsg path>"),
             tsg_source: Cow::from(tsg_source.to_string()),
+            tsg_files: Vec::new(),
             functions: Self::default_functions(),
         })
     }
@@ -451,14 +583,90 @@ impl StackGraphLanguage {
         Ok(sgl)
     }
 
+    /// Creates a new stack graph language for the given language, composing the TSG stack graph
+    /// construction rules from the given sources, in order. This allows rules, such as common
+    /// scoping conventions, to be shared between languages. The sources are combined as if they
+    /// were a single file, so variables, global declarations and attribute shorthands are shared
+    /// between them. The paths are purely for informational purposes, and are used to report
+    /// errors in the file they occur in. Errors that cannot be attributed to a single file, such
+    /// as a global declared in two files, are reported against the combined source.
+    pub fn from_sources(
+        language: tree_sitter::Language,
+        tsg_sources: &[(PathBuf, &str)],
+    ) -> Result<StackGraphLanguage, LanguageError> {
+        if let [(tsg_path, tsg_source)] = tsg_sources {
+            return Self::from_source(language, tsg_path.clone(), tsg_source);
+        }
+        let (tsg_source, tsg_files) = combine_tsg_sources(tsg_sources.iter());
+        let tsg = match tree_sitter_graph::ast::File::from_str(language, &tsg_source) {
+            Ok(tsg) => tsg,
+            Err(err) => {
+                return Err(locate_tsg_parse_error(
+                    language,
+                    tsg_sources,
+                    &tsg_files,
+                    &tsg_source,
+                    err,
+                ))
+            }
+        };
+        Ok(StackGraphLanguage {
+            language,
+            tsg,
+            tsg_path: combined_tsg_path(&tsg_files),
+            tsg_source: Cow::from(tsg_source),
+            tsg_files,
+            functions: Self::default_functions(),
+        })
+    }
+
     pub fn set_tsg_info(&mut self, path: PathBuf, source: Cow<'static, str>) {
         self.tsg_path = path;
         self.tsg_source = source;
+        self.tsg_files.clear();
+    }
+
+    /// Adds the files that the rules were composed from, and the lines they occupy in the combined
+    /// TSG source, to an execution error. The locations in execution errors are not exposed by
+    /// tree-sitter-graph, so they remain relative to the combined source, and the added lines
+    /// relate them to the files.
+    fn add_tsg_files_to_execution_error(
+        &self,
+        err: tree_sitter_graph::ExecutionError,
+    ) -> tree_sitter_graph::ExecutionError {
+        if self.tsg_files.is_empty() {
+            return err;
+        }
+        let files = self
+            .tsg_files
+            .iter()
+            .map(|file| {
+                let row_count = self.tsg_source[file.bytes.clone()].matches('\n').count();
+                format!(
+                    "{} at lines {}-{}",
+                    file.path.display(),
+                    file.first_row + 1,
+                    file.first_row + row_count
+                )
+            })
+            .collect::<Vec<_>>();
+        tree_sitter_graph::ExecutionError::InContext(
+            format!(
+                "Error in {}, composed from {}",
+                self.tsg_path.display(),
+                files.join(", ")
+            )
+            .into(),
+            Box::new(err),
+        )
     }
 
     fn default_functions() -> tree_sitter_graph::functions::Functions {
         let mut functions = tree_sitter_graph::functions::Functions::stdlib();
         crate::functions::add_path_functions(&mut functions);
+        crate::functions::add_regex_functions(&mut functions);
+        crate::functions::add_string_functions(&mut functions);
+        crate::functions::add_symbol_functions(&mut functions);
         functions
     }
 
@@ -470,17 +678,34 @@ impl StackGraphLanguage {
         self.language
     }
 
+    /// Returns the parsed TSG stack graph construction rules.
+    pub fn tsg(&self) -> &tree_sitter_graph::ast::File {
+        &self.tsg
+    }
+
     /// Returns the original TSG path, if it was provided at construction or set with
     /// [`set_tsg_info`][]. Can be used as input for [`BuildError::display_pretty`][].
+    /// For rules composed from several files, this is a description listing all files.
     pub fn tsg_path(&self) -> &Path {
         &self.tsg_path
     }
 
     /// Returns the original TSG source, if it was provided at construction or set with
     /// [`set_tsg_info`][]. Can be used as input for [`BuildError::display_pretty`][].
+    /// For rules composed from several files, this is the combined source of all files.
     pub fn tsg_source(&self) -> &Cow<'static, str> {
         &self.tsg_source
     }
+
+    /// Returns the TSG file that contains the given zero-based row of the TSG source, and
+    /// the row within that file. For rules composed from several files, this locates the row
+    /// in the file it came from. Otherwise, the TSG path and the row are returned as is.
+    pub fn tsg_location(&self, row: usize) -> (&Path, usize) {
+        match tsg_source_file(&self.tsg_files, row) {
+            Some(file) => (&file.path, row - file.first_row),
+            None => (&self.tsg_path, row),
+        }
+    }
 }
 
 /// An error that can occur while loading in the TSG stack graph construction rules for a language
@@ -488,18 +713,205 @@ impl StackGraphLanguage {
 pub enum LanguageError {
     #[error(transparent)]
     ParseError(#[from] tree_sitter_graph::ParseError),
+    /// A parse error in rules that were composed from several files. The path and source are
+    /// those of the file the error occurs in, and the location of the error is relative to the
+    /// file. If the error cannot be attributed to a single file, the path and source are those of
+    /// the combined source, see [`StackGraphLanguage::from_sources`][].
+    #[error("{inner} in {}", tsg_path.display())]
+    ParseErrorInFile {
+        #[source]
+        inner: tree_sitter_graph::ParseError,
+        tsg_path: PathBuf,
+        tsg: String,
+    },
 }
 
 impl LanguageError {
+    /// Display the error with an excerpt of the TSG source. Errors in rules composed from several
+    /// files are displayed with the file they occur in, or with the combined source, and the given
+    /// path and source are ignored.
     pub fn display_pretty<'a>(
         &'a self,
         path: &'a Path,
         source: &'a str,
     ) -> impl std::fmt::Display + 'a {
-        match self {
-            Self::ParseError(err) => err.display_pretty(path, source),
+        DisplayLanguageErrorPretty {
+            error: self,
+            path,
+            source,
+        }
+    }
+}
+
+struct DisplayLanguageErrorPretty<'a> {
+    error: &'a LanguageError,
+    path: &'a Path,
+    source: &'a str,
+}
+
+impl std::fmt::Display for DisplayLanguageErrorPretty<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.error {
+            LanguageError::ParseError(err) => {
+                write!(f, "{}", err.display_pretty(self.path, self.source))
+            }
+            LanguageError::ParseErrorInFile {
+                inner,
+                tsg_path,
+                tsg,
+            } => write!(f, "{}", inner.display_pretty(tsg_path, tsg)),
+        }
+    }
+}
+
+/// Combines the given TSG sources into a single source, and returns it together with the part
+/// of the combined source that every file occupies. Every file is terminated by a newline, so
+/// that every file starts on a row of its own.
+fn combine_tsg_sources<'a>(
+    tsg_sources: impl Iterator<Item = &'a (PathBuf, &'a str)>,
+) -> (String, Vec<TsgSourceFile>) {
+    let mut tsg_source = String::new();
+    let mut tsg_files = Vec::new();
+    let mut first_row = 0;
+    for (path, source) in tsg_sources {
+        let start = tsg_source.len();
+        tsg_source.push_str(source);
+        if !tsg_source.is_empty() && !tsg_source.ends_with('\n') {
+            tsg_source.push('\n');
+        }
+        tsg_files.push(TsgSourceFile {
+            path: path.clone(),
+            first_row,
+            bytes: start..tsg_source.len(),
+        });
+        first_row += tsg_source[start..].matches('\n').count();
+    }
+    (tsg_source, tsg_files)
+}
+
+/// Returns the path used for the combined source of TSG rules composed from the given files.
+fn combined_tsg_path(files: &[TsgSourceFile]) -> PathBuf {
+    PathBuf::from(format!(
+        "<{}>",
+        files
+            .iter()
+            .map(|f| f.path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" + ")
+    ))
+}
+
+/// Returns the file of composed TSG rules that contains the given row of the combined source.
+fn tsg_source_file(files: &[TsgSourceFile], row: usize) -> Option<&TsgSourceFile> {
+    files
+        .iter()
+        .rev()
+        .find(|file| file.first_row <= row && !file.bytes.is_empty())
+}
+
+/// Locates a parse error in rules composed from several files in the file it occurs in. The file
+/// is found from the row of the error in the combined source. The rules are then parsed again
+/// with that file first, so that the location of the error is relative to the file. If the file
+/// cannot be determined, the error is located in the combined source.
+fn locate_tsg_parse_error(
+    language: tree_sitter::Language,
+    tsg_sources: &[(PathBuf, &str)],
+    tsg_files: &[TsgSourceFile],
+    tsg_source: &str,
+    err: tree_sitter_graph::ParseError,
+) -> LanguageError {
+    let in_combined_source = |inner| LanguageError::ParseErrorInFile {
+        inner,
+        tsg_path: combined_tsg_path(tsg_files),
+        tsg: tsg_source.to_string(),
+    };
+    let file = match tsg_parse_error_row(language, tsg_source, &err)
+        .and_then(|row| tsg_source_file(tsg_files, row))
+    {
+        Some(file) => file,
+        None => return in_combined_source(err),
+    };
+    let file_tsg = &tsg_source[file.bytes.clone()];
+    let row_count = file_tsg.matches('\n').count();
+    let (reordered_source, _) = combine_tsg_sources(
+        tsg_sources
+            .iter()
+            .filter(|(path, _)| *path == file.path)
+            .chain(tsg_sources.iter().filter(|(path, _)| *path != file.path)),
+    );
+    match tree_sitter_graph::ast::File::from_str(language, &reordered_source) {
+        Err(inner)
+            if tsg_parse_error_row(language, &reordered_source, &inner)
+                .is_some_and(|row| row < row_count) =>
+        {
+            LanguageError::ParseErrorInFile {
+                inner,
+                tsg_path: file.path.clone(),
+                tsg: file_tsg.to_string(),
+            }
+        }
+        _ => in_combined_source(err),
+    }
+}
+
+/// Returns the zero-based row of a TSG parse error in the given source. The locations of check
+/// errors are not exposed by tree-sitter-graph. For those, the source is parsed again without
+/// checking, and the row of the global declaration or stanza that fails the check by itself is
+/// returned.
+fn tsg_parse_error_row(
+    language: tree_sitter::Language,
+    tsg_source: &str,
+    err: &tree_sitter_graph::ParseError,
+) -> Option<usize> {
+    if let Some(location) = tsg_parse_error_location(err) {
+        return Some(location.row);
+    }
+    let mut tsg = tree_sitter_graph::ast::File::new(language);
+    #[allow(deprecated)]
+    tsg.parse(tsg_source).ok()?;
+    let stanzas = std::mem::take(&mut tsg.stanzas);
+    if tsg.check().is_err() {
+        // global declarations are checked first, and only fail if they are duplicated
+        let mut names = HashSet::new();
+        return tsg
+            .globals
+            .iter()
+            .find(|global| !names.insert(global.name.clone()))
+            .map(|global| global.location.row);
+    }
+    for stanza in stanzas {
+        let row = stanza.range.start.row;
+        tsg.stanzas = vec![stanza];
+        if tsg.check().is_err() {
+            return Some(row);
         }
     }
+    None
+}
+
+/// Returns the location of a TSG parse error, or `None` for check errors, whose location is not
+/// exposed by tree-sitter-graph.
+fn tsg_parse_error_location(err: &tree_sitter_graph::ParseError) -> Option<Location> {
+    use tree_sitter_graph::ParseError;
+    let location = match err {
+        ParseError::ExpectedQuantifier(location)
+        | ParseError::ExpectedToken(_, location)
+        | ParseError::ExpectedVariable(location)
+        | ParseError::ExpectedUnscopedVariable(location)
+        | ParseError::InvalidRegex(_, location)
+        | ParseError::InvalidRegexCapture(location)
+        | ParseError::UnexpectedCharacter(_, _, location)
+        | ParseError::UnexpectedEOF(location)
+        | ParseError::UnexpectedKeyword(_, location)
+        | ParseError::UnexpectedLiteral(_, location)
+        | ParseError::UnexpectedQueryPatterns(location) => *location,
+        ParseError::QueryError(err) => Location {
+            row: err.row,
+            column: err.column,
+        },
+        ParseError::Check(_) => return None,
+    };
+    Some(location)
 }
 
 impl StackGraphLanguage {
@@ -542,6 +954,12 @@ pub struct Builder<'a> {
     remapped_nodes: HashMap<usize, NodeID>,
     injected_node_count: usize,
     span_calculator: SpanCalculator<'a>,
+    profile: Option<&'a mut BuildProfile>,
+    tree: Option<tree_sitter::Tree>,
+    parse_time: Duration,
+    skipped_matches: HashSet<(usize, usize)>,
+    attribute_diagnostics: Option<&'a mut Vec<AttributeDiagnostic>>,
+    strict_attributes: bool,
 }
 
 impl<'a> Builder<'a> {
@@ -561,33 +979,139 @@ impl<'a> Builder<'a> {
             remapped_nodes: HashMap::new(),
             injected_node_count: 0,
             span_calculator,
+            profile: None,
+            tree: None,
+            parse_time: Duration::ZERO,
+            skipped_matches: HashSet::new(),
+            attribute_diagnostics: None,
+            strict_attributes: false,
         }
     }
 
+    /// Collect a profile of the execution of the graph construction rules into the given profile.
+    /// Profiling runs the combined query of all stanzas an additional time, so building is slower
+    /// when a profile is collected.
+    pub fn set_profile(&mut self, profile: &'a mut BuildProfile) {
+        self.profile = Some(profile);
+    }
+
+    /// Collect diagnostics about unexpected node attributes into the given vector. If no vector is
+    /// set, the diagnostics are logged as warnings.
+    pub fn set_attribute_diagnostics(&mut self, diagnostics: &'a mut Vec<AttributeDiagnostic>) {
+        self.attribute_diagnostics = Some(diagnostics);
+    }
+
+    /// Fail with [`BuildError::UnexpectedAttribute`][] on the first unexpected node attribute,
+    /// instead of reporting a diagnostic.
+    pub fn set_strict_attributes(&mut self, strict_attributes: bool) {
+        self.strict_attributes = strict_attributes;
+    }
+
+    /// Parses the source of this builder from scratch. The resulting tree is used by
+    /// [`build`][Self::build], and can be retrieved with [`tree`][Self::tree] to parse a later
+    /// version of the source incrementally. Calling this method is optional, because `build`
+    /// parses the source if it has not been parsed yet.
+    pub fn parse(&mut self, cancellation_flag: &dyn CancellationFlag) -> Result<(), BuildError> {
+        let parse_start = Instant::now();
+        self.tree = Some(self.parse_tree(None, cancellation_flag)?);
+        self.parse_time = parse_start.elapsed();
+        Ok(())
+    }
+
+    /// Parses the source of this builder incrementally, reusing the tree of a previous version of
+    /// the source. The edits must describe the changes from the previous source to the source of
+    /// this builder, in the order in which they were made. The edits are applied to a copy of the
+    /// old tree, so the old tree itself is not modified.
+    ///
+    /// Returns the source ranges that changed, which are the ranges of the edits in the new source
+    /// together with the ranges whose syntactic structure changed, sorted and merged. Callers can use these to decide
+    /// whether the stack graph needs to be rebuilt at all. The resulting tree is used by
+    /// [`build`][Self::build], and can be retrieved with [`tree`][Self::tree].
+    pub fn parse_incremental(
+        &mut self,
+        old_tree: &tree_sitter::Tree,
+        edits: &[InputEdit],
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<tree_sitter::Range>, BuildError> {
+        let parse_start = Instant::now();
+        let mut old_tree = old_tree.clone();
+        for edit in edits {
+            old_tree.edit(edit);
+        }
+        let tree = self.parse_tree(Some(&old_tree), cancellation_flag)?;
+        self.parse_time = parse_start.elapsed();
+
+        let mut changed_ranges = old_tree.changed_ranges(&tree).collect::<Vec<_>>();
+        changed_ranges.extend(edited_ranges(edits));
+        self.tree = Some(tree);
+        Ok(merge_ranges(changed_ranges))
+    }
+
+    /// Returns the parsed tree of the source, if the source was parsed with [`parse`][Self::parse]
+    /// or [`parse_incremental`][Self::parse_incremental].
+    pub fn tree(&self) -> Option<&tree_sitter::Tree> {
+        self.tree.as_ref()
+    }
+
+    fn parse_tree(
+        &self,
+        old_tree: Option<&tree_sitter::Tree>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<tree_sitter::Tree, BuildError> {
+        let mut parser = Parser::new();
+        parser.set_language(self.sgl.language)?;
+        let ts_cancellation_flag = TreeSitterCancellationFlag::from(cancellation_flag);
+        // The parser.set_cancellation_flag` is unsafe, because it does not tie the
+        // lifetime of the parser to the lifetime of the cancellation flag in any way.
+        // To make it more obvious that the parser does not outlive the cancellation flag,
+        // it is put into its own method here, so that it is dropped before the flag.
+        unsafe { parser.set_cancellation_flag(Some(ts_cancellation_flag.as_ref())) };
+        parser
+            .parse(self.source, old_tree)
+            .ok_or(BuildError::ParseError)
+    }
+
     /// Executes this builder.
     pub fn build(
-        mut self,
+        self,
         globals: &'a Variables<'a>,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), BuildError> {
-        let tree = {
-            let mut parser = Parser::new();
-            parser.set_language(self.sgl.language)?;
-            let ts_cancellation_flag = TreeSitterCancellationFlag::from(cancellation_flag);
-            // The parser.set_cancellation_flag` is unsafe, because it does not tie the
-            // lifetime of the parser to the lifetime of the cancellation flag in any way.
-            // To make it more obvious that the parser does not outlive the cancellation flag,
-            // it is put into its own block here, instead of extending to the end of the method.
-            unsafe { parser.set_cancellation_flag(Some(ts_cancellation_flag.as_ref())) };
-            parser
-                .parse(self.source, None)
-                .ok_or(BuildError::ParseError)?
-        };
+        self.build_inner(globals, false, cancellation_flag)?;
+        Ok(())
+    }
+
+    /// Executes this builder, tolerating parse errors in the source. The graph is only built for
+    /// the well-formed parts of the syntax tree: stack graph nodes created by stanza matches with
+    /// captured nodes that are, or lie inside, an `ERROR` or `MISSING` node are skipped, as are push
+    /// scoped symbol nodes referring to skipped scopes, and all edges from or to skipped nodes.
+    /// Matches of nodes that only contain parse errors, such as the definitions enclosing them, are
+    /// kept. Errors while executing the graph construction rules are not tolerated.
+    ///
+    /// Returns the tolerated parse errors, or `None` if the source parsed without errors.
+    pub fn build_tolerant(
+        self,
+        globals: &'a Variables<'a>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Option<TreeWithParseErrorVec>, BuildError> {
+        self.build_inner(globals, true, cancellation_flag)
+    }
+
+    fn build_inner(
+        mut self,
+        globals: &'a Variables<'a>,
+        tolerate_parse_errors: bool,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Option<TreeWithParseErrorVec>, BuildError> {
+        if self.tree.is_none() {
+            self.parse(cancellation_flag)?;
+        }
+        let tree = self.tree.take().expect("Missing parsed tree");
+        let parse_time = self.parse_time;
         let parse_errors = ParseError::into_all(tree);
-        if parse_errors.errors().len() > 0 {
+        if parse_errors.errors().len() > 0 && !tolerate_parse_errors {
             return Err(BuildError::ParseErrors(parse_errors));
         }
-        let tree = parse_errors.into_tree();
 
         let mut globals = Variables::nested(globals);
         if globals.get(&ROOT_NODE_VAR.into()).is_none() {
@@ -627,16 +1151,194 @@ impl<'a> Builder<'a> {
         // (1) this method takes ownership of the Builder; and
         // (2) it returns no values connected to 'a.
         // These together guarantee that no values connected to the lifetime 'a outlive the Tree.
-        let tree: &'a tree_sitter::Tree = unsafe { transmute(&tree) };
-        self.sgl.tsg.execute_into(
-            &mut self.graph,
-            tree,
-            self.source,
-            &mut config,
-            &(cancellation_flag as &dyn CancellationFlag),
-        )?;
+        // The parse errors, which own the Tree, are returned only after the Builder is consumed.
+        let tree: &'a tree_sitter::Tree = unsafe { transmute(parse_errors.tree()) };
+        self.skipped_matches = self.matches_in_errors(tree, parse_errors.errors());
+        let execution_start = Instant::now();
+        self.sgl
+            .tsg
+            .execute_into(
+                &mut self.graph,
+                tree,
+                self.source,
+                &mut config,
+                &(cancellation_flag as &dyn CancellationFlag),
+            )
+            .map_err(|err| self.sgl.add_tsg_files_to_execution_error(err))?;
+        let execution_time = execution_start.elapsed();
+
+        match self.profile.take() {
+            Some(profile) => {
+                self.record_stanza_profile(profile, tree);
+                let sgl = self.sgl;
+                let load_start = Instant::now();
+                self.load(cancellation_flag)?;
+                let language_profile = profile.language_mut(sgl);
+                language_profile.file_count += 1;
+                language_profile.parse_time += parse_time;
+                language_profile.execution_time += execution_time;
+                language_profile.load_time += load_start.elapsed();
+            }
+            None => self.load(cancellation_flag)?,
+        }
+
+        if parse_errors.errors().is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(parse_errors))
+        }
+    }
+
+    /// Returns the stanza index and matched syntax node id of every stanza match with a captured
+    /// node that is, or lies inside, an `ERROR` or `MISSING` node. Matches of nodes that merely
+    /// contain parse errors, such as the definitions enclosing them, are not returned.
+    fn matches_in_errors(
+        &self,
+        tree: &tree_sitter::Tree,
+        parse_errors: &[ParseError],
+    ) -> HashSet<(usize, usize)> {
+        let mut matches = HashSet::new();
+        if parse_errors.is_empty() {
+            return matches;
+        }
+        let in_error = |node: tree_sitter::Node| {
+            let mut node = Some(node);
+            while let Some(n) = node {
+                if n.is_error() || n.is_missing() {
+                    return true;
+                }
+                node = n.parent();
+            }
+            false
+        };
+        self.visit_stanza_matches(tree, |index, mat| {
+            let full_capture = mat.full_capture();
+            if in_error(full_capture)
+                || mat
+                    .named_captures()
+                    .any(|(_, _, mut nodes)| nodes.any(&in_error))
+            {
+                matches.insert((index, full_capture.id()));
+            }
+        });
+        matches
+    }
+
+    /// Visits the matches of the combined query of all stanzas, which is the query that is run when
+    /// the rules are executed, together with the index of the matched stanza.
+    fn visit_stanza_matches(
+        &self,
+        tree: &tree_sitter::Tree,
+        mut visit: impl FnMut(usize, &tree_sitter_graph::Match),
+    ) {
+        let stanza_index = self
+            .sgl
+            .tsg
+            .stanzas
+            .iter()
+            .enumerate()
+            .map(|(index, stanza)| ((stanza.range.start.row, stanza.range.start.column), index))
+            .collect::<HashMap<_, _>>();
+        self.sgl
+            .tsg
+            .try_visit_matches(tree, self.source, true, |mat| {
+                let location = mat.query_location();
+                if let Some(index) = stanza_index.get(&(location.row, location.column)) {
+                    visit(*index, &mat);
+                }
+                Ok::<_, std::convert::Infallible>(())
+            })
+            .unwrap_or_default();
+    }
 
-        self.load(cancellation_flag)
+    /// Returns the index of the stanza that created a graph node or edge, based on the TSG location
+    /// debug attribute.
+    fn stanza_for_attributes(
+        &self,
+        attributes: &tree_sitter_graph::graph::Attributes,
+    ) -> Option<usize> {
+        let row = tsg_row_for_attributes(attributes)?;
+        self.sgl
+            .tsg
+            .stanzas
+            .iter()
+            .position(|s| s.range.start.row <= row && row <= s.range.end.row)
+    }
+
+    /// Returns the graph nodes that must be skipped when loading, because they were created by a
+    /// stanza match of a node in a parse error, or refer to such a node as their scope.
+    fn skipped_nodes(&self) -> HashSet<GraphNodeRef> {
+        let mut skipped_nodes = HashSet::new();
+        if self.skipped_matches.is_empty() {
+            return skipped_nodes;
+        }
+        let match_node_attr = [DEBUG_ATTR_PREFIX, "tsg_match_node"].concat();
+        for node_ref in self.graph.iter_nodes().skip(self.injected_node_count) {
+            let node = &self.graph[node_ref];
+            let stanza = match self.stanza_for_attributes(&node.attributes) {
+                Some(stanza) => stanza,
+                None => continue,
+            };
+            let match_node = match node
+                .attributes
+                .get(match_node_attr.as_str())
+                .and_then(|value| value.as_syntax_node_ref().ok())
+            {
+                Some(match_node) => self.graph[match_node].id(),
+                None => continue,
+            };
+            if self.skipped_matches.contains(&(stanza, match_node)) {
+                skipped_nodes.insert(node_ref);
+            }
+        }
+        for node_ref in self.graph.iter_nodes().skip(self.injected_node_count) {
+            let scope = self.graph[node_ref]
+                .attributes
+                .get(SCOPE_ATTR)
+                .and_then(|scope| scope.as_graph_node_ref().ok());
+            if let Some(scope) = scope {
+                if skipped_nodes.contains(&scope) {
+                    skipped_nodes.insert(node_ref);
+                }
+            }
+        }
+        skipped_nodes
+    }
+
+    /// Records the matches of every stanza, the nodes and edges created by the statements of every
+    /// stanza, and the executed statements of every matched stanza, in the profile. Matches are
+    /// found by running the combined query of all stanzas again, and the time spent finding a match
+    /// is attributed to the matched stanza. Nodes and edges are attributed to stanzas based on their
+    /// TSG location debug attribute.
+    fn record_stanza_profile(&self, profile: &mut BuildProfile, tree: &tree_sitter::Tree) {
+        let language_profile = profile.language_mut(self.sgl);
+        let mut matched_stanzas = HashSet::new();
+        let mut match_start = Instant::now();
+        self.visit_stanza_matches(tree, |index, _| {
+            let stanza_profile = &mut language_profile.stanzas[index];
+            stanza_profile.match_count += 1;
+            stanza_profile.match_time += match_start.elapsed();
+            matched_stanzas.insert(index);
+            match_start = Instant::now();
+        });
+
+        let mut executed_lines = HashSet::new();
+        for node_ref in self.graph.iter_nodes() {
+            let node = &self.graph[node_ref];
+            executed_lines.extend(tsg_row_for_attributes(&node.attributes));
+            if let Some(index) = self.stanza_for_attributes(&node.attributes) {
+                language_profile.stanzas[index].node_count += 1;
+            }
+            for (_, edge) in node.iter_edges() {
+                executed_lines.extend(tsg_row_for_attributes(&edge.attributes));
+                if let Some(index) = self.stanza_for_attributes(&edge.attributes) {
+                    language_profile.stanzas[index].edge_count += 1;
+                }
+            }
+        }
+        for index in matched_stanzas {
+            language_profile.record_executed_statements(self.sgl, index, &executed_lines);
+        }
     }
 
     /// Create a graph node to represent the stack graph node. It is the callers responsibility to
@@ -649,6 +1351,93 @@ impl<'a> Builder<'a> {
     }
 }
 
+/// Returns the zero-based TSG line of the statement that created a graph node or edge, based on
+/// the TSG location debug attribute.
+fn tsg_row_for_attributes(attributes: &tree_sitter_graph::graph::Attributes) -> Option<usize> {
+    attributes
+        .get([DEBUG_ATTR_PREFIX, "tsg_location"].concat().as_str())?
+        .as_str()
+        .ok()?
+        .strip_prefix("line ")?
+        .split(' ')
+        .next()?
+        .parse::<usize>()
+        .ok()?
+        .checked_sub(1)
+}
+
+/// Returns the ranges of the given edits in the final source. The range of every edit is mapped
+/// through the edits that follow it, because those are relative to the source after the earlier
+/// edits.
+fn edited_ranges(edits: &[InputEdit]) -> Vec<tree_sitter::Range> {
+    let mut ranges: Vec<tree_sitter::Range> = Vec::with_capacity(edits.len());
+    for edit in edits {
+        for range in &mut ranges {
+            (range.start_byte, range.start_point) =
+                edit_position(edit, range.start_byte, range.start_point, true);
+            (range.end_byte, range.end_point) =
+                edit_position(edit, range.end_byte, range.end_point, false);
+        }
+        ranges.push(tree_sitter::Range {
+            start_byte: edit.start_byte,
+            end_byte: edit.new_end_byte,
+            start_point: edit.start_position,
+            end_point: edit.new_end_position,
+        });
+    }
+    ranges
+}
+
+/// Maps a position through an edit. Positions before the edit are unchanged, and positions after
+/// the edit are shifted. Positions inside the replaced text are moved to the start of the edit if
+/// they start a range, and to the end of the inserted text if they end one, so that ranges
+/// overlapping the edit grow to include it.
+fn edit_position(
+    edit: &InputEdit,
+    byte: usize,
+    point: tree_sitter::Point,
+    is_start: bool,
+) -> (usize, tree_sitter::Point) {
+    if byte <= edit.start_byte {
+        (byte, point)
+    } else if byte >= edit.old_end_byte {
+        let point = if point.row == edit.old_end_position.row {
+            tree_sitter::Point::new(
+                edit.new_end_position.row,
+                point.column - edit.old_end_position.column + edit.new_end_position.column,
+            )
+        } else {
+            tree_sitter::Point::new(
+                point.row - edit.old_end_position.row + edit.new_end_position.row,
+                point.column,
+            )
+        };
+        (byte - edit.old_end_byte + edit.new_end_byte, point)
+    } else if is_start {
+        (edit.start_byte, edit.start_position)
+    } else {
+        (edit.new_end_byte, edit.new_end_position)
+    }
+}
+
+/// Sorts the given ranges, and merges overlapping and adjacent ranges.
+fn merge_ranges(mut ranges: Vec<tree_sitter::Range>) -> Vec<tree_sitter::Range> {
+    ranges.sort_by_key(|range| (range.start_byte, range.end_byte));
+    let mut merged: Vec<tree_sitter::Range> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start_byte <= last.end_byte => {
+                if range.end_byte > last.end_byte {
+                    last.end_byte = range.end_byte;
+                    last.end_point = range.end_point;
+                }
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
 /// Trait to signal that the execution is cancelled
 pub trait CancellationFlag: Sync {
     fn check(&self, at: &'static str) -> Result<(), CancellationError>;
@@ -781,6 +1570,63 @@ pub enum BuildError {
     LanguageError(#[from] tree_sitter::LanguageError),
     #[error("Expected exported symbol scope in {0}, got {1}")]
     SymbolScopeError(String, String),
+    #[error("{0}")]
+    UnexpectedAttribute(AttributeDiagnostic),
+}
+
+/// A diagnostic about an attribute that is not expected on a node of its type.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AttributeDiagnostic {
+    /// The name of the unexpected attribute.
+    pub attribute: String,
+    /// The type of the node that has the attribute.
+    pub node_type: String,
+    /// Zero-based line of the TSG statement that set the attribute, or of the stanza that created
+    /// the node if the statement is not known.
+    pub tsg_line: Option<usize>,
+    /// The TSG file that contains the line, if the rules were composed from several files.
+    pub tsg_path: Option<PathBuf>,
+}
+
+impl std::fmt::Display for AttributeDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unexpected attribute ‘{}’ on node of type {}",
+            self.attribute, self.node_type
+        )
+    }
+}
+
+impl AttributeDiagnostic {
+    /// Display the diagnostic, preceded by its location in the given TSG file. If the diagnostic
+    /// has its own TSG path, that is used instead.
+    pub fn display_with_tsg_path<'a>(&'a self, tsg_path: &'a Path) -> impl std::fmt::Display + 'a {
+        DisplayAttributeDiagnostic {
+            diagnostic: self,
+            tsg_path: self.tsg_path.as_deref().unwrap_or(tsg_path),
+        }
+    }
+}
+
+struct DisplayAttributeDiagnostic<'a> {
+    diagnostic: &'a AttributeDiagnostic,
+    tsg_path: &'a Path,
+}
+
+impl std::fmt::Display for DisplayAttributeDiagnostic<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.diagnostic.tsg_line {
+            Some(line) => write!(
+                f,
+                "{}:{}: {}",
+                self.tsg_path.display(),
+                line + 1,
+                self.diagnostic
+            ),
+            None => write!(f, "{}: {}", self.tsg_path.display(), self.diagnostic),
+        }
+    }
 }
 
 impl From<stack_graphs::CancellationError> for BuildError {
@@ -842,6 +1688,9 @@ impl std::fmt::Display for DisplayBuildErrorPretty<'_> {
                     max_errors: crate::MAX_PARSE_ERRORS,
                 }
             ),
+            BuildError::UnexpectedAttribute(diagnostic) => {
+                write!(f, "{}", diagnostic.display_with_tsg_path(self.tsg_path))
+            }
             err => err.fmt(f),
         }
     }
@@ -872,11 +1721,19 @@ impl<'a> Builder<'a> {
                 .map(|_| panic!("index already remapped"));
         }
 
+        // Nodes created by stanza matches of nodes in parse errors are skipped, together with the
+        // edges from and to them. This set is only non-empty when parse errors are tolerated.
+        let skipped_nodes = self.skipped_nodes();
+
         // First create a stack graph node for each TSG node.  (The skip(...) is because the first
         // DSL nodes that we create are the proxies for the injected stack graph nodes.)
         for node_ref in self.graph.iter_nodes().skip(self.injected_node_count) {
             cancellation_flag.check("loading graph nodes")?;
+            if skipped_nodes.contains(&node_ref) {
+                continue;
+            }
             let node_type = self.get_node_type(node_ref)?;
+            self.verify_attributes(node_ref, &node_type)?;
             let handle = match node_type {
                 NodeType::DropScopes => self.load_drop_scopes(node_ref),
                 NodeType::PopScopedSymbol => self.load_pop_scoped_symbol(node_ref)?,
@@ -885,7 +1742,7 @@ impl<'a> Builder<'a> {
                 NodeType::PushSymbol => self.load_push_symbol(node_ref)?,
                 NodeType::Scope => self.load_scope(node_ref)?,
             };
-            self.load_span(node_ref, handle)?;
+            self.load_source_info(node_ref, handle)?;
             self.load_node_debug_info(node_ref, handle)?;
         }
 
@@ -898,11 +1755,17 @@ impl<'a> Builder<'a> {
         // (Technically the caller could add outgoing nodes from “jump to scope” as well, but those
         // are invalid according to the stack graph semantics and will never be followed.
         for source_ref in self.graph.iter_nodes() {
+            if skipped_nodes.contains(&source_ref) {
+                continue;
+            }
             let source = &self.graph[source_ref];
             let source_node_id = self.node_id_for_graph_node(source_ref);
             let source_handle = self.stack_graph.node_for_id(source_node_id).unwrap();
             for (sink_ref, edge) in source.iter_edges() {
                 cancellation_flag.check("loading graph edges")?;
+                if skipped_nodes.contains(&sink_ref) {
+                    continue;
+                }
                 let precedence = match edge.attributes.get(PRECEDENCE_ATTR) {
                     Some(precedence) => precedence.as_integer()? as i32,
                     None => 0,
@@ -1003,7 +1866,6 @@ impl<'a> Builder<'a> {
         let symbol = self.stack_graph.add_symbol(&symbol);
         let id = self.node_id_for_graph_node(node_ref);
         let is_definition = self.load_flag(node, IS_DEFINITION_ATTR)?;
-        self.verify_attributes(node, POP_SCOPED_SYMBOL_TYPE, &POP_SCOPED_SYMBOL_ATTRS);
         Ok(self
             .stack_graph
             .add_pop_scoped_symbol_node(id, symbol, is_definition)
@@ -1019,7 +1881,6 @@ impl<'a> Builder<'a> {
         let symbol = self.stack_graph.add_symbol(&symbol);
         let id = self.node_id_for_graph_node(node_ref);
         let is_definition = self.load_flag(node, IS_DEFINITION_ATTR)?;
-        self.verify_attributes(node, POP_SYMBOL_TYPE, &POP_SYMBOL_ATTRS);
         Ok(self
             .stack_graph
             .add_pop_symbol_node(id, symbol, is_definition)
@@ -1042,7 +1903,6 @@ impl<'a> Builder<'a> {
             None => return Err(BuildError::MissingScope(node_ref)),
         };
         let is_reference = self.load_flag(node, IS_REFERENCE_ATTR)?;
-        self.verify_attributes(node, PUSH_SCOPED_SYMBOL_TYPE, &PUSH_SCOPED_SYMBOL_ATTRS);
         Ok(self
             .stack_graph
             .add_push_scoped_symbol_node(id, symbol, scope, is_reference)
@@ -1058,7 +1918,6 @@ impl<'a> Builder<'a> {
         let symbol = self.stack_graph.add_symbol(&symbol);
         let id = self.node_id_for_graph_node(node_ref);
         let is_reference = self.load_flag(node, IS_REFERENCE_ATTR)?;
-        self.verify_attributes(node, PUSH_SYMBOL_TYPE, &PUSH_SYMBOL_ATTRS);
         Ok(self
             .stack_graph
             .add_push_symbol_node(id, symbol, is_reference)
@@ -1070,7 +1929,6 @@ impl<'a> Builder<'a> {
         let id = self.node_id_for_graph_node(node_ref);
         let is_exported =
             self.load_flag(node, IS_EXPORTED_ATTR)? || self.load_flag(node, IS_ENDPOINT_ATTR)?;
-        self.verify_attributes(node, SCOPE_TYPE, &SCOPE_ATTRS);
         Ok(self.stack_graph.add_scope_node(id, is_exported).unwrap())
     }
 
@@ -1091,6 +1949,35 @@ impl<'a> Builder<'a> {
         }
     }
 
+    fn load_source_info(
+        &mut self,
+        node_ref: GraphNodeRef,
+        node_handle: Handle<Node>,
+    ) -> Result<(), BuildError> {
+        let node = &self.graph[node_ref];
+
+        if let Some(syntax_type) = node.attributes.get(SYNTAX_TYPE_ATTR) {
+            let syntax_type = self.stack_graph.add_string(syntax_type.as_str()?);
+            let source_info = self.stack_graph.source_info_mut(node_handle);
+            source_info.syntax_type = ControlledOption::some(syntax_type);
+        }
+
+        if let Some(definiens_node) = node.attributes.get(DEFINIENS_NODE_ATTR) {
+            let definiens_node = &self.graph[definiens_node.as_syntax_node_ref()?];
+            let span = self.span_calculator.for_node(definiens_node);
+            let source_info = self.stack_graph.source_info_mut(node_handle);
+            source_info.definiens_span = span;
+        }
+
+        if let Some(fully_qualified_name) = node.attributes.get(FULLY_QUALIFIED_NAME_ATTR) {
+            let fully_qualified_name = self.stack_graph.add_string(fully_qualified_name.as_str()?);
+            let source_info = self.stack_graph.source_info_mut(node_handle);
+            source_info.fully_qualified_name = ControlledOption::some(fully_qualified_name);
+        }
+
+        self.load_span(node_ref, node_handle)
+    }
+
     fn load_span(
         &mut self,
         node_ref: GraphNodeRef,
@@ -1164,21 +2051,99 @@ impl<'a> Builder<'a> {
         Ok(())
     }
 
+    /// Reports attributes that are not expected on the node, given its type.
     fn verify_attributes(
-        &self,
-        node: &GraphNode,
-        node_type: &str,
-        allowed_attributes: &HashSet<&'static str>,
-    ) {
+        &mut self,
+        node_ref: GraphNodeRef,
+        node_type: &NodeType,
+    ) -> Result<(), BuildError> {
+        let (node_type, allowed_attributes) = match node_type {
+            NodeType::DropScopes => return Ok(()),
+            NodeType::PopScopedSymbol => (POP_SCOPED_SYMBOL_TYPE, &*POP_SCOPED_SYMBOL_ATTRS),
+            NodeType::PopSymbol => (POP_SYMBOL_TYPE, &*POP_SYMBOL_ATTRS),
+            NodeType::PushScopedSymbol => (PUSH_SCOPED_SYMBOL_TYPE, &*PUSH_SCOPED_SYMBOL_ATTRS),
+            NodeType::PushSymbol => (PUSH_SYMBOL_TYPE, &*PUSH_SYMBOL_ATTRS),
+            NodeType::Scope => (SCOPE_TYPE, &*SCOPE_ATTRS),
+        };
+        let node = &self.graph[node_ref];
         for (id, _) in node.attributes.iter() {
             let id = id.as_str();
-            if !allowed_attributes.contains(id)
-                && id != SOURCE_NODE_ATTR
-                && id != EMPTY_SOURCE_SPAN_ATTR
-                && !id.starts_with(DEBUG_ATTR_PREFIX)
+            if allowed_attributes.contains(id) || is_common_attribute(id) {
+                continue;
+            }
+            let tsg_location = tsg_row_for_attributes(&node.attributes)
+                .and_then(|row| self.attribute_tsg_row(row, id))
+                .map(|row| self.sgl.tsg_location(row));
+            let diagnostic = AttributeDiagnostic {
+                attribute: id.to_string(),
+                node_type: node_type.to_string(),
+                tsg_line: tsg_location.map(|(_, row)| row),
+                tsg_path: tsg_location
+                    .filter(|_| !self.sgl.tsg_files.is_empty())
+                    .map(|(path, _)| path.to_path_buf()),
+            };
+            if self.strict_attributes {
+                return Err(BuildError::UnexpectedAttribute(diagnostic));
+            }
+            match &mut self.attribute_diagnostics {
+                Some(diagnostics) => diagnostics.push(diagnostic),
+                None => log::warn!("{}", diagnostic.display_with_tsg_path(self.sgl.tsg_path())),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the zero-based TSG line of the `attr` statement that sets the given attribute on a
+    /// graph node created on the given line. If no such statement is found in the stanza that
+    /// created the node, for example because the attribute is set by another stanza, the line of
+    /// the stanza is returned instead.
+    fn attribute_tsg_row(&self, node_row: usize, attribute: &str) -> Option<usize> {
+        let stanza = self
+            .sgl
+            .tsg
+            .stanzas
+            .iter()
+            .find(|s| s.range.start.row <= node_row && node_row <= s.range.end.row)?;
+        let mut node_variable = None;
+        let mut statements = Vec::new();
+        collect_attribute_statements(
+            &stanza.statements,
+            node_row,
+            attribute,
+            &mut node_variable,
+            &mut statements,
+        );
+        let statement = statements
+            .iter()
+            .find(|stmt| Some(stmt.node.to_string()) == node_variable)
+            .or(statements.first());
+        Some(statement.map_or(stanza.range.start.row, |stmt| stmt.location.row))
+    }
+}
+
+/// Collects the `attr` statements that set the given attribute, and the variable of the `node`
+/// statement on the given line, from the given statements and their nested blocks.
+fn collect_attribute_statements<'s>(
+    statements: &'s [Statement],
+    node_row: usize,
+    attribute: &str,
+    node_variable: &mut Option<String>,
+    into: &mut Vec<&'s AddGraphNodeAttribute>,
+) {
+    for statement in statements {
+        match statement {
+            Statement::CreateGraphNode(stmt) if stmt.location.row == node_row => {
+                *node_variable = Some(stmt.node.to_string());
+            }
+            Statement::AddGraphNodeAttribute(stmt)
+                if stmt.attributes.iter().any(|a| a.name.as_str() == attribute) =>
             {
-                eprintln!("Unexpected attribute {} on node of type {}", id, node_type);
+                into.push(stmt);
             }
+            _ => {}
+        }
+        for block in nested_blocks(statement) {
+            collect_attribute_statements(block, node_row, attribute, node_variable, into);
         }
     }
 }
@@ -15,17 +15,23 @@ use stack_graphs::graph::StackGraph;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::ffi::OsStr;
+use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::SystemTime;
 use thiserror::Error;
 use tree_sitter::Language;
-use tree_sitter_graph::ast::File as TsgFile;
 use tree_sitter_graph::Variables;
 use tree_sitter_loader::Config as TsConfig;
 use tree_sitter_loader::LanguageConfiguration as TSLanguageConfiguration;
 use tree_sitter_loader::Loader as TsLoader;
 
+#[cfg(feature = "bincode")]
+use crate::builtins::PrecompiledBuiltins;
+#[cfg(feature = "bincode")]
+use crate::builtins::PrecompiledBuiltinsError;
 use crate::CancellationFlag;
 use crate::FileAnalyzer;
 use crate::StackGraphLanguage;
@@ -35,47 +41,112 @@ pub static DEFAULT_TSG_PATHS: Lazy<Vec<LoadPath>> =
 pub static DEFAULT_BUILTINS_PATHS: Lazy<Vec<LoadPath>> =
     Lazy::new(|| vec![LoadPath::Grammar("queries/builtins".into())]);
 
+/// The name of the global variable that selects builtins variants, as a comma-separated list of names.
+pub const BUILTINS_VAR: &str = "BUILTINS";
+
 /// Data type that holds all information to recognize and analyze files for a language
 pub struct LanguageConfiguration {
     pub language: Language,
     pub scope: Option<String>,
     pub content_regex: Option<Regex>,
     pub file_types: Vec<String>,
+    /// Additional ways to recognize files for this language, see [`FileDetection`][].
+    pub detection: FileDetection,
     pub sgl: StackGraphLanguage,
     pub builtins: StackGraph,
+    /// The minimal partial paths of the default builtins, if they were loaded from precompiled
+    /// builtins. If missing, the paths must be computed from the builtins graph.
+    pub builtins_paths: Option<Vec<stack_graphs::serde::PartialPath>>,
+    /// Builtins that can be selected in addition to the default builtins, see [`BUILTINS_VAR`][].
+    pub builtins_variants: BuiltinsVariants,
     pub special_files: FileAnalyzers,
 }
 
 impl LanguageConfiguration {
-    /// Build a language configuration from tsg and builtins sources. The tsg path
-    /// is kept for informational only, see [`StackGraphLanguage::from_source`][].
+    /// Build a language configuration from tsg and builtins sources. The rules are composed
+    /// from the tsg sources, in order. The tsg paths are kept for informational purposes only,
+    /// see [`StackGraphLanguage::from_sources`][]. The builtins variants can be selected in
+    /// addition to the default builtins, see [`BUILTINS_VAR`][].
     pub fn from_sources<'a>(
         language: Language,
         scope: Option<String>,
         content_regex: Option<Regex>,
         file_types: Vec<String>,
-        tsg_path: PathBuf,
-        tsg_source: &'a str,
+        tsg_sources: &[(PathBuf, &'a str)],
         builtins_source: Option<(PathBuf, &'a str)>,
         builtins_config: Option<&str>,
+        builtins_variants: Vec<BuiltinsVariant>,
         special_files: FileAnalyzers,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<Self, LoadError<'a>> {
-        let sgl = StackGraphLanguage::from_source(language, tsg_path.clone(), tsg_source).map_err(
-            |err| LoadError::SglParse {
+        let sgl = StackGraphLanguage::from_sources(language, tsg_sources).map_err(|err| {
+            // errors in composed rules carry their own path and source, so these are only used
+            // for errors in rules from a single file
+            let (tsg_path, tsg_source) = tsg_sources
+                .first()
+                .cloned()
+                .unwrap_or_else(|| (PathBuf::from("<tsg>"), ""));
+            LoadError::SglParse {
                 inner: err,
                 tsg_path,
                 tsg: Cow::from(tsg_source),
-            },
-        )?;
-        let mut builtins = StackGraph::new();
-        if let Some((builtins_path, builtins_source)) = builtins_source {
-            let mut builtins_globals = Variables::new();
-            if let Some(builtins_config) = builtins_config {
-                Loader::load_globals_from_config_str(builtins_config, &mut builtins_globals)?;
             }
-            let file = builtins.add_file("<builtins>").unwrap();
-            sgl.build_stack_graph_into(
+        })?;
+        let mut lc = LanguageConfiguration {
+            language,
+            scope,
+            content_regex,
+            file_types,
+            detection: FileDetection::default(),
+            sgl,
+            builtins: StackGraph::new(),
+            builtins_paths: None,
+            builtins_variants: BuiltinsVariants::new(),
+            special_files,
+        };
+        if let Some(builtins_source) = builtins_source {
+            lc.load_builtins_from_source(builtins_source, builtins_config, cancellation_flag)?;
+        }
+        for builtins_variant in builtins_variants {
+            lc.builtins_variants.add(builtins_variant);
+        }
+        Ok(lc)
+    }
+
+    /// Returns a stack graph containing the builtins of the selected variants, see
+    /// [`BuiltinsVariants::load`][]. Selected variants that this language does not have are
+    /// ignored, so that the same selection can be used for all languages of a project.
+    pub fn load_builtins_variants(
+        &self,
+        selection: &[String],
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Arc<StackGraph>, LoadError<'_>> {
+        let selection = selection
+            .iter()
+            .filter(|name| self.builtins_variants.contains(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.builtins_variants
+            .load(&self.sgl, &selection, cancellation_flag)
+    }
+
+    /// Replace the default builtins by builtins built from the given source and optional
+    /// configuration. The builtins are loaded into a file named after the builtins path, so that
+    /// the builtins of different languages can be loaded into the same graph.
+    pub fn load_builtins_from_source<'a>(
+        &mut self,
+        (builtins_path, builtins_source): (PathBuf, &'a str),
+        builtins_config: Option<&str>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), LoadError<'a>> {
+        let mut builtins = StackGraph::new();
+        let mut builtins_globals = Variables::new();
+        if let Some(builtins_config) = builtins_config {
+            Loader::load_globals_from_config_str(builtins_config, &mut builtins_globals)?;
+        }
+        let file = builtins.add_file(&builtins_path.to_string_lossy()).unwrap();
+        self.sgl
+            .build_stack_graph_into(
                 &mut builtins,
                 file,
                 builtins_source,
@@ -86,19 +157,49 @@ impl LanguageConfiguration {
                 inner: err,
                 source_path: builtins_path,
                 source: Cow::from(builtins_source),
-                tsg_path: sgl.tsg_path.clone(),
-                tsg: Cow::from(tsg_source),
+                tsg_path: self.sgl.tsg_path.clone(),
+                tsg: self.sgl.tsg_source.clone(),
             })?;
+        self.builtins = builtins;
+        self.builtins_paths = None;
+        Ok(())
+    }
+
+    /// Replace the default builtins by the given precompiled builtins, see [`crate::builtins`][].
+    #[cfg(feature = "bincode")]
+    pub fn load_precompiled_builtins(
+        &mut self,
+        precompiled_builtins: &[u8],
+    ) -> Result<(), PrecompiledBuiltinsError> {
+        let precompiled_builtins = PrecompiledBuiltins::decode(precompiled_builtins)?;
+        let mut builtins = StackGraph::new();
+        precompiled_builtins.graph.load_into(&mut builtins)?;
+        self.builtins = builtins;
+        self.builtins_paths = Some(precompiled_builtins.paths);
+        Ok(())
+    }
+
+    /// Replace the default builtins by the given precompiled builtins. If the precompiled builtins
+    /// cannot be loaded, for example because they were precompiled with an incompatible format
+    /// version, the builtins are built from the given source and optional configuration instead,
+    /// and the reason is logged as a warning.
+    #[cfg(feature = "bincode")]
+    pub fn load_builtins_with_precompiled<'a>(
+        &mut self,
+        precompiled_builtins: &[u8],
+        builtins_source: (PathBuf, &'a str),
+        builtins_config: Option<&str>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), LoadError<'a>> {
+        match self.load_precompiled_builtins(precompiled_builtins) {
+            Ok(()) => return Ok(()),
+            Err(err) => log::warn!(
+                "Cannot load precompiled builtins, building {} instead: {}",
+                builtins_source.0.display(),
+                err
+            ),
         }
-        Ok(LanguageConfiguration {
-            language,
-            scope,
-            content_regex,
-            file_types,
-            sgl,
-            builtins,
-            special_files,
-        })
+        self.load_builtins_from_source(builtins_source, builtins_config, cancellation_flag)
     }
 
     // Extracted from tree_sitter_loader::Loader::language_configuration_for_file_name
@@ -107,19 +208,11 @@ impl LanguageConfiguration {
         path: &Path,
         content: &mut dyn ContentProvider,
     ) -> std::io::Result<Option<&'a LanguageConfiguration>> {
-        let mut best_score = -1isize;
-        let mut best = None;
+        let mut matches = Vec::new();
         for language in languages {
-            if let Some(score) =
-                matches_file(&language.file_types, &language.content_regex, path, content)?
-            {
-                if score > best_score {
-                    best_score = score;
-                    best = Some(language);
-                }
-            }
+            matches.push(language.explain_file_match(path, content)?);
         }
-        Ok(best)
+        Ok(FileMatch::best(&matches).map(|index| &languages[index]))
     }
 
     pub fn matches_file(
@@ -127,10 +220,59 @@ impl LanguageConfiguration {
         path: &Path,
         content: &mut dyn ContentProvider,
     ) -> std::io::Result<bool> {
-        matches_file(&self.file_types, &self.content_regex, path, content).map(|l| l.is_some())
+        self.explain_file_match(path, content).map(|m| m.is_match())
+    }
+
+    /// Match the given file against this language, and explain why it does or does not match.
+    pub fn explain_file_match(
+        &self,
+        path: &Path,
+        content: &mut dyn ContentProvider,
+    ) -> std::io::Result<FileMatch> {
+        explain_file_match(
+            &self.file_types,
+            &self.content_regex,
+            &self.detection,
+            path,
+            content,
+        )
     }
 }
 
+/// Describes how files are recognized in addition to their file type. This makes it possible to
+/// recognize scripts without an extension, and to choose between languages that share a file type.
+/// Files that have one of the file types are only checked for a modeline, if any modeline names
+/// are given. The content of other files is only read if any content detection is configured. Only
+/// the start of a file is considered, and files that cannot be read as text do not match by
+/// content.
+///
+/// Languages that are loaded from paths take the first line regex from the grammar's `package.json`,
+/// and the other settings from an optional `[detection]` section in the configuration file next to
+/// the TSG path, e.g. `queries/stack-graphs.cfg`:
+///
+/// ```ini
+/// [detection]
+/// interpreters = node, nodejs
+/// modelines = javascript, js
+/// priority = 1
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FileDetection {
+    /// Regex matched against the first line of files that do not have one of the file types.
+    pub first_line_regex: Option<Regex>,
+    /// Interpreter names matched against the interpreter of a shebang line, such as `node` for
+    /// `#!/usr/bin/env node`. Names must match exactly, so versioned names must be listed as well.
+    pub interpreters: Vec<String>,
+    /// Language names matched, case insensitively, against Emacs (`-*- mode: NAME -*-`) and Vim
+    /// (`vim: set ft=NAME:`) modelines in the first lines of a file. If any names are given, files
+    /// with a modeline for a different language do not match, even if they have one of the file
+    /// types.
+    pub modelines: Vec<String>,
+    /// Priority of the language over other languages that match a file with the same kind of
+    /// evidence. Higher priorities are preferred, and the default priority is zero.
+    pub priority: isize,
+}
+
 #[derive(Clone, Default)]
 pub struct FileAnalyzers {
     file_analyzers: HashMap<String, Arc<dyn FileAnalyzer + Send + Sync>>,
@@ -157,6 +299,97 @@ impl FileAnalyzers {
     }
 }
 
+/// A builtins source that can be selected by name, in addition to the default builtins of a
+/// language. Variants can be used to model different versions of a standard library, or stubs
+/// provided by a project.
+#[derive(Clone, Debug)]
+pub struct BuiltinsVariant {
+    pub name: String,
+    pub path: PathBuf,
+    pub source: String,
+    pub config: String,
+}
+
+/// The builtins variants of a language. The builtins for a selection of variants are loaded on
+/// demand, and cached per selection.
+#[derive(Default)]
+pub struct BuiltinsVariants {
+    variants: Vec<BuiltinsVariant>,
+    cache: Mutex<HashMap<Vec<String>, Arc<StackGraph>>>,
+}
+
+impl BuiltinsVariants {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a builtins variant, replacing any existing variant with the same name.
+    pub fn add(&mut self, variant: BuiltinsVariant) {
+        self.variants.retain(|v| v.name != variant.name);
+        self.variants.push(variant);
+        self.cache.get_mut().unwrap().clear();
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.variants.iter().any(|v| v.name == name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.variants.iter().map(|v| v.name.as_str())
+    }
+
+    /// Returns a stack graph containing the builtins of the selected variants. The graph is
+    /// loaded using the given stack graph language, unless it was loaded for the same selection
+    /// before. Each variant is loaded into its own file, named after the variant's path.
+    pub fn load(
+        &self,
+        sgl: &StackGraphLanguage,
+        selection: &[String],
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Arc<StackGraph>, LoadError<'_>> {
+        let mut selection = selection.to_vec();
+        selection.sort();
+        selection.dedup();
+        if let Some(graph) = self.cache.lock().unwrap().get(&selection) {
+            return Ok(graph.clone());
+        }
+        let mut graph = StackGraph::new();
+        for name in &selection {
+            let variant = self
+                .variants
+                .iter()
+                .find(|v| &v.name == name)
+                .ok_or_else(|| LoadError::UnknownBuiltinsVariant(name.clone()))?;
+            Loader::load_builtins_into(
+                sgl,
+                &variant.path,
+                Cow::from(variant.source.as_str()),
+                &variant.config,
+                &mut graph,
+                cancellation_flag,
+            )?;
+        }
+        let graph = Arc::new(graph);
+        self.cache.lock().unwrap().insert(selection, graph.clone());
+        Ok(graph)
+    }
+}
+
+/// Returns the names of the builtins variants selected by the given value of the
+/// [`BUILTINS_VAR`][] global.
+pub fn builtins_selection(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
 /// A load path specifies a file to load from, either as a regular path or relative to the grammar location.
 #[derive(Clone, Debug)]
 pub enum LoadPath {
@@ -211,6 +444,7 @@ impl Loader {
             tsg_paths,
             builtins_paths,
             cache: Vec::new(),
+            sources: Vec::new(),
         })))
     }
 
@@ -227,6 +461,7 @@ impl Loader {
             tsg_paths,
             builtins_paths,
             cache: Vec::new(),
+            sources: Vec::new(),
         })))
     }
 
@@ -272,6 +507,41 @@ impl Loader {
         }
     }
 
+    /// Explain which language is selected for the given file, and why. An explanation is returned
+    /// for every language that was considered, at most one of which is marked as selected. This does
+    /// not load the stack graph definitions of any language.
+    pub fn explain_file(
+        &mut self,
+        path: &Path,
+        content: &mut dyn ContentProvider,
+    ) -> Result<Vec<FileLanguageExplanation>, LoadError<'static>> {
+        match &mut self.0 {
+            LoaderImpl::Paths(loader) => loader.explain_file(path, content),
+            LoaderImpl::Provided(loader) => loader.explain_file(path, content),
+        }
+    }
+
+    /// Returns the language configurations that have been loaded so far. New configurations are
+    /// added at the end, so the position of a configuration does not change until languages are
+    /// invalidated.
+    pub fn loaded_configurations(&self) -> Vec<&LanguageConfiguration> {
+        match &self.0 {
+            LoaderImpl::Paths(loader) => loader.cache.iter().map(|e| &e.1).collect(),
+            LoaderImpl::Provided(loader) => loader.configurations.iter().collect(),
+        }
+    }
+
+    /// Remove cached languages whose stack graph definitions or builtins changed on disk since they
+    /// were loaded, so that they are reloaded the next time they are used. Languages that failed to
+    /// load are removed as well if their sources changed. Returns the removed languages. Provided
+    /// language configurations are never reloaded.
+    pub fn invalidate_changed(&mut self) -> Vec<tree_sitter::Language> {
+        match &mut self.0 {
+            LoaderImpl::Paths(loader) => loader.invalidate_changed(),
+            LoaderImpl::Provided(_) => Vec::new(),
+        }
+    }
+
     pub fn load_globals_from_config_path(
         path: &Path,
         globals: &mut Variables,
@@ -291,18 +561,6 @@ impl Loader {
         Self::load_globals_from_config(&conf, globals)
     }
 
-    fn load_tsg<'a>(
-        language: Language,
-        tsg_source: Cow<'a, str>,
-    ) -> Result<TsgFile, LoadError<'a>> {
-        let tsg = TsgFile::from_str(language, &tsg_source).map_err(|err| LoadError::TsgParse {
-            inner: err,
-            tsg_path: PathBuf::from("<unknown tsg path>"),
-            tsg: Cow::from(tsg_source),
-        })?;
-        Ok(tsg)
-    }
-
     fn load_builtins_into<'a>(
         sgl: &StackGraphLanguage,
         path: &Path,
@@ -360,6 +618,32 @@ impl FileLanguageConfigurations<'_> {
     }
 }
 
+/// Explains whether a language was selected for a file, see [`Loader::explain_file`][].
+#[derive(Clone, Debug)]
+pub struct FileLanguageExplanation {
+    pub language: Language,
+    pub scope: Option<String>,
+    pub file_types: Vec<String>,
+    /// The path the language was found at, if it was discovered by a path loader.
+    pub language_path: Option<PathBuf>,
+    /// Whether the file matched the language, and why.
+    pub file_match: FileMatch,
+    /// Whether the language was selected as the file's primary language.
+    pub selected: bool,
+}
+
+impl FileLanguageExplanation {
+    fn select_best(explanations: &mut [FileLanguageExplanation]) {
+        let matches = explanations
+            .iter()
+            .map(|e| e.file_match.clone())
+            .collect::<Vec<_>>();
+        if let Some(index) = FileMatch::best(&matches) {
+            explanations[index].selected = true;
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum LoadError<'a> {
     #[error("{0}")]
@@ -379,6 +663,8 @@ pub enum LoadError<'a> {
     NoLanguagesFound(String),
     #[error("No TSG file found")]
     NoTsgFound,
+    #[error("Unknown builtins variant ‘{0}’")]
+    UnknownBuiltinsVariant(String),
     #[error(transparent)]
     Reader(Box<dyn std::error::Error + Send + Sync>),
     #[error("{inner}")]
@@ -456,12 +742,8 @@ impl LanguageConfigurationsLoader {
         path: &Path,
         content: &mut dyn ContentProvider,
     ) -> Result<Option<tree_sitter::Language>, LoadError<'static>> {
-        for configuration in self.configurations.iter() {
-            if configuration.matches_file(path, content)? {
-                return Ok(Some(configuration.language));
-            }
-        }
-        Ok(None)
+        let primary = LanguageConfiguration::best_for_file(&self.configurations, path, content)?;
+        Ok(primary.map(|lc| lc.language))
     }
 
     /// Load a stack graph language for the given file. Loading is based on the loader configuration and the given file path.
@@ -482,6 +764,26 @@ impl LanguageConfigurationsLoader {
         }
         Ok(FileLanguageConfigurations { primary, secondary })
     }
+
+    pub fn explain_file(
+        &self,
+        path: &Path,
+        content: &mut dyn ContentProvider,
+    ) -> Result<Vec<FileLanguageExplanation>, LoadError<'static>> {
+        let mut explanations = Vec::new();
+        for lc in &self.configurations {
+            explanations.push(FileLanguageExplanation {
+                language: lc.language,
+                scope: lc.scope.clone(),
+                file_types: lc.file_types.clone(),
+                language_path: None,
+                file_match: lc.explain_file_match(path, content)?,
+                selected: false,
+            });
+        }
+        FileLanguageExplanation::select_best(&mut explanations);
+        Ok(explanations)
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -494,6 +796,29 @@ struct PathLoader {
     tsg_paths: Vec<LoadPath>,
     builtins_paths: Vec<LoadPath>,
     cache: Vec<(Language, LanguageConfiguration)>,
+    /// Source files that languages were loaded from, including languages that failed to load.
+    sources: Vec<(Language, Vec<SourceFile>)>,
+}
+
+/// A file that was read while loading a language, and its modification time at that moment.
+struct SourceFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl SourceFile {
+    fn new(path: PathBuf) -> Self {
+        let modified = Self::modified(&path);
+        Self { path, modified }
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    fn has_changed(&self) -> bool {
+        Self::modified(&self.path) != self.modified
+    }
 }
 
 impl PathLoader {
@@ -549,24 +874,71 @@ impl PathLoader {
         let index = match index {
             Some(index) => index,
             None => {
-                let tsg = self.load_tsg_from_paths(&language)?;
-                let sgl = StackGraphLanguage::new(language.language, tsg);
+                let (tsg_dir, tsg_paths) = self.find_tsg_paths(&language)?;
+                let builtins_path = self.find_builtins_path(&language);
+                let mut sources = tsg_dir
+                    .iter()
+                    .chain(tsg_paths.iter())
+                    .map(|p| SourceFile::new(p.clone()))
+                    .collect::<Vec<_>>();
+                if let Some(builtins_path) = &builtins_path {
+                    sources.push(SourceFile::new(builtins_path.clone()));
+                    sources.push(SourceFile::new(builtins_path.with_extension("cfg")));
+                }
+                let (builtins_dir, builtins_variant_paths) =
+                    self.find_builtins_variant_paths(&language)?;
+                sources.extend(builtins_dir.map(SourceFile::new));
+                for builtins_variant_path in &builtins_variant_paths {
+                    sources.push(SourceFile::new(builtins_variant_path.clone()));
+                    sources.push(SourceFile::new(builtins_variant_path.with_extension("cfg")));
+                }
+                self.sources.retain(|e| e.0 != language.language);
+                self.sources.push((language.language, sources));
+
+                let mut tsg_sources = Vec::new();
+                for tsg_path in tsg_paths {
+                    let tsg_source = std::fs::read_to_string(&tsg_path)?;
+                    tsg_sources.push((tsg_path, tsg_source));
+                }
+                let sgl = StackGraphLanguage::from_sources(
+                    language.language,
+                    &tsg_sources
+                        .iter()
+                        .map(|(path, source)| (path.clone(), source.as_str()))
+                        .collect::<Vec<_>>(),
+                )
+                // errors in composed rules carry their own path and source, so these are only
+                // used for errors in rules from a single file
+                .map_err(|err| LoadError::SglParse {
+                    inner: err,
+                    tsg_path: tsg_sources[0].0.clone(),
+                    tsg: Cow::from(tsg_sources[0].1.clone()),
+                })?;
 
                 let mut builtins = StackGraph::new();
-                self.load_builtins_from_paths_into(
-                    &language,
-                    &sgl,
-                    &mut builtins,
-                    cancellation_flag,
-                )?;
+                if let Some(builtins_path) = &builtins_path {
+                    Self::load_builtins_from_path_into(
+                        &sgl,
+                        builtins_path,
+                        &mut builtins,
+                        cancellation_flag,
+                    )?;
+                }
+                let mut builtins_variants = BuiltinsVariants::new();
+                for builtins_variant_path in builtins_variant_paths {
+                    builtins_variants.add(Self::read_builtins_variant(builtins_variant_path)?);
+                }
 
                 let lc = LanguageConfiguration {
                     language: language.language,
                     scope: language.scope,
                     content_regex: language.content_regex,
                     file_types: language.file_types,
+                    detection: language.detection,
                     sgl,
                     builtins,
+                    builtins_paths: None,
+                    builtins_variants,
                     special_files: FileAnalyzers::new(),
                 };
                 self.cache.push((language.language, lc));
@@ -623,9 +995,11 @@ impl PathLoader {
         language_path: &Path,
         file_path: &Path,
         file_content: &mut dyn ContentProvider,
-    ) -> Result<Option<&SupplementedLanguage>, LoadError> {
+    ) -> Result<Option<&SupplementedLanguage>, LoadError<'static>> {
         let scope = self.scope.as_deref();
-        let languages = self.loader.languages_at_path(language_path, scope)?;
+        let languages = self
+            .loader
+            .languages_at_path(language_path, scope, &self.tsg_paths)?;
         if languages.is_empty() {
             return Err(LoadError::NoLanguagesFound(format!(
                 "at {}{}",
@@ -641,57 +1015,172 @@ impl PathLoader {
         Ok(None)
     }
 
-    // Load the TSG file for the given language and path
-    fn load_tsg_from_paths(
+    // Explain language selection for the given file, considering paths and scope fields
+    fn explain_file(
+        &mut self,
+        file_path: &Path,
+        file_content: &mut dyn ContentProvider,
+    ) -> Result<Vec<FileLanguageExplanation>, LoadError<'static>> {
+        let mut found_languages = false;
+        let mut explanations = Vec::new();
+        for path in &self.paths {
+            let languages =
+                self.loader
+                    .languages_at_path(path, self.scope.as_deref(), &self.tsg_paths)?;
+            found_languages |= !languages.is_empty();
+            let mut path_explanations = Vec::new();
+            for language in languages {
+                path_explanations.push(FileLanguageExplanation {
+                    language: language.language,
+                    scope: language.scope.clone(),
+                    file_types: language.file_types.clone(),
+                    language_path: Some(path.clone()),
+                    file_match: language.explain_file_match(file_path, file_content)?,
+                    selected: false,
+                });
+            }
+            // languages are selected from the first path that has a matching language
+            if !explanations
+                .iter()
+                .any(|e: &FileLanguageExplanation| e.selected)
+            {
+                FileLanguageExplanation::select_best(&mut path_explanations);
+            }
+            explanations.extend(path_explanations);
+        }
+        if !found_languages {
+            return Err(LoadError::NoLanguagesFound(format!(
+                "in {}{}",
+                self.paths.iter().map(|p| p.display()).format(":"),
+                self.scope
+                    .as_ref()
+                    .map_or(String::default(), |s| format!(" for scope {}", s)),
+            )));
+        }
+        Ok(explanations)
+    }
+
+    // Remove cached languages for which any of the source files changed
+    fn invalidate_changed(&mut self) -> Vec<Language> {
+        let changed = self
+            .sources
+            .iter()
+            .filter(|(_, sources)| sources.iter().any(|s| s.has_changed()))
+            .map(|(language, _)| *language)
+            .collect::<Vec<_>>();
+        self.sources.retain(|e| !changed.contains(&e.0));
+        self.cache.retain(|e| !changed.contains(&e.0));
+        changed
+    }
+
+    // Find the TSG files for the given language. If a TSG path is a directory instead of a file,
+    // the rules are composed from all TSG files in that directory, in file name order. The
+    // directory is returned as well, so that added or removed files can be detected.
+    fn find_tsg_paths(
         &self,
         language: &SupplementedLanguage,
-    ) -> Result<TsgFile, LoadError<'static>> {
+    ) -> Result<(Option<PathBuf>, Vec<PathBuf>), LoadError<'static>> {
         for tsg_path in &self.tsg_paths {
-            let mut tsg_path = tsg_path.get_for_grammar(&language.root_path);
+            let tsg_dir = tsg_path.get_for_grammar(&language.root_path);
+            let mut tsg_path = tsg_dir.clone();
             if tsg_path.extension().is_none() {
                 tsg_path.set_extension("tsg");
             }
-            if tsg_path.exists() {
-                let tsg_source = std::fs::read_to_string(tsg_path)?;
-                return Loader::load_tsg(language.language, Cow::from(tsg_source));
+            if tsg_path.is_file() {
+                return Ok((None, vec![tsg_path]));
+            }
+            if tsg_dir.is_dir() {
+                let mut tsg_paths = Vec::new();
+                for entry in std::fs::read_dir(&tsg_dir)? {
+                    let path = entry?.path();
+                    if path.is_file() && path.extension().map_or(false, |e| e == "tsg") {
+                        tsg_paths.push(path);
+                    }
+                }
+                if !tsg_paths.is_empty() {
+                    tsg_paths.sort();
+                    return Ok((Some(tsg_dir), tsg_paths));
+                }
             }
         }
         return Err(LoadError::NoTsgFound);
     }
 
     // Builtins are loaded from queries/builtins.EXT and an optional queries/builtins.cfg configuration.
-    // In the future, we may extend this to support builtins spread over multiple files queries/builtins/NAME.EXT
+    // Builtins variants, which can be selected in addition, are loaded from queries/builtins/NAME.EXT
     // and optional corresponding configuration files queries/builtins/NAME.cfg.
-    fn load_builtins_from_paths_into(
-        &self,
-        language: &SupplementedLanguage,
-        sgl: &StackGraphLanguage,
-        graph: &mut StackGraph,
-        cancellation_flag: &dyn CancellationFlag,
-    ) -> Result<(), LoadError<'static>> {
+    fn find_builtins_path(&self, language: &SupplementedLanguage) -> Option<PathBuf> {
         for builtins_path in &self.builtins_paths {
             let mut builtins_path = builtins_path.get_for_grammar(&language.root_path);
             if builtins_path.exists() && !builtins_path.is_dir() {
-                return Self::load_builtins_from_path_into(
-                    sgl,
-                    &builtins_path,
-                    graph,
-                    cancellation_flag,
-                );
+                return Some(builtins_path);
             }
             for extension in &language.file_types {
                 builtins_path.set_extension(extension);
                 if builtins_path.exists() && !builtins_path.is_dir() {
-                    return Self::load_builtins_from_path_into(
-                        sgl,
-                        &builtins_path,
-                        graph,
-                        cancellation_flag,
-                    );
+                    return Some(builtins_path);
                 }
             }
         }
-        Ok(())
+        None
+    }
+
+    // Find the builtins variants for the given language, in the directory next to the builtins
+    // file. The directory is returned as well, so that added or removed files can be detected.
+    fn find_builtins_variant_paths(
+        &self,
+        language: &SupplementedLanguage,
+    ) -> Result<(Option<PathBuf>, Vec<PathBuf>), LoadError<'static>> {
+        for builtins_path in &self.builtins_paths {
+            let builtins_dir = builtins_path
+                .get_for_grammar(&language.root_path)
+                .with_extension("");
+            if !builtins_dir.is_dir() {
+                continue;
+            }
+            let mut variant_paths = Vec::new();
+            for entry in std::fs::read_dir(&builtins_dir)? {
+                let path = entry?.path();
+                if path.is_file()
+                    && path.extension().map_or(false, |e| {
+                        language
+                            .file_types
+                            .iter()
+                            .any(|file_type| e == file_type.as_str())
+                    })
+                {
+                    variant_paths.push(path);
+                }
+            }
+            variant_paths.sort();
+            return Ok((Some(builtins_dir), variant_paths));
+        }
+        Ok((None, Vec::new()))
+    }
+
+    fn read_builtins_variant(path: PathBuf) -> Result<BuiltinsVariant, LoadError<'static>> {
+        let name = path
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let source = std::fs::read_to_string(&path)?;
+        let config = Self::read_builtins_config(&path)?;
+        Ok(BuiltinsVariant {
+            name,
+            path,
+            source,
+            config,
+        })
+    }
+
+    // Read the optional configuration file that corresponds to the given builtins file
+    fn read_builtins_config(builtins_path: &Path) -> std::io::Result<String> {
+        let config_path = builtins_path.with_extension("cfg");
+        if config_path.exists() {
+            std::fs::read_to_string(config_path)
+        } else {
+            Ok("".into())
+        }
     }
 
     fn load_builtins_from_path_into(
@@ -700,14 +1189,8 @@ impl PathLoader {
         graph: &mut StackGraph,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), LoadError<'static>> {
-        let source = std::fs::read_to_string(builtins_path.clone())?;
-        let mut config_path = builtins_path.to_path_buf();
-        config_path.set_extension("cfg");
-        let config = if config_path.exists() {
-            std::fs::read_to_string(builtins_path.clone())?
-        } else {
-            "".into()
-        };
+        let source = std::fs::read_to_string(builtins_path)?;
+        let config = Self::read_builtins_config(builtins_path)?;
         Loader::load_builtins_into(
             sgl,
             builtins_path,
@@ -735,7 +1218,8 @@ impl SupplementedTsLoader {
         &mut self,
         path: &Path,
         scope: Option<&str>,
-    ) -> Result<Vec<&SupplementedLanguage>, LoadError> {
+        tsg_paths: &[LoadPath],
+    ) -> Result<Vec<&SupplementedLanguage>, LoadError<'static>> {
         if !self.1.contains_key(path) {
             let languages = self
                 .0
@@ -750,7 +1234,11 @@ impl SupplementedTsLoader {
                 .zip(configurations.into_iter())
                 .map(SupplementedLanguage::from)
                 .filter(|language| scope.map_or(true, |scope| language.matches_scope(scope)))
-                .collect::<Vec<_>>();
+                .map(|mut language| {
+                    language.read_detection_config(tsg_paths)?;
+                    Ok(language)
+                })
+                .collect::<Result<Vec<_>, LoadError<'static>>>()?;
             self.1.insert(path.to_path_buf(), languages);
         }
         Ok(self.1[path].iter().map(|l| l).collect())
@@ -763,6 +1251,7 @@ struct SupplementedLanguage {
     pub scope: Option<String>,
     pub content_regex: Option<Regex>,
     pub file_types: Vec<String>,
+    pub detection: FileDetection,
     pub root_path: PathBuf,
 }
 
@@ -771,13 +1260,33 @@ impl SupplementedLanguage {
         self.scope.as_ref().map_or(false, |s| s == scope)
     }
 
-    // Extracted from tree_sitter_loader::Loader::language_configuration_for_file_name
-    pub fn matches_file(
+    // File detection settings are read from an optional configuration file next to the TSG path,
+    // e.g. queries/stack-graphs.cfg, since tree-sitter's package.json only has a first line regex.
+    fn read_detection_config(&mut self, tsg_paths: &[LoadPath]) -> Result<(), LoadError<'static>> {
+        for tsg_path in tsg_paths {
+            let config_path = tsg_path
+                .get_for_grammar(&self.root_path)
+                .with_extension("cfg");
+            if config_path.is_file() {
+                let conf = Ini::load_from_file(&config_path)?;
+                return load_detection_from_config(&conf, &mut self.detection);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn explain_file_match(
         &self,
         path: &Path,
         content: &mut dyn ContentProvider,
-    ) -> std::io::Result<Option<isize>> {
-        matches_file(&self.file_types, &self.content_regex, path, content)
+    ) -> std::io::Result<FileMatch> {
+        explain_file_match(
+            &self.file_types,
+            &self.content_regex,
+            &self.detection,
+            path,
+            content,
+        )
     }
 
     // Extracted from tree_sitter_loader::Loader::language_configuration_for_file_name
@@ -786,17 +1295,11 @@ impl SupplementedLanguage {
         path: &Path,
         content: &mut dyn ContentProvider,
     ) -> std::io::Result<Option<&'a SupplementedLanguage>> {
-        let mut best_score = -1isize;
-        let mut best = None;
-        for language in languages {
-            if let Some(score) = language.matches_file(path, content)? {
-                if score > best_score {
-                    best_score = score;
-                    best = Some(language);
-                }
-            }
+        let mut matches = Vec::new();
+        for language in &languages {
+            matches.push(language.explain_file_match(path, content)?);
         }
-        Ok(best)
+        Ok(FileMatch::best(&matches).map(|index| languages[index]))
     }
 }
 
@@ -806,12 +1309,53 @@ impl From<(Language, &TSLanguageConfiguration<'_>)> for SupplementedLanguage {
             scope: config.scope.clone(),
             content_regex: config.content_regex.clone(),
             file_types: config.file_types.clone(),
+            detection: FileDetection {
+                first_line_regex: config._first_line_regex.clone(),
+                ..FileDetection::default()
+            },
             root_path: config.root_path.clone(),
             language,
         }
     }
 }
 
+// Read the [detection] section of a configuration, which has comma-separated lists of interpreters
+// and modelines, and a priority.
+fn load_detection_from_config(
+    conf: &Ini,
+    detection: &mut FileDetection,
+) -> Result<(), LoadError<'static>> {
+    fn names(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect()
+    }
+    if let Some(detection_section) = conf.section(Some("detection")) {
+        for (name, value) in detection_section.iter() {
+            match name {
+                "interpreters" => detection.interpreters = names(value),
+                "modelines" => detection.modelines = names(value),
+                "priority" => {
+                    detection.priority = value.trim().parse().map_err(|_| {
+                        LoadError::Reader(
+                            format!("Invalid detection priority {} in config", value).into(),
+                        )
+                    })?
+                }
+                _ => {
+                    return Err(LoadError::Reader(
+                        format!("Unknown detection setting {} in config", name).into(),
+                    ))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 // Extracted from tree_sitter_loader::Loader::language_configuration_for_file_name
 pub fn matches_file(
     file_types: &Vec<String>,
@@ -819,43 +1363,336 @@ pub fn matches_file(
     path: &Path,
     content: &mut dyn ContentProvider,
 ) -> std::io::Result<Option<isize>> {
-    // Check path extension
-    if !path
+    let file_match = explain_file_match(
+        file_types,
+        content_regex,
+        &FileDetection::default(),
+        path,
+        content,
+    )?;
+    match file_match {
+        FileMatch::Match { score, .. } => Ok(Some(score)),
+        FileMatch::NoMatch(_) => Ok(None),
+    }
+}
+
+/// Match a file against the given language properties, and explain why it does or does not match.
+pub fn explain_file_match(
+    file_types: &[String],
+    content_regex: &Option<Regex>,
+    detection: &FileDetection,
+    path: &Path,
+    content: &mut dyn ContentProvider,
+) -> std::io::Result<FileMatch> {
+    let priority = detection.priority;
+    let file_type = path
         .extension()
         .and_then(OsStr::to_str)
-        .map_or(false, |ext| file_types.iter().any(|ft| ft == ext))
+        .filter(|ext| file_types.iter().any(|ft| ft == ext));
+
+    if let Some(file_type) = file_type {
+        // Modelines are explicit, and can select between languages that share a file type
+        if !detection.modelines.is_empty() {
+            if let Some(file_match) =
+                content_prefix(content, path).and_then(|content| match_modeline(detection, content))
+            {
+                return Ok(file_match);
+            }
+        }
+
+        // Apply content regex
+        if let Some(content_regex) = content_regex {
+            if let Some(content) = content.get(path)? {
+                // If the language configuration has a content regex, assign
+                // a score based on the length of the first match.
+                if let Some(mat) = content_regex.find(content) {
+                    let score = (mat.end() - mat.start()) as isize;
+                    return Ok(FileMatch::Match {
+                        evidence: MatchEvidence::FileType(file_type.to_string()),
+                        priority,
+                        score,
+                    });
+                } else {
+                    return Ok(FileMatch::NoMatch(format!(
+                        "file type ‘{}’ matches, but content does not match ‘{}’",
+                        file_type, content_regex
+                    )));
+                }
+            }
+        }
+        return Ok(FileMatch::Match {
+            evidence: MatchEvidence::FileType(file_type.to_string()),
+            priority,
+            score: 0,
+        });
+    }
+
+    let needs_content = detection.first_line_regex.is_some()
+        || !detection.interpreters.is_empty()
+        || !detection.modelines.is_empty();
+    let content = if needs_content {
+        content_prefix(content, path)
+    } else {
+        None
+    };
+
+    if let Some(content) = content {
+        // Modelines are explicit, and override all other evidence
+        if let Some(file_match) = match_modeline(detection, content) {
+            return Ok(file_match);
+        }
+
+        if let Some(interpreter) = find_shebang_interpreter(content) {
+            if detection.interpreters.iter().any(|i| i == interpreter) {
+                return Ok(FileMatch::Match {
+                    evidence: MatchEvidence::Shebang(interpreter.to_string()),
+                    priority,
+                    score: 0,
+                });
+            }
+        }
+
+        if let Some(first_line_regex) = &detection.first_line_regex {
+            let first_line = content.lines().next().unwrap_or_default();
+            if let Some(mat) = first_line_regex.find(first_line) {
+                let score = (mat.end() - mat.start()) as isize;
+                return Ok(FileMatch::Match {
+                    evidence: MatchEvidence::FirstLine,
+                    priority,
+                    score,
+                });
+            }
+        }
+    }
+
+    Ok(FileMatch::NoMatch(
+        match path.extension().and_then(OsStr::to_str) {
+            Some(ext) => format!(
+                "file type ‘{}’ is not one of {}",
+                ext,
+                file_types.join(", ")
+            ),
+            None => "file has no file type and no other evidence matches".to_string(),
+        },
+    ))
+}
+
+/// The result of matching a file against a language.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileMatch {
+    /// The file matches because of the given evidence. If several languages match a file, the one
+    /// with the strongest evidence is selected, followed by the highest priority and score.
+    Match {
+        evidence: MatchEvidence,
+        priority: isize,
+        score: isize,
+    },
+    /// The file does not match for the given reason.
+    NoMatch(String),
+}
+
+impl FileMatch {
+    pub fn is_match(&self) -> bool {
+        matches!(self, Self::Match { .. })
+    }
+
+    fn rank(&self) -> Option<(usize, isize, isize)> {
+        match self {
+            Self::Match {
+                evidence,
+                priority,
+                score,
+            } => Some((evidence.strength(), *priority, *score)),
+            Self::NoMatch(_) => None,
+        }
+    }
+
+    /// Returns the index of the best match, if any. Ties are resolved in favor of the first match.
+    fn best(matches: &[FileMatch]) -> Option<usize> {
+        let mut best: Option<(usize, (usize, isize, isize))> = None;
+        for (index, file_match) in matches.iter().enumerate() {
+            if let Some(rank) = file_match.rank() {
+                if best.map_or(true, |(_, best_rank)| rank > best_rank) {
+                    best = Some((index, rank));
+                }
+            }
+        }
+        best.map(|(index, _)| index)
+    }
+}
+
+impl std::fmt::Display for FileMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Match {
+                evidence,
+                priority,
+                score,
+            } => write!(
+                f,
+                "matches by {} (priority {}, score {})",
+                evidence, priority, score
+            ),
+            Self::NoMatch(reason) => write!(f, "does not match: {}", reason),
+        }
+    }
+}
+
+/// The evidence by which a file matches a language, from weakest to strongest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MatchEvidence {
+    /// The file has one of the file types of the language.
+    FileType(String),
+    /// The first line of the file matches the first line regex of the language.
+    FirstLine,
+    /// The file has a shebang line with one of the interpreters of the language.
+    Shebang(String),
+    /// The file has a modeline with one of the names of the language.
+    Modeline(String),
+}
+
+impl MatchEvidence {
+    fn strength(&self) -> usize {
+        match self {
+            Self::FileType(_) => 0,
+            Self::FirstLine => 1,
+            Self::Shebang(_) => 2,
+            Self::Modeline(_) => 3,
+        }
+    }
+}
+
+impl std::fmt::Display for MatchEvidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FileType(file_type) => write!(f, "file type ‘{}’", file_type),
+            Self::FirstLine => write!(f, "first line"),
+            Self::Shebang(interpreter) => write!(f, "shebang interpreter ‘{}’", interpreter),
+            Self::Modeline(name) => write!(f, "modeline ‘{}’", name),
+        }
+    }
+}
+
+/// Returns the start of the file that is used to detect its language by content. Files that cannot
+/// be read as text, such as binary files, have no content.
+fn content_prefix<'a>(content: &'a mut dyn ContentProvider, path: &Path) -> Option<&'a str> {
+    content
+        .get_prefix(path, CONTENT_PREFIX_LENGTH)
+        .ok()
+        .flatten()
+}
+
+/// Matches the modeline in the given content against the modeline names of a language. Returns
+/// `None` if the language has no modeline names or the content has no modeline, and otherwise
+/// whether the modeline selects the language.
+fn match_modeline(detection: &FileDetection, content: &str) -> Option<FileMatch> {
+    if detection.modelines.is_empty() {
+        return None;
+    }
+    let name = find_modeline(content)?;
+    if detection
+        .modelines
+        .iter()
+        .any(|m| m.eq_ignore_ascii_case(name))
     {
-        return Ok(None);
+        Some(FileMatch::Match {
+            evidence: MatchEvidence::Modeline(name.to_string()),
+            priority: detection.priority,
+            score: 0,
+        })
+    } else {
+        Some(FileMatch::NoMatch(format!(
+            "modeline selects language ‘{}’",
+            name
+        )))
     }
+}
 
-    // Apply content regex
-    let content = content.get(path)?;
-    if let (Some(file_content), Some(content_regex)) = (content, &content_regex) {
-        // If the language configuration has a content regex, assign
-        // a score based on the length of the first match.
-        if let Some(mat) = content_regex.find(&file_content) {
-            let score = (mat.end() - mat.start()) as isize;
-            return Ok(Some(score));
-        } else {
-            return Ok(None);
+/// The number of bytes at the start of a file that are read to detect its language by content.
+const CONTENT_PREFIX_LENGTH: usize = 1024;
+
+/// The number of lines at the start of a file that are searched for modelines.
+const MODELINE_LINES: usize = 5;
+
+static EMACS_MODELINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"-\*-(.*?)-\*-").unwrap());
+static VIM_MODELINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:^|\s)(?:vi|vim|ex):(?:.*?[\s:])?(?:ft|filetype|syn|syntax)=([^\s:]+)").unwrap()
+});
+
+// Find the language name in an Emacs or Vim modeline in the first lines of the content
+fn find_modeline(content: &str) -> Option<&str> {
+    for line in content.lines().take(MODELINE_LINES) {
+        if let Some(captures) = EMACS_MODELINE.captures(line) {
+            let variables = captures.get(1).unwrap().as_str();
+            if !variables.contains(':') {
+                return Some(variables.trim());
+            }
+            for variable in variables.split(';') {
+                if let Some((name, value)) = variable.split_once(':') {
+                    if name.trim().eq_ignore_ascii_case("mode") {
+                        return Some(value.trim());
+                    }
+                }
+            }
+        }
+        if let Some(captures) = VIM_MODELINE.captures(line) {
+            return Some(captures.get(1).unwrap().as_str());
         }
     }
+    None
+}
+
+// Find the interpreter name in a shebang line, looking through `env` if necessary
+fn find_shebang_interpreter(content: &str) -> Option<&str> {
+    let line = content.lines().next()?.strip_prefix("#!")?;
+    let mut args = line.split_whitespace();
+    let mut interpreter = file_name(args.next()?);
+    if interpreter == "env" {
+        // skip options and variable assignments
+        interpreter = args
+            .find(|arg| !arg.starts_with('-') && !arg.contains('='))
+            .map(file_name)?;
+    }
+    Some(interpreter)
+}
 
-    Ok(Some(0isize))
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
 }
 
 pub trait ContentProvider {
     fn get(&mut self, path: &Path) -> std::io::Result<Option<&str>>;
+
+    /// Returns at most the given number of bytes from the start of the content, cut at a character
+    /// boundary. Implementations can override this to avoid reading complete files.
+    fn get_prefix(&mut self, path: &Path, length: usize) -> std::io::Result<Option<&str>> {
+        Ok(self
+            .get(path)?
+            .map(|content| truncate_at_char_boundary(content, length)))
+    }
+}
+
+// Truncate the string to at most the given number of bytes, without splitting a character
+fn truncate_at_char_boundary(content: &str, length: usize) -> &str {
+    let mut end = length.min(content.len());
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    &content[..end]
 }
 
 /// FileReader reads files from the filesystem and caches the most recently read file.
 pub struct FileReader {
     cache: Option<(PathBuf, String)>,
+    prefix_cache: Option<(PathBuf, String)>,
 }
 
 impl FileReader {
     pub fn new() -> Self {
-        Self { cache: None }
+        Self {
+            cache: None,
+            prefix_cache: None,
+        }
     }
 
     pub fn get(&mut self, path: &Path) -> std::io::Result<&str> {
@@ -871,6 +1708,34 @@ impl ContentProvider for FileReader {
     fn get(&mut self, path: &Path) -> std::io::Result<Option<&str>> {
         self.get(path).map(Some)
     }
+
+    /// Reads only the start of the file, unless the complete file was read before. Fails if the
+    /// start of the file is not valid UTF-8.
+    fn get_prefix(&mut self, path: &Path, length: usize) -> std::io::Result<Option<&str>> {
+        if matches!(&self.cache, Some((p, _)) if p == path) {
+            let content = &self.cache.as_ref().unwrap().1;
+            return Ok(Some(truncate_at_char_boundary(content, length)));
+        }
+        if !matches!(&self.prefix_cache, Some((p, _)) if p == path) {
+            let mut bytes = Vec::new();
+            std::fs::File::open(path)?
+                .take(length as u64)
+                .read_to_end(&mut bytes)?;
+            let prefix = match String::from_utf8(bytes) {
+                Ok(prefix) => prefix,
+                // a character may be cut off at the end of the prefix
+                Err(err) if err.utf8_error().error_len().is_none() => {
+                    let valid_length = err.utf8_error().valid_up_to();
+                    let mut bytes = err.into_bytes();
+                    bytes.truncate(valid_length);
+                    String::from_utf8(bytes).expect("prefix is valid UTF-8")
+                }
+                Err(err) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err)),
+            };
+            self.prefix_cache = Some((path.to_path_buf(), prefix));
+        }
+        Ok(Some(&self.prefix_cache.as_ref().unwrap().1))
+    }
 }
 
 impl ContentProvider for Option<&str> {
@@ -0,0 +1,395 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Defines profiles of the execution of TSG stack graph construction rules.
+//!
+//! A profile is collected by passing it to [`Builder::set_profile`][crate::Builder::set_profile].
+//! For every stanza, the profile records how often its query matched, how much time was spent
+//! matching it, how many graph nodes and edges were created by its statements, and which of its
+//! statements were executed. Matches are counted and timed by running the combined query of all
+//! stanzas, which is the query that is run during execution, once more after the execution. The
+//! time spent finding a match is attributed to the matched stanza. The execution of graph
+//! construction rules does not report the time spent executing the statements of a stanza, so that
+//! time is only included in the total execution time, and the number of nodes and edges created by
+//! a stanza is the best indication of the work done by its statements.
+//!
+//! The execution of graph construction rules does not report which statements ran, so execution
+//! is derived from the matches and the constructed graph. The statements at the top level of a
+//! stanza are executed for every match of the stanza. The statements in the arms of `if` and
+//! `scan` statements and in the body of `for` statements are only executed conditionally. Such a
+//! block is known to be executed if one of its `node` or `edge` statements created a graph node or
+//! edge, which is identified by the TSG location in the debug info of the graph. Statements in
+//! blocks without `node` or `edge` statements are therefore never reported as executed.
+//!
+//! Profiles are collected per language, identified by the TSG path of its rules. For rules that
+//! are composed from several files, the stanzas and statements are located in the file they come
+//! from, and [`LanguageProfile::files`][] gives the profile of every file.
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use tree_sitter_graph::ast::Statement;
+use tree_sitter_graph::Location;
+
+use crate::StackGraphLanguage;
+
+/// A profile of building stack graphs, for all languages that were used.
+#[derive(Clone, Debug, Default)]
+pub struct BuildProfile {
+    languages: BTreeMap<PathBuf, LanguageProfile>,
+}
+
+/// A profile of building stack graphs with the rules of a language.
+#[derive(Clone, Debug)]
+pub struct LanguageProfile {
+    /// Number of files that were built.
+    pub file_count: usize,
+    /// Time spent parsing source files.
+    pub parse_time: Duration,
+    /// Time spent executing the graph construction rules.
+    pub execution_time: Duration,
+    /// Time spent loading the constructed graphs into stack graphs.
+    pub load_time: Duration,
+    pub stanzas: Vec<StanzaProfile>,
+}
+
+/// A profile of the stanzas of a single TSG file of the rules of a language.
+#[derive(Clone, Copy, Debug)]
+pub struct TsgFileProfile<'a> {
+    pub tsg_path: &'a Path,
+    pub stanzas: &'a [StanzaProfile],
+}
+
+/// A profile of a single stanza.
+#[derive(Clone, Debug)]
+pub struct StanzaProfile {
+    /// The TSG file that contains the stanza.
+    pub tsg_path: PathBuf,
+    /// Zero-based line of the stanza in the TSG file.
+    pub line: usize,
+    /// The first line of the stanza query.
+    pub query: String,
+    pub match_count: usize,
+    /// Time spent finding matches of the stanza in the combined query of all stanzas. This does not
+    /// include the time spent executing the statements of the stanza.
+    pub match_time: Duration,
+    pub node_count: usize,
+    pub edge_count: usize,
+    /// The statements of the stanza, including the ones nested in other statements, in the order
+    /// in which they appear in the TSG file.
+    pub statements: Vec<StatementProfile>,
+}
+
+/// A profile of a single statement.
+#[derive(Clone, Debug)]
+pub struct StatementProfile {
+    /// Zero-based line of the statement in the TSG file.
+    pub line: usize,
+    /// Whether the statement is known to have been executed.
+    pub executed: bool,
+}
+
+impl BuildProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the profile for the given language, creating an empty one if necessary.
+    pub(crate) fn language_mut(&mut self, sgl: &StackGraphLanguage) -> &mut LanguageProfile {
+        self.languages
+            .entry(sgl.tsg_path().to_path_buf())
+            .or_insert_with(|| LanguageProfile::new(sgl))
+    }
+
+    /// Returns the profiles of all languages, by TSG path.
+    pub fn languages(&self) -> impl Iterator<Item = (&Path, &LanguageProfile)> {
+        self.languages.iter().map(|(p, l)| (p.as_path(), l))
+    }
+}
+
+impl LanguageProfile {
+    fn new(sgl: &StackGraphLanguage) -> Self {
+        let source_lines = sgl.tsg_source().lines().collect::<Vec<_>>();
+        let stanzas = sgl
+            .tsg()
+            .stanzas
+            .iter()
+            .map(|stanza| {
+                let mut statements = Vec::new();
+                collect_statements(sgl, &stanza.statements, &mut statements);
+                let (tsg_path, line) = sgl.tsg_location(stanza.range.start.row);
+                StanzaProfile {
+                    tsg_path: tsg_path.to_path_buf(),
+                    line,
+                    query: source_lines
+                        .get(stanza.range.start.row)
+                        .map(|line| line.trim().to_string())
+                        .unwrap_or_default(),
+                    match_count: 0,
+                    match_time: Duration::ZERO,
+                    node_count: 0,
+                    edge_count: 0,
+                    statements,
+                }
+            })
+            .collect();
+        Self {
+            file_count: 0,
+            parse_time: Duration::ZERO,
+            execution_time: Duration::ZERO,
+            load_time: Duration::ZERO,
+            stanzas,
+        }
+    }
+
+    /// Returns the stanzas that matched at least once, ordered by the time spent on them, with
+    /// the most expensive stanza first.
+    pub fn sorted_stanzas(&self) -> Vec<&StanzaProfile> {
+        let mut stanzas = self
+            .stanzas
+            .iter()
+            .filter(|s| s.match_count > 0)
+            .collect::<Vec<_>>();
+        stanzas.sort_by(|a, b| {
+            b.match_time
+                .cmp(&a.match_time)
+                .then((b.node_count + b.edge_count).cmp(&(a.node_count + a.edge_count)))
+                .then(a.tsg_path.cmp(&b.tsg_path))
+                .then(a.line.cmp(&b.line))
+        });
+        stanzas
+    }
+
+    /// Returns the profiles of the TSG files that the rules were composed from, in order. Rules
+    /// from a single TSG file have a single file profile.
+    pub fn files(&self) -> Vec<TsgFileProfile<'_>> {
+        let mut files = Vec::new();
+        let mut start = 0;
+        for end in 1..=self.stanzas.len() {
+            if end == self.stanzas.len()
+                || self.stanzas[end].tsg_path != self.stanzas[start].tsg_path
+            {
+                files.push(TsgFileProfile {
+                    tsg_path: &self.stanzas[start].tsg_path,
+                    stanzas: &self.stanzas[start..end],
+                });
+                start = end;
+            }
+        }
+        files
+    }
+
+    /// Returns the number of stanzas that matched at least once.
+    pub fn matched_stanza_count(&self) -> usize {
+        matched_stanza_count(&self.stanzas)
+    }
+
+    /// Returns the number of statements in all stanzas.
+    pub fn statement_count(&self) -> usize {
+        statement_count(&self.stanzas)
+    }
+
+    /// Returns the number of statements in all stanzas that are known to have been executed.
+    pub fn executed_statement_count(&self) -> usize {
+        executed_statement_count(&self.stanzas)
+    }
+
+    /// Marks the statements of the given stanza as executed for a match of the stanza. Nested
+    /// statements are marked based on the given TSG lines of the `node` and `edge` statements that
+    /// created graph nodes and edges.
+    pub(crate) fn record_executed_statements(
+        &mut self,
+        sgl: &StackGraphLanguage,
+        stanza_index: usize,
+        executed_lines: &HashSet<usize>,
+    ) {
+        let mut statements = self.stanzas[stanza_index].statements.iter_mut();
+        mark_executed_statements(
+            &sgl.tsg().stanzas[stanza_index].statements,
+            true,
+            executed_lines,
+            &mut statements,
+        );
+    }
+}
+
+impl TsgFileProfile<'_> {
+    /// Returns the number of stanzas in this file that matched at least once.
+    pub fn matched_stanza_count(&self) -> usize {
+        matched_stanza_count(self.stanzas)
+    }
+
+    /// Returns the number of statements in all stanzas in this file.
+    pub fn statement_count(&self) -> usize {
+        statement_count(self.stanzas)
+    }
+
+    /// Returns the number of statements in all stanzas in this file that are known to have been
+    /// executed.
+    pub fn executed_statement_count(&self) -> usize {
+        executed_statement_count(self.stanzas)
+    }
+}
+
+fn matched_stanza_count(stanzas: &[StanzaProfile]) -> usize {
+    stanzas.iter().filter(|s| s.match_count > 0).count()
+}
+
+fn statement_count(stanzas: &[StanzaProfile]) -> usize {
+    stanzas.iter().map(|s| s.statements.len()).sum()
+}
+
+fn executed_statement_count(stanzas: &[StanzaProfile]) -> usize {
+    stanzas
+        .iter()
+        .flat_map(|s| s.statements.iter())
+        .filter(|s| s.executed)
+        .count()
+}
+
+/// Collects the statements, including the ones nested in other statements, in order. The lines of
+/// the statements are relative to the TSG file they are in.
+fn collect_statements(
+    sgl: &StackGraphLanguage,
+    statements: &[Statement],
+    into: &mut Vec<StatementProfile>,
+) {
+    for statement in statements {
+        into.push(StatementProfile {
+            line: sgl.tsg_location(statement_location(statement).row).1,
+            executed: false,
+        });
+        for block in nested_blocks(statement) {
+            collect_statements(sgl, block, into);
+        }
+    }
+}
+
+/// Marks the statements of a block as executed, in the same order as they were collected by
+/// [`collect_statements`]. Nested blocks are executed if the block is, and if one of their `node`
+/// or `edge` statements created a graph node or edge.
+fn mark_executed_statements<'a>(
+    statements: &[Statement],
+    executed: bool,
+    executed_lines: &HashSet<usize>,
+    profiles: &mut impl Iterator<Item = &'a mut StatementProfile>,
+) {
+    for statement in statements {
+        if let Some(profile) = profiles.next() {
+            profile.executed |= executed;
+        }
+        for block in nested_blocks(statement) {
+            let block_executed = executed && created_graph_elements(block, executed_lines);
+            mark_executed_statements(block, block_executed, executed_lines, profiles);
+        }
+    }
+}
+
+/// Returns whether any `node` or `edge` statement in the block, or in its nested blocks, created
+/// a graph node or edge.
+fn created_graph_elements(statements: &[Statement], executed_lines: &HashSet<usize>) -> bool {
+    statements.iter().any(|statement| {
+        let creates_elements = matches!(
+            statement,
+            Statement::CreateGraphNode(_) | Statement::CreateEdge(_)
+        );
+        (creates_elements && executed_lines.contains(&statement_location(statement).row))
+            || nested_blocks(statement)
+                .into_iter()
+                .any(|block| created_graph_elements(block, executed_lines))
+    })
+}
+
+/// Returns the blocks of statements nested in a statement.
+pub(crate) fn nested_blocks(statement: &Statement) -> Vec<&[Statement]> {
+    match statement {
+        Statement::Scan(stmt) => stmt.arms.iter().map(|a| a.statements.as_slice()).collect(),
+        Statement::If(stmt) => stmt.arms.iter().map(|a| a.statements.as_slice()).collect(),
+        Statement::ForIn(stmt) => vec![stmt.statements.as_slice()],
+        _ => Vec::new(),
+    }
+}
+
+fn statement_location(statement: &Statement) -> Location {
+    match statement {
+        Statement::DeclareImmutable(stmt) => stmt.location,
+        Statement::DeclareMutable(stmt) => stmt.location,
+        Statement::Assign(stmt) => stmt.location,
+        Statement::CreateGraphNode(stmt) => stmt.location,
+        Statement::AddGraphNodeAttribute(stmt) => stmt.location,
+        Statement::CreateEdge(stmt) => stmt.location,
+        Statement::AddEdgeAttribute(stmt) => stmt.location,
+        Statement::Scan(stmt) => stmt.location,
+        Statement::Print(stmt) => stmt.location,
+        Statement::If(stmt) => stmt.location,
+        Statement::ForIn(stmt) => stmt.location,
+    }
+}
+
+impl std::ops::AddAssign<Self> for BuildProfile {
+    fn add_assign(&mut self, rhs: Self) {
+        for (tsg_path, rhs) in rhs.languages {
+            let lhs = match self.languages.get_mut(&tsg_path) {
+                Some(lhs) => lhs,
+                None => {
+                    self.languages.insert(tsg_path, rhs);
+                    continue;
+                }
+            };
+            lhs.file_count += rhs.file_count;
+            lhs.parse_time += rhs.parse_time;
+            lhs.execution_time += rhs.execution_time;
+            lhs.load_time += rhs.load_time;
+            for (lhs, rhs) in lhs.stanzas.iter_mut().zip(rhs.stanzas) {
+                lhs.match_count += rhs.match_count;
+                lhs.match_time += rhs.match_time;
+                lhs.node_count += rhs.node_count;
+                lhs.edge_count += rhs.edge_count;
+                for (lhs, rhs) in lhs.statements.iter_mut().zip(rhs.statements) {
+                    lhs.executed |= rhs.executed;
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for BuildProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (tsg_path, profile) in self.languages() {
+            writeln!(
+                f,
+                "{}: {} files, parsing {:.3?}, execution {:.3?}, loading {:.3?}",
+                tsg_path.display(),
+                profile.file_count,
+                profile.parse_time,
+                profile.execution_time,
+                profile.load_time,
+            )?;
+            writeln!(
+                f,
+                "  {:>12} {:>10} {:>10} {:>10}  stanza",
+                "match time", "matches", "nodes", "edges"
+            )?;
+            for stanza in profile.sorted_stanzas() {
+                writeln!(
+                    f,
+                    "  {:>12} {:>10} {:>10} {:>10}  {}:{}: {}",
+                    format!("{:.3?}", stanza.match_time),
+                    stanza.match_count,
+                    stanza.node_count,
+                    stanza.edge_count,
+                    stanza.tsg_path.display(),
+                    stanza.line + 1,
+                    stanza.query,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
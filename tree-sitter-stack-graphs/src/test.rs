@@ -32,11 +32,33 @@
 //!
 //!  - `defined`: takes a comma-separated list of line numbers, and expects a reference at this
 //!    position to resolves to definitions on those lines.
+//!  - `defined-in`: takes a comma-separated list of `PATH:LINE` pairs, and expects a reference at
+//!    this position to resolve to definitions on those lines of the fragments with those paths.
+//!  - `defined-count`: takes a number, and expects a reference at this position to resolve to
+//!    exactly that many definitions.
+//!  - `not-defined`: takes a comma-separated list of line numbers, and expects a reference at this
+//!    position to not resolve to definitions on any of those lines. If no lines are given, the
+//!    reference is expected to not resolve at all.
+//!  - `references`: takes a comma-separated list of line numbers, and expects the definition at
+//!    this position to be the resolution of references on exactly those lines.
 //!  - `defines`: takes a comma-separated list of names, and expects definitions at this position
 //!    with the given names.
 //!  - `refers`: takes a comma-separated list of names, and expects references at this position
 //!    with the given names.
 //!
+//! ## Expected failures
+//!
+//! Assertions that are known to fail can be marked by adding `(xfail)` after the assertion values:
+//!
+//! ``` skip
+//! print(foo)
+//! #     ^ defined: 1 (xfail)
+//! ```
+//!
+//! All assertions in a test are expected to fail if the test contains an `--- xfail ---` header.
+//! Expected failures do not cause the test to fail. Assertions that are expected to fail but pass
+//! are reported separately, so that it is noticed when the underlying problem is fixed.
+//!
 //! ## Fragments for multi-file testing
 //!
 //! Test files may also consist of multiple fragments, which are treated as separate files in the
@@ -65,6 +87,7 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use stack_graphs::arena::Handle;
 use stack_graphs::assert::Assertion;
+use stack_graphs::assert::AssertionContext;
 use stack_graphs::assert::AssertionError;
 use stack_graphs::assert::AssertionSource;
 use stack_graphs::assert::AssertionTarget;
@@ -72,9 +95,11 @@ use stack_graphs::graph::File;
 use stack_graphs::graph::Node;
 use stack_graphs::graph::SourceInfo;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::graph::Symbol;
 use stack_graphs::partial::PartialPaths;
 use stack_graphs::stitching::Database;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::path::Path;
 use std::path::PathBuf;
 use thiserror::Error;
@@ -83,17 +108,24 @@ use tree_sitter_graph::Variables;
 use crate::CancellationFlag;
 
 const DEFINED: &'static str = "defined";
+const DEFINED_COUNT: &'static str = "defined-count";
+const DEFINED_IN: &'static str = "defined-in";
 const DEFINES: &'static str = "defines";
+const NOT_DEFINED: &'static str = "not-defined";
+const REFERENCES: &'static str = "references";
 const REFERS: &'static str = "refers";
 
 static PATH_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r#"---\s*path:\s*([^\s]+)\s*---"#).unwrap());
 static GLOBAL_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r#"---\s*global:\s*([^\s]+)=([^\s]+)\s*---"#).unwrap());
+static XFAIL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"---\s*xfail\s*---"#).unwrap());
+static ASSERTION_XFAIL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\s*\(xfail\)"#).unwrap());
 static ASSERTION_REGEX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r#"(\^)\s*(\w+):\s*([^\s,]+(?:\s*,\s*[^\s,]+)*)?"#).unwrap());
+    Lazy::new(|| Regex::new(r#"(\^)\s*([\w-]+):\s*([^\s,]+(?:\s*,\s*[^\s,]+)*)?"#).unwrap());
 static LINE_NUMBER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\d+"#).unwrap());
 static NAME_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"[^\s,]+"#).unwrap());
+static PATH_LINE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^(.+):(\d+)$"#).unwrap());
 
 /// An error that can occur while parsing tests
 #[derive(Debug, Error)]
@@ -104,6 +136,8 @@ pub enum TestError {
     GlobalBeforeFirstFragment(usize),
     InvalidAssertion(usize, String),
     InvalidColumn(usize, usize, usize),
+    InvalidAssertionValue(usize, String),
+    UnknownFragmentPath(usize, String),
 }
 
 impl std::fmt::Display for TestError {
@@ -140,6 +174,17 @@ impl std::fmt::Display for TestError {
                 column + 1,
                 regular_line + 1
             ),
+            Self::InvalidAssertionValue(line, value) => {
+                write!(f, "Invalid assertion value {} on line {}", value, line + 1)
+            }
+            Self::UnknownFragmentPath(line, path) => {
+                write!(
+                    f,
+                    "Assertion on line {} refers to unknown fragment path {}",
+                    line + 1,
+                    path
+                )
+            }
         }
     }
 }
@@ -149,6 +194,8 @@ pub struct Test {
     pub path: PathBuf,
     pub fragments: Vec<TestFragment>,
     pub graph: StackGraph,
+    /// Whether all assertions in the test are expected to fail.
+    pub xfail: bool,
 }
 
 /// A fragment from a stack graph test
@@ -158,6 +205,8 @@ pub struct TestFragment {
     pub path: PathBuf,
     pub source: String,
     pub assertions: Vec<Assertion>,
+    /// Indices of the assertions that are expected to fail.
+    pub expected_failures: HashSet<usize>,
     pub globals: HashMap<String, String>,
 }
 
@@ -176,6 +225,7 @@ impl Test {
         let mut current_source = String::new();
         let mut current_globals = HashMap::new();
         let mut have_globals = false;
+        let mut xfail = false;
         let mut prev_source = String::new();
         let mut line_files = Vec::new();
         let mut line_count = 0;
@@ -203,6 +253,7 @@ impl Test {
                         path: current_path,
                         source: current_source,
                         assertions: Vec::new(),
+                        expected_failures: HashSet::new(),
                         globals: current_globals,
                     });
                 } else {
@@ -231,6 +282,9 @@ impl Test {
                     ));
                 }
 
+                Self::push_whitespace_for(&current_line, &mut current_source);
+            } else if XFAIL_REGEX.is_match(current_line.content) {
+                xfail = true;
                 Self::push_whitespace_for(&current_line, &mut current_source);
             } else {
                 current_source.push_str(current_line.content);
@@ -255,6 +309,7 @@ impl Test {
                 path: current_path,
                 source: current_source,
                 assertions: Vec::new(),
+                expected_failures: HashSet::new(),
                 globals: current_globals,
             });
         }
@@ -268,6 +323,7 @@ impl Test {
             path: path.to_path_buf(),
             fragments,
             graph,
+            xfail,
         })
     }
 
@@ -297,6 +353,7 @@ impl TestFragment {
         F: Fn(usize) -> Option<Handle<File>>,
     {
         self.assertions.clear();
+        self.expected_failures.clear();
 
         let mut current_line_span_calculator = SpanCalculator::new(&self.source);
         let mut last_regular_line: Option<PositionedSubstring> = None;
@@ -305,7 +362,13 @@ impl TestFragment {
         for (current_line_number, current_line) in
             PositionedSubstring::lines_iter(&self.source).enumerate()
         {
-            if let Some(m) = ASSERTION_REGEX.captures_iter(current_line.content).next() {
+            // an expected failure marker is removed before parsing the assertion, so that it is
+            // not mistaken for an assertion value
+            let (content, xfail) = match ASSERTION_XFAIL_REGEX.find(current_line.content) {
+                Some(m) => (&current_line.content[..m.start()], true),
+                None => (current_line.content, false),
+            };
+            if let Some(m) = ASSERTION_REGEX.captures_iter(content).next() {
                 // assertion line
                 let last_regular_line = last_regular_line.as_ref().ok_or_else(|| {
                     TestError::AssertionRefersToNonSourceLine(current_line_number)
@@ -342,20 +405,68 @@ impl TestFragment {
                     position,
                 };
 
+                if xfail {
+                    self.expected_failures.insert(self.assertions.len());
+                }
+                let values = values_match.map(|m| m.as_str()).unwrap_or("");
                 match assertion_match.as_str() {
                     DEFINED => {
+                        let targets =
+                            Self::parse_line_targets(values, current_line_number, &line_file)?;
+                        self.assertions.push(Assertion::Defined { source, targets });
+                    }
+                    DEFINED_COUNT => {
+                        let count = values.trim().parse::<usize>().map_err(|_| {
+                            TestError::InvalidAssertionValue(
+                                current_line_number,
+                                values.to_string(),
+                            )
+                        })?;
+                        self.assertions
+                            .push(Assertion::DefinedCount { source, count });
+                    }
+                    DEFINED_IN => {
                         let mut targets = Vec::new();
-                        for line in LINE_NUMBER_REGEX
-                            .find_iter(values_match.map(|m| m.as_str()).unwrap_or(""))
-                        {
-                            let line = line.as_str().parse::<usize>().unwrap() - 1;
-                            let file = line_file(line).ok_or(
-                                TestError::AssertionRefersToNonSourceLine(current_line_number),
+                        for value in NAME_REGEX.find_iter(values) {
+                            let captures =
+                                PATH_LINE_REGEX.captures(value.as_str()).ok_or_else(|| {
+                                    TestError::InvalidAssertionValue(
+                                        current_line_number,
+                                        value.as_str().to_string(),
+                                    )
+                                })?;
+                            let path = captures.get(1).unwrap().as_str();
+                            let file = graph.get_file(path).ok_or_else(|| {
+                                TestError::UnknownFragmentPath(
+                                    current_line_number,
+                                    path.to_string(),
+                                )
+                            })?;
+                            let line = Self::parse_line(
+                                captures.get(2).unwrap().as_str(),
+                                current_line_number,
                             )?;
+                            if line_file(line) != Some(file) {
+                                return Err(TestError::AssertionRefersToNonSourceLine(
+                                    current_line_number,
+                                ));
+                            }
                             targets.push(AssertionTarget { file, line });
                         }
                         self.assertions.push(Assertion::Defined { source, targets });
                     }
+                    NOT_DEFINED => {
+                        let targets =
+                            Self::parse_line_targets(values, current_line_number, &line_file)?;
+                        self.assertions
+                            .push(Assertion::NotDefined { source, targets });
+                    }
+                    REFERENCES => {
+                        let targets =
+                            Self::parse_line_targets(values, current_line_number, &line_file)?;
+                        self.assertions
+                            .push(Assertion::Referenced { source, targets });
+                    }
                     DEFINES => {
                         let mut symbols = Vec::new();
                         for name in
@@ -392,6 +503,35 @@ impl TestFragment {
 
         Ok(())
     }
+
+    /// Parse a comma-separated list of line numbers into assertion targets.
+    fn parse_line_targets<F>(
+        values: &str,
+        current_line_number: usize,
+        line_file: &F,
+    ) -> Result<Vec<AssertionTarget>, TestError>
+    where
+        F: Fn(usize) -> Option<Handle<File>>,
+    {
+        let mut targets = Vec::new();
+        for line in LINE_NUMBER_REGEX.find_iter(values) {
+            let line = Self::parse_line(line.as_str(), current_line_number)?;
+            let file = line_file(line).ok_or(TestError::AssertionRefersToNonSourceLine(
+                current_line_number,
+            ))?;
+            targets.push(AssertionTarget { file, line });
+        }
+        Ok(targets)
+    }
+
+    /// Parse a one-based line number into a zero-based line index.
+    fn parse_line(value: &str, current_line_number: usize) -> Result<usize, TestError> {
+        value
+            .parse::<usize>()
+            .ok()
+            .and_then(|line| line.checked_sub(1))
+            .ok_or_else(|| TestError::InvalidAssertionValue(current_line_number, value.to_string()))
+    }
 }
 
 /// Result of running a stack graph test.
@@ -399,6 +539,9 @@ impl TestFragment {
 pub struct TestResult {
     success_count: usize,
     failures: Vec<TestFailure>,
+    expected_failures: Vec<TestFailure>,
+    unexpected_successes: Vec<UnexpectedSuccess>,
+    cases: Vec<TestCase>,
 }
 
 impl TestResult {
@@ -406,15 +549,25 @@ impl TestResult {
         Self {
             failures: Vec::new(),
             success_count: 0,
+            expected_failures: Vec::new(),
+            unexpected_successes: Vec::new(),
+            cases: Vec::new(),
         }
     }
 
-    fn add_success(&mut self) {
-        self.success_count += 1;
-    }
-
-    fn add_failure(&mut self, reason: TestFailure) {
-        self.failures.push(reason);
+    /// Record the outcome of a single assertion or check.
+    pub(crate) fn add_case(&mut self, case: TestCase) {
+        match &case.outcome {
+            TestCaseOutcome::Success => self.success_count += 1,
+            TestCaseOutcome::Failure(failure) => self.failures.push(failure.clone()),
+            TestCaseOutcome::ExpectedFailure(failure) => {
+                self.expected_failures.push(failure.clone())
+            }
+            TestCaseOutcome::UnexpectedSuccess(success) => {
+                self.unexpected_successes.push(success.clone())
+            }
+        }
+        self.cases.push(case);
     }
 
     /// Number of successfull assertions.
@@ -435,15 +588,47 @@ impl TestResult {
         self.failures.into_iter()
     }
 
+    /// Number of assertions that failed, and were expected to fail.
+    pub fn expected_failure_count(&self) -> usize {
+        self.expected_failures.len()
+    }
+
+    pub fn expected_failures_iter(&self) -> std::slice::Iter<'_, TestFailure> {
+        self.expected_failures.iter()
+    }
+
+    /// Number of assertions that succeeded, but were expected to fail.
+    pub fn unexpected_success_count(&self) -> usize {
+        self.unexpected_successes.len()
+    }
+
+    pub fn unexpected_successes_iter(&self) -> std::slice::Iter<'_, UnexpectedSuccess> {
+        self.unexpected_successes.iter()
+    }
+
+    /// Iterate over the outcomes of all assertions and checks, in the order they were run.
+    pub fn cases_iter(&self) -> std::slice::Iter<'_, TestCase> {
+        self.cases.iter()
+    }
+
     /// Total number of assertions that were run.
     pub fn count(&self) -> usize {
-        self.success_count() + self.failure_count()
+        self.success_count()
+            + self.failure_count()
+            + self.expected_failure_count()
+            + self.unexpected_success_count()
     }
 
     pub fn absorb(&mut self, other: TestResult) {
         self.success_count += other.success_count;
         let mut failures = other.failures;
         self.failures.append(&mut failures);
+        let mut expected_failures = other.expected_failures;
+        self.expected_failures.append(&mut expected_failures);
+        let mut unexpected_successes = other.unexpected_successes;
+        self.unexpected_successes.append(&mut unexpected_successes);
+        let mut cases = other.cases;
+        self.cases.append(&mut cases);
     }
 }
 
@@ -455,6 +640,69 @@ impl std::fmt::Display for TestResult {
             self.count(),
             self.success_count(),
             self.failure_count()
+        )?;
+        if self.expected_failure_count() > 0 {
+            write!(f, ", {} failed as expected", self.expected_failure_count())?;
+        }
+        if self.unexpected_success_count() > 0 {
+            write!(
+                f,
+                ", {} passed unexpectedly",
+                self.unexpected_success_count()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of a single assertion or check of a test.
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    /// Short description of the assertion or check, such as `3:5: defined: 1`.
+    pub name: String,
+    /// Path of the fragment that contains the assertion, if the case is an assertion.
+    pub fragment_path: Option<PathBuf>,
+    /// Position the assertion applies to, if the case is an assertion.
+    pub position: Option<Position>,
+    /// Content of the source line the assertion applies to, if the case is an assertion.
+    pub source_line: Option<String>,
+    pub outcome: TestCaseOutcome,
+}
+
+impl TestCase {
+    /// Returns whether the outcome of this case should fail the test.
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self.outcome,
+            TestCaseOutcome::Failure(_) | TestCaseOutcome::UnexpectedSuccess(_)
+        )
+    }
+}
+
+/// Outcome of a test case.
+#[derive(Debug, Clone)]
+pub enum TestCaseOutcome {
+    Success,
+    Failure(TestFailure),
+    ExpectedFailure(TestFailure),
+    UnexpectedSuccess(UnexpectedSuccess),
+}
+
+/// An assertion that succeeded, but was expected to fail.
+#[derive(Debug, Clone)]
+pub struct UnexpectedSuccess {
+    pub path: PathBuf,
+    pub position: Position,
+}
+
+impl std::fmt::Display for UnexpectedSuccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: assertion passed, but was expected to fail",
+            self.path.display(),
+            self.position.line + 1,
+            self.position.column.grapheme_offset + 1
         )
     }
 }
@@ -478,12 +726,39 @@ pub enum TestFailure {
         missing_lines: Vec<usize>,
         unexpected_lines: HashMap<String, Vec<Option<usize>>>,
     },
+    NoDefinitions {
+        path: PathBuf,
+        position: Position,
+    },
+    IncorrectResolutionCount {
+        path: PathBuf,
+        position: Position,
+        references: Vec<String>,
+        expected_count: usize,
+        actual_count: usize,
+    },
+    IncorrectReferrers {
+        path: PathBuf,
+        position: Position,
+        definitions: Vec<String>,
+        missing_lines: Vec<usize>,
+        unexpected_lines: HashMap<String, Vec<Option<usize>>>,
+    },
     IncorrectDefinitions {
         path: PathBuf,
         position: Position,
         missing_symbols: Vec<String>,
         unexpected_symbols: Vec<String>,
     },
+    MissingSnapshot {
+        path: PathBuf,
+        snapshot_path: PathBuf,
+    },
+    SnapshotMismatch {
+        path: PathBuf,
+        snapshot_path: PathBuf,
+        diff: String,
+    },
     IncorrectReferences {
         path: PathBuf,
         position: Position,
@@ -530,27 +805,67 @@ impl std::fmt::Display for TestFailure {
                         missing_lines.iter().map(|l| l + 1).format(", ")
                     )?;
                 }
-                if !unexpected_lines.is_empty() {
-                    write!(f, " found unexpected",)?;
-                    let mut first = true;
-                    for (definition, lines) in unexpected_lines.into_iter() {
-                        if first {
-                            first = false;
-                        } else {
-                            write!(f, ",")?;
-                        }
-                        write!(f, " ‘{}’ on lines(s) ", definition)?;
-                        write!(
-                            f,
-                            "{}",
-                            lines
-                                .into_iter()
-                                .map(|l| l.map(|l| format!("{}", l + 1)).unwrap_or("?".into()))
-                                .format(", ")
-                        )?;
-                    }
+                write_unexpected_lines(f, unexpected_lines)
+            }
+            Self::NoDefinitions { path, position } => {
+                write!(
+                    f,
+                    "{}:{}:{}: no definitions found",
+                    path.display(),
+                    position.line + 1,
+                    position.column.grapheme_offset + 1
+                )
+            }
+            Self::IncorrectResolutionCount {
+                path,
+                position,
+                references,
+                expected_count,
+                actual_count,
+            } => {
+                write!(
+                    f,
+                    "{}:{}:{}: ",
+                    path.display(),
+                    position.line + 1,
+                    position.column.grapheme_offset + 1
+                )?;
+                write!(f, "definition(s) for reference(s)")?;
+                for reference in references {
+                    write!(f, " ‘{}’", reference)?;
                 }
-                Ok(())
+                write!(
+                    f,
+                    " expected {} definition(s), found {}",
+                    expected_count, actual_count
+                )
+            }
+            Self::IncorrectReferrers {
+                path,
+                position,
+                definitions,
+                missing_lines,
+                unexpected_lines,
+            } => {
+                write!(
+                    f,
+                    "{}:{}:{}: ",
+                    path.display(),
+                    position.line + 1,
+                    position.column.grapheme_offset + 1
+                )?;
+                write!(f, "reference(s) to definition(s)")?;
+                for definition in definitions {
+                    write!(f, " ‘{}’", definition)?;
+                }
+                if !missing_lines.is_empty() {
+                    write!(
+                        f,
+                        " missing expected on line(s) {}",
+                        missing_lines.iter().map(|l| l + 1).format(", ")
+                    )?;
+                }
+                write_unexpected_lines(f, unexpected_lines)
             }
             Self::IncorrectDefinitions {
                 path,
@@ -610,11 +925,63 @@ impl std::fmt::Display for TestFailure {
                 }
                 Ok(())
             }
+            Self::MissingSnapshot {
+                path,
+                snapshot_path,
+            } => {
+                write!(
+                    f,
+                    "{}: snapshot {} does not exist",
+                    path.display(),
+                    snapshot_path.display()
+                )
+            }
+            Self::SnapshotMismatch {
+                path,
+                snapshot_path,
+                diff,
+            } => {
+                write!(
+                    f,
+                    "{}: graph or paths differ from snapshot {}\n{}",
+                    path.display(),
+                    snapshot_path.display(),
+                    diff
+                )
+            }
             Self::Cancelled(err) => write!(f, "{}", err),
         }
     }
 }
 
+/// Write symbols found on unexpected lines, if there are any.
+fn write_unexpected_lines(
+    f: &mut std::fmt::Formatter<'_>,
+    unexpected_lines: &HashMap<String, Vec<Option<usize>>>,
+) -> std::fmt::Result {
+    if !unexpected_lines.is_empty() {
+        write!(f, " found unexpected",)?;
+        let mut first = true;
+        for (symbol, lines) in unexpected_lines.iter().sorted() {
+            if first {
+                first = false;
+            } else {
+                write!(f, ",")?;
+            }
+            write!(f, " ‘{}’ on lines(s) ", symbol)?;
+            write!(
+                f,
+                "{}",
+                lines
+                    .iter()
+                    .map(|l| l.map(|l| format!("{}", l + 1)).unwrap_or("?".into()))
+                    .format(", ")
+            )?;
+        }
+    }
+    Ok(())
+}
+
 impl Test {
     /// Run the test. It is the responsibility of the caller to ensure that
     /// the stack graph for the test fragments has been constructed, and the
@@ -626,20 +993,70 @@ impl Test {
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<TestResult, stack_graphs::CancellationError> {
         let mut result = TestResult::new();
+        let mut ctx = AssertionContext::for_files(self.fragments.iter().map(|f| f.file));
         for fragment in &self.fragments {
-            for assertion in &fragment.assertions {
-                match assertion
-                    .run(&self.graph, partials, db, &cancellation_flag)
+            for (index, assertion) in fragment.assertions.iter().enumerate() {
+                let xfail = self.xfail || fragment.expected_failures.contains(&index);
+                let outcome = match assertion
+                    .run_in_context(&mut ctx, &self.graph, partials, db, &cancellation_flag)
                     .map_or_else(|e| self.from_error(e), |v| Ok(v))
                 {
-                    Ok(_) => result.add_success(),
-                    Err(f) => result.add_failure(f),
-                }
+                    Ok(_) if xfail => TestCaseOutcome::UnexpectedSuccess(UnexpectedSuccess {
+                        path: self.path.clone(),
+                        position: assertion.source().position.clone(),
+                    }),
+                    Ok(_) => TestCaseOutcome::Success,
+                    Err(f @ TestFailure::Cancelled(_)) => TestCaseOutcome::Failure(f),
+                    Err(f) if xfail => TestCaseOutcome::ExpectedFailure(f),
+                    Err(f) => TestCaseOutcome::Failure(f),
+                };
+                let position = &assertion.source().position;
+                result.add_case(TestCase {
+                    name: format!(
+                        "{}:{}: {}",
+                        position.line + 1,
+                        position.column.grapheme_offset + 1,
+                        self.describe_assertion(assertion)
+                    ),
+                    fragment_path: Some(fragment.path.clone()),
+                    position: Some(position.clone()),
+                    source_line: fragment
+                        .source
+                        .get(position.containing_line.clone())
+                        .map(|line| line.trim().to_string()),
+                    outcome,
+                });
             }
         }
         Ok(result)
     }
 
+    /// Describe an assertion in the syntax of the test file.
+    fn describe_assertion(&self, assertion: &Assertion) -> String {
+        let targets = |targets: &Vec<AssertionTarget>| {
+            targets.iter().map(|t| t.line + 1).format(", ").to_string()
+        };
+        let symbols = |symbols: &Vec<Handle<Symbol>>| {
+            symbols
+                .iter()
+                .map(|s| &self.graph[*s])
+                .format(", ")
+                .to_string()
+        };
+        match assertion {
+            Assertion::Defined { targets: t, .. } => format!("{}: {}", DEFINED, targets(t)),
+            Assertion::DefinedCount { count, .. } => format!("{}: {}", DEFINED_COUNT, count),
+            Assertion::NotDefined { targets: t, .. } => {
+                format!("{}: {}", NOT_DEFINED, targets(t))
+            }
+            Assertion::Referenced { targets: t, .. } => format!("{}: {}", REFERENCES, targets(t)),
+            Assertion::Defines { symbols: s, .. } => format!("{}: {}", DEFINES, symbols(s)),
+            Assertion::Refers { symbols: s, .. } => format!("{}: {}", REFERS, symbols(s)),
+        }
+        .trim_end()
+        .to_string()
+    }
+
     /// Construct a TestFailure from an AssertionError.
     fn from_error(&self, err: AssertionError) -> Result<(), TestFailure> {
         match err {
@@ -653,40 +1070,10 @@ impl Test {
                 missing_targets,
                 unexpected_paths,
             } => {
-                let references = references
-                    .into_iter()
-                    .map(|r| self.graph[self.graph[r].symbol().unwrap()].to_string())
-                    .unique()
-                    .sorted()
-                    .collect();
-                let missing_lines = missing_targets
-                    .into_iter()
-                    .map(|t| t.line)
-                    .unique()
-                    .sorted()
-                    .collect::<Vec<_>>();
-                let unexpected_lines = unexpected_paths
-                    .into_iter()
-                    .filter(|p| {
-                        // ignore results outside of this test, which may be include files or builtins
-                        self.fragments
-                            .iter()
-                            .any(|f| f.file == self.graph[p.end_node].id().file().unwrap())
-                    })
-                    .map(|p| {
-                        let symbol =
-                            self.graph[self.graph[p.end_node].symbol().unwrap()].to_string();
-                        let line = self
-                            .get_source_info(p.end_node)
-                            .map(|si| si.span.start.line);
-                        (symbol, line)
-                    })
-                    .unique()
-                    .sorted()
-                    .into_group_map();
-                if missing_lines.is_empty() && unexpected_lines.is_empty() {
-                    return Ok(());
-                }
+                let references = self.symbols(references);
+                let missing_lines = Self::lines(missing_targets);
+                let unexpected_lines =
+                    self.lines_by_symbol(unexpected_paths.into_iter().map(|p| p.end_node));
                 Err(TestFailure::IncorrectResolutions {
                     path: self.path.clone(),
                     position: source.position,
@@ -695,6 +1082,52 @@ impl Test {
                     unexpected_lines,
                 })
             }
+            AssertionError::UnexpectedlyDefined {
+                source,
+                references,
+                unexpected_paths,
+            } => Err(TestFailure::IncorrectResolutions {
+                path: self.path.clone(),
+                position: source.position,
+                references: self.symbols(references),
+                missing_lines: Vec::new(),
+                unexpected_lines: self
+                    .lines_by_symbol(unexpected_paths.into_iter().map(|p| p.end_node)),
+            }),
+            AssertionError::NoDefinitions { source } => Err(TestFailure::NoDefinitions {
+                path: self.path.clone(),
+                position: source.position,
+            }),
+            AssertionError::IncorrectDefinitionCount {
+                source,
+                references,
+                expected_count,
+                actual_paths,
+            } => Err(TestFailure::IncorrectResolutionCount {
+                path: self.path.clone(),
+                position: source.position,
+                references: self.symbols(references),
+                expected_count,
+                actual_count: actual_paths.len(),
+            }),
+            AssertionError::IncorrectlyReferenced {
+                source,
+                definitions,
+                missing_targets,
+                unexpected_paths,
+            } => {
+                let definitions = self.symbols(definitions);
+                let missing_lines = Self::lines(missing_targets);
+                let unexpected_lines =
+                    self.lines_by_symbol(unexpected_paths.into_iter().map(|p| p.start_node));
+                Err(TestFailure::IncorrectReferrers {
+                    path: self.path.clone(),
+                    position: source.position,
+                    definitions,
+                    missing_lines,
+                    unexpected_lines,
+                })
+            }
             AssertionError::IncorrectDefinitions {
                 source,
                 missing_symbols,
@@ -739,6 +1172,114 @@ impl Test {
         }
     }
 
+    /// Render a stable, textual snapshot of the graphs and minimal partial paths of the test
+    /// fragments, which can be compared between runs. Builtins are excluded. Nodes are identified
+    /// by their file-local IDs, so the snapshot changes whenever the TSG rules create nodes in a
+    /// different order. It is the responsibility of the caller to ensure that the database
+    /// contains the minimal partial paths of the fragments.
+    pub fn snapshot(&self, partials: &mut PartialPaths, db: &Database) -> String {
+        let files = self.fragments.iter().map(|f| f.file).collect::<Vec<_>>();
+        let in_fragments =
+            |node: Handle<Node>| files.iter().any(|file| self.graph[node].is_in_file(*file));
+        let mut snapshot = String::new();
+        for fragment in &self.fragments {
+            snapshot.push_str(&format!("--- path: {} ---\n", fragment.path.display()));
+            for node in self.graph.nodes_for_file(fragment.file) {
+                snapshot.push_str(&format!("node {}", node.display(&self.graph)));
+                if let Some(si) = self.get_source_info(node) {
+                    snapshot.push_str(&format!(
+                        " at {}:{}",
+                        si.span.start.line + 1,
+                        si.span.start.column.grapheme_offset + 1
+                    ));
+                }
+                snapshot.push('\n');
+            }
+            let edges = self
+                .graph
+                .nodes_for_file(fragment.file)
+                .flat_map(|source| self.graph.outgoing_edges(source))
+                .chain(
+                    [StackGraph::root_node(), StackGraph::jump_to_node()]
+                        .iter()
+                        .flat_map(|source| self.graph.outgoing_edges(*source))
+                        .filter(|edge| self.graph[edge.sink].file() == Some(fragment.file)),
+                )
+                .map(|edge| {
+                    let mut line = format!(
+                        "edge {} -> {}",
+                        edge.source.display(&self.graph),
+                        edge.sink.display(&self.graph)
+                    );
+                    if edge.precedence != 0 {
+                        line.push_str(&format!(" (precedence {})", edge.precedence));
+                    }
+                    line
+                })
+                .sorted()
+                .collect::<Vec<_>>();
+            for edge in edges {
+                snapshot.push_str(&edge);
+                snapshot.push('\n');
+            }
+        }
+        snapshot.push_str("--- paths ---\n");
+        let paths = db
+            .iter_partial_paths()
+            .filter(|p| in_fragments(db[*p].start_node) || in_fragments(db[*p].end_node))
+            .map(|p| db[p].display(&self.graph, partials).to_string())
+            .sorted()
+            .collect::<Vec<_>>();
+        for path in paths {
+            snapshot.push_str(&path);
+            snapshot.push('\n');
+        }
+        snapshot
+    }
+
+    /// Get the sorted, unique symbols of the given nodes.
+    fn symbols(&self, nodes: Vec<Handle<Node>>) -> Vec<String> {
+        nodes
+            .into_iter()
+            .map(|n| self.graph[self.graph[n].symbol().unwrap()].to_string())
+            .unique()
+            .sorted()
+            .collect()
+    }
+
+    /// Get the sorted, unique lines of the given targets.
+    fn lines(targets: Vec<AssertionTarget>) -> Vec<usize> {
+        targets
+            .into_iter()
+            .map(|t| t.line)
+            .unique()
+            .sorted()
+            .collect()
+    }
+
+    /// Get the lines of the given nodes, grouped by symbol. Nodes outside of this test, which may
+    /// be in include files or builtins, have no line.
+    fn lines_by_symbol(
+        &self,
+        nodes: impl Iterator<Item = Handle<Node>>,
+    ) -> HashMap<String, Vec<Option<usize>>> {
+        nodes
+            .map(|n| {
+                let symbol = self.graph[self.graph[n].symbol().unwrap()].to_string();
+                let in_fragments = self
+                    .fragments
+                    .iter()
+                    .any(|f| self.graph[n].is_in_file(f.file));
+                let line = in_fragments
+                    .then(|| self.get_source_info(n).map(|si| si.span.start.line))
+                    .flatten();
+                (symbol, line)
+            })
+            .unique()
+            .sorted()
+            .into_group_map()
+    }
+
     /// Get source info for a node, using a heuristic to rule default null source info results.
     fn get_source_info(&self, node: Handle<Node>) -> Option<&SourceInfo> {
         self.graph.source_info(node).filter(|si| {
@@ -5,8 +5,14 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::path::PathBuf;
+
 use stack_graphs::graph::StackGraph;
+use tree_sitter::InputEdit;
+use tree_sitter::Point;
 use tree_sitter_graph::Variables;
+use tree_sitter_stack_graphs::profile::BuildProfile;
+use tree_sitter_stack_graphs::BuildError;
 use tree_sitter_stack_graphs::NoCancellation;
 use tree_sitter_stack_graphs::StackGraphLanguage;
 
@@ -72,3 +78,361 @@ fn can_support_injected_nodes() {
         &["[test.py(1) scope] -0-> [test.py(0) exported scope]"],
     );
 }
+
+#[test]
+fn can_profile_stanzas() {
+    let tsg = r#"
+    global ROOT_NODE
+    (module)@mod {
+      node @mod.lexical_scope
+    }
+    (identifier)@id {
+      node @id.def
+      edge @id.def -> ROOT_NODE
+    }
+    (class_definition)@class {
+      node @class.def
+    }
+    "#;
+    let python = "x = y";
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let language = StackGraphLanguage::from_str(tree_sitter_python::language(), tsg).unwrap();
+    let mut profile = BuildProfile::new();
+    let globals = Variables::new();
+    let mut builder = language.builder_into_stack_graph(&mut graph, file, python);
+    builder.set_profile(&mut profile);
+    builder
+        .build(&globals, &NoCancellation)
+        .expect("Failed to build graph");
+
+    let (_, language_profile) = profile.languages().next().expect("Expected profile");
+    assert_eq!(1, language_profile.file_count);
+    let stanzas = language_profile
+        .stanzas
+        .iter()
+        .map(|s| (s.line, s.match_count, s.node_count, s.edge_count))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![(2, 1, 1, 0), (5, 2, 2, 2), (9, 0, 0, 0)], stanzas);
+    assert_eq!(2, language_profile.sorted_stanzas().len());
+}
+
+#[test]
+fn can_profile_stanzas_per_tsg_file() {
+    let common = r#"
+    (module)@mod {
+      node @mod.lexical_scope
+    }
+    "#;
+    let python = r#"
+    (identifier)@id {
+      node @id.def
+    }
+    (class_definition)@class {
+      node @class.def
+    }
+    "#;
+    let source = "x = y";
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let language = StackGraphLanguage::from_sources(
+        tree_sitter_python::language(),
+        &[
+            (PathBuf::from("common.tsg"), common),
+            (PathBuf::from("python.tsg"), python),
+        ],
+    )
+    .unwrap();
+    let mut profile = BuildProfile::new();
+    let globals = Variables::new();
+    let mut builder = language.builder_into_stack_graph(&mut graph, file, source);
+    builder.set_profile(&mut profile);
+    builder
+        .build(&globals, &NoCancellation)
+        .expect("Failed to build graph");
+
+    let (_, language_profile) = profile.languages().next().expect("Expected profile");
+    let files = language_profile
+        .files()
+        .iter()
+        .map(|f| {
+            (
+                f.tsg_path.to_string_lossy().into_owned(),
+                f.stanzas.iter().map(|s| s.line).collect::<Vec<_>>(),
+                f.matched_stanza_count(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            ("common.tsg".to_string(), vec![1], 1),
+            ("python.tsg".to_string(), vec![1, 4], 1),
+        ],
+        files
+    );
+}
+
+#[test]
+fn can_profile_executed_statements() {
+    let tsg = r#"
+    (module)@mod {
+      node @mod.lexical_scope
+      let x = 1
+      attr (@mod.lexical_scope) value = x
+    }
+    (identifier)@id {
+      scan (source-text @id) {
+        "^x$" {
+          node n
+          attr (n) value = "x"
+        }
+        "^zzz$" {
+          let y = 2
+          node m
+        }
+      }
+    }
+    (class_definition)@class {
+      let z = (source-text @class)
+    }
+    "#;
+    let python = "x = y";
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let language = StackGraphLanguage::from_str(tree_sitter_python::language(), tsg).unwrap();
+    let mut profile = BuildProfile::new();
+    let globals = Variables::new();
+    let mut builder = language.builder_into_stack_graph(&mut graph, file, python);
+    builder.set_profile(&mut profile);
+    builder
+        .build(&globals, &NoCancellation)
+        .expect("Failed to build graph");
+
+    let (_, language_profile) = profile.languages().next().expect("Expected profile");
+    let statements = language_profile
+        .stanzas
+        .iter()
+        .flat_map(|s| s.statements.iter())
+        .map(|s| (s.line, s.executed))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            (2, true),
+            (3, true),
+            (4, true),
+            (7, true),
+            (9, true),
+            (10, true),
+            (13, false),
+            (14, false),
+            (19, false),
+        ],
+        statements
+    );
+    assert_eq!(2, language_profile.matched_stanza_count());
+    assert_eq!(9, language_profile.statement_count());
+    assert_eq!(6, language_profile.executed_statement_count());
+}
+
+#[test]
+fn can_build_incrementally() {
+    let tsg = r#"
+    (module)@mod {
+      node @mod.lexical_scope
+    }
+    "#;
+    let old_python = "x = 1\ny = 2\n";
+    let new_python = "x = 1\nzz = 2\n";
+    let edit = InputEdit {
+        start_byte: 6,
+        old_end_byte: 7,
+        new_end_byte: 8,
+        start_position: Point::new(1, 0),
+        old_end_position: Point::new(1, 1),
+        new_end_position: Point::new(1, 2),
+    };
+
+    let language = StackGraphLanguage::from_str(tree_sitter_python::language(), tsg).unwrap();
+    let globals = Variables::new();
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let mut builder = language.builder_into_stack_graph(&mut graph, file, old_python);
+    builder.parse(&NoCancellation).expect("Failed to parse");
+    let old_tree = builder.tree().expect("Expected tree").clone();
+    builder
+        .build(&globals, &NoCancellation)
+        .expect("Failed to build graph");
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let mut builder = language.builder_into_stack_graph(&mut graph, file, new_python);
+    let changed_ranges = builder
+        .parse_incremental(&old_tree, &[edit], &NoCancellation)
+        .expect("Failed to parse incrementally");
+    let changed_ranges = changed_ranges
+        .iter()
+        .map(|r| (r.start_byte, r.end_byte))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![(6, 8)], changed_ranges);
+    assert_eq!(
+        "zz",
+        builder
+            .tree()
+            .expect("Expected tree")
+            .root_node()
+            .named_descendant_for_byte_range(6, 8)
+            .unwrap()
+            .utf8_text(new_python.as_bytes())
+            .unwrap()
+    );
+    builder
+        .build(&globals, &NoCancellation)
+        .expect("Failed to build graph");
+    check_stack_graph_nodes(&graph, file, &["[test.py(0) scope]"]);
+}
+
+#[test]
+fn can_parse_incrementally_with_multiple_edits() {
+    let old_python = "x = 1\ny = 2\n";
+    let new_python = "z = 0\nx = 1\nyyyy = 2\n";
+    let edits = [
+        // replace `y` by `yyyy`
+        InputEdit {
+            start_byte: 6,
+            old_end_byte: 7,
+            new_end_byte: 10,
+            start_position: Point::new(1, 0),
+            old_end_position: Point::new(1, 1),
+            new_end_position: Point::new(1, 4),
+        },
+        // insert `z = 0` before the first line, which moves the first edit
+        InputEdit {
+            start_byte: 0,
+            old_end_byte: 0,
+            new_end_byte: 6,
+            start_position: Point::new(0, 0),
+            old_end_position: Point::new(0, 0),
+            new_end_position: Point::new(1, 0),
+        },
+    ];
+
+    let language = StackGraphLanguage::from_str(tree_sitter_python::language(), "").unwrap();
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let mut builder = language.builder_into_stack_graph(&mut graph, file, old_python);
+    builder.parse(&NoCancellation).expect("Failed to parse");
+    let old_tree = builder.tree().expect("Expected tree").clone();
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let mut builder = language.builder_into_stack_graph(&mut graph, file, new_python);
+    let changed_ranges = builder
+        .parse_incremental(&old_tree, &edits, &NoCancellation)
+        .expect("Failed to parse incrementally");
+    let changed_ranges = changed_ranges
+        .iter()
+        .map(|r| {
+            (
+                r.start_byte,
+                r.end_byte,
+                (r.start_point.row, r.start_point.column),
+                (r.end_point.row, r.end_point.column),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![(0, 6, (0, 0), (1, 0)), (12, 16, (2, 0), (2, 4))],
+        changed_ranges
+    );
+    assert_eq!(
+        "yyyy",
+        &new_python[changed_ranges[1].0..changed_ranges[1].1]
+    );
+}
+
+#[test]
+fn can_build_tolerating_parse_errors() {
+    let tsg = r#"
+    (module)@mod {
+      node @mod.lexical_scope
+    }
+    (expression_statement (assignment left:(identifier)@name))@stmt {
+      node @stmt.def
+      attr (@stmt.def) type = "pop_symbol", symbol = (source-text @name), source_node = @name, is_definition
+    }
+    "#;
+    let python = "x = 1\ny = (\nz = 3\n";
+
+    let language = StackGraphLanguage::from_str(tree_sitter_python::language(), tsg).unwrap();
+    let globals = Variables::new();
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let result = language
+        .builder_into_stack_graph(&mut graph, file, python)
+        .build(&globals, &NoCancellation);
+    assert!(matches!(result, Err(BuildError::ParseErrors(_))));
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let parse_errors = language
+        .builder_into_stack_graph(&mut graph, file, python)
+        .build_tolerant(&globals, &NoCancellation)
+        .expect("Failed to build graph")
+        .expect("Expected parse errors");
+    assert!(!parse_errors.errors().is_empty());
+    check_stack_graph_nodes(
+        &graph,
+        file,
+        &[
+            "[test.py(0) scope]",
+            "[test.py(1) definition x]",
+            "[test.py(2) definition y]",
+        ],
+    );
+}
+
+#[test]
+fn can_build_definitions_enclosing_parse_errors() {
+    let tsg = r#"
+    (function_definition name:(identifier)@name)@fun {
+      node @fun.def
+      attr (@fun.def) type = "pop_symbol", symbol = (source-text @name), source_node = @name, is_definition
+    }
+    (assignment left:(identifier)@name)@assign {
+      node @assign.def
+      attr (@assign.def) type = "pop_symbol", symbol = (source-text @name), source_node = @name, is_definition
+    }
+    (ERROR (identifier)@name) {
+      node @name.ref
+      attr (@name.ref) type = "push_symbol", symbol = (source-text @name), source_node = @name, is_reference
+    }
+    "#;
+    let python = "def f():\n    x = 1\n    y = a )\n    z = 3\n";
+
+    let language = StackGraphLanguage::from_str(tree_sitter_python::language(), tsg).unwrap();
+    let globals = Variables::new();
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    language
+        .builder_into_stack_graph(&mut graph, file, python)
+        .build_tolerant(&globals, &NoCancellation)
+        .expect("Failed to build graph")
+        .expect("Expected parse errors");
+    check_stack_graph_nodes(
+        &graph,
+        file,
+        &[
+            "[test.py(0) definition f]",
+            "[test.py(1) definition x]",
+            "[test.py(2) definition y]",
+            "[test.py(4) definition z]",
+        ],
+    );
+}
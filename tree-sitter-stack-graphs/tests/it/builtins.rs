@@ -0,0 +1,147 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use stack_graphs::graph::StackGraph;
+use tree_sitter_stack_graphs::builtins::PrecompiledBuiltins;
+use tree_sitter_stack_graphs::builtins::PrecompiledBuiltinsError;
+use tree_sitter_stack_graphs::builtins::VERSION;
+use tree_sitter_stack_graphs::loader::FileAnalyzers;
+use tree_sitter_stack_graphs::loader::LanguageConfiguration;
+use tree_sitter_stack_graphs::NoCancellation;
+
+use crate::edges::check_stack_graph_edges;
+
+static TSG: &str = r#"
+  global ROOT_NODE
+
+  (module) @mod {
+    node @mod.def
+    attr (@mod.def) type = "pop_symbol", symbol = "builtins", is_exported
+    edge ROOT_NODE -> @mod.def
+  }
+  (identifier) @id {
+    node @id.def
+    attr (@id.def) type = "pop_symbol", symbol = (source-text @id), source_node = @id, is_definition
+  }
+  (module (expression_statement (identifier) @id)) @mod {
+    edge @mod.def -> @id.def
+  }
+"#;
+
+fn language_configuration(builtins_source: &str) -> LanguageConfiguration {
+    LanguageConfiguration::from_sources(
+        tree_sitter_python::language(),
+        Some("source.py".into()),
+        None,
+        vec!["py".into()],
+        &[("test.tsg".into(), TSG)],
+        Some(("builtins.py".into(), builtins_source)),
+        None,
+        vec![],
+        FileAnalyzers::new(),
+        &NoCancellation,
+    )
+    .expect("Expected loading language configuration to succeed")
+}
+
+fn file_names(graph: &StackGraph) -> Vec<&str> {
+    graph.iter_files().map(|f| graph[f].name()).collect()
+}
+
+#[test]
+fn can_load_precompiled_builtins() {
+    let lc = language_configuration("print");
+    let precompiled = PrecompiledBuiltins::from_graph(&lc.builtins, &NoCancellation)
+        .expect("Expected precompiling builtins to succeed");
+    let path_count = precompiled.paths.len();
+    assert!(path_count > 0);
+    let bytes = precompiled
+        .encode()
+        .expect("Expected encoding builtins to succeed");
+
+    let mut lc = language_configuration("");
+    lc.load_precompiled_builtins(&bytes)
+        .expect("Expected loading precompiled builtins to succeed");
+    assert_eq!(vec!["builtins.py"], file_names(&lc.builtins));
+    check_stack_graph_edges(
+        &lc.builtins,
+        &[
+            "[root] -0-> [builtins.py(0) pop builtins]",
+            "[builtins.py(0) pop builtins] -0-> [builtins.py(1) definition print]",
+        ],
+    );
+    assert_eq!(
+        Some(path_count),
+        lc.builtins_paths.as_ref().map(|paths| paths.len())
+    );
+}
+
+#[test]
+fn can_precompile_builtins_from_sources() {
+    let precompiled = PrecompiledBuiltins::from_sources(
+        tree_sitter_python::language(),
+        &[("test.tsg".into(), TSG)],
+        ("builtins.py".into(), "print"),
+        None,
+        &NoCancellation,
+    )
+    .expect("Expected precompiling builtins to succeed")
+    .encode()
+    .expect("Expected encoding builtins to succeed");
+
+    let mut lc = language_configuration("");
+    lc.load_precompiled_builtins(&precompiled)
+        .expect("Expected loading precompiled builtins to succeed");
+    check_stack_graph_edges(
+        &lc.builtins,
+        &[
+            "[root] -0-> [builtins.py(0) pop builtins]",
+            "[builtins.py(0) pop builtins] -0-> [builtins.py(1) definition print]",
+        ],
+    );
+}
+
+#[test]
+fn can_fall_back_to_builtins_source() {
+    let lc = language_configuration("print");
+    let valid_bytes = PrecompiledBuiltins::from_graph(&lc.builtins, &NoCancellation)
+        .expect("Expected precompiling builtins to succeed")
+        .encode()
+        .expect("Expected encoding builtins to succeed");
+    let header = |version: u32| {
+        bincode::encode_to_vec(
+            ("tree-sitter-stack-graphs builtins", version),
+            bincode::config::standard(),
+        )
+        .expect("Expected encoding header to succeed")
+    };
+    assert!(valid_bytes.starts_with(&header(VERSION)));
+    let mut bytes = header(VERSION + 1);
+    bytes.extend(&valid_bytes[header(VERSION).len()..]);
+
+    let mut lc = language_configuration("");
+    assert!(matches!(
+        lc.load_precompiled_builtins(&bytes),
+        Err(PrecompiledBuiltinsError::VersionMismatch(v)) if v == VERSION + 1
+    ));
+    lc.load_builtins_with_precompiled(
+        &bytes,
+        ("builtins.py".into(), "input"),
+        None,
+        &NoCancellation,
+    )
+    .expect("Expected loading builtins to succeed");
+    assert!(lc.builtins_paths.is_none());
+    check_stack_graph_edges(
+        &lc.builtins,
+        &[
+            "[root] -0-> [builtins.py(0) pop builtins]",
+            "[builtins.py(0) pop builtins] -0-> [builtins.py(1) definition input]",
+        ],
+    );
+}
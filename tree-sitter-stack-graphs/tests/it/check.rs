@@ -0,0 +1,67 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use tree_sitter_stack_graphs::check::check_tsg;
+use tree_sitter_stack_graphs::StackGraphLanguage;
+
+fn check(tsg_source: &str) -> Vec<String> {
+    let language =
+        StackGraphLanguage::from_str(tree_sitter_python::language(), tsg_source).unwrap();
+    check_tsg(language.tsg(), tsg_source)
+        .into_iter()
+        .map(|d| format!("{}:{}: {}", d.line + 1, d.column + 1, d))
+        .collect()
+}
+
+#[test]
+fn can_check_valid_rules() {
+    let tsg = r#"
+      (identifier) @id {
+         node @id.scope
+         attr (@id.scope) is_exported
+         node @id.ref
+         attr (@id.ref) type = "push_scoped_symbol", symbol = (source-text @id), scope = @id.scope
+         attr (@id.ref) source_node = @id, is_reference
+      }
+      (function_definition name: (identifier) @name) {
+         attr (@name.ref) source_node = @name
+      }
+    "#;
+    assert_eq!(Vec::<String>::new(), check(tsg));
+}
+
+#[test]
+fn can_report_problems() {
+    let tsg = r#"
+      (identifier) @id {
+         node @id.def
+         attr (@id.def) type = "pop_symbol", symbol = (source-text @id), is_definition
+         node scope
+         node ref
+         attr (ref) type = "push_scoped_symbol", symbol = "x", scope = scope, is_exported
+      }
+      [(identifier) (string)] @node {
+         attr (@node.def) type = "push_symbol"
+      }
+      (function_definition) @func {
+         node @func.def
+         attr (@func.def) type = "pop_symbol", symbol = "f"
+         attr (@func.def) type = "definition"
+      }
+    "#;
+    assert_eq!(
+        vec![
+            "4:10: Node @id.def is marked ‘is_definition’ but has no ‘source_node’",
+            "7:10: Scope scope of push_scoped_symbol node is never marked ‘is_exported’",
+            "7:10: Unexpected attribute ‘is_exported’ on node ref of type push_scoped_symbol",
+            "10:10: Type ‘push_symbol’ of node @node.def conflicts with type ‘pop_symbol’ set on line 4",
+            "15:10: Unknown type ‘definition’ for node @func.def",
+        ],
+        check(tsg)
+    );
+}
@@ -0,0 +1,120 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use super::build_stack_graph;
+use super::edges::check_stack_graph_edges;
+use super::nodes::check_stack_graph_nodes;
+
+fn build_and_check_stack_graph_nodes(
+    python_source: &str,
+    tsg_source: &str,
+    expected_nodes: &[&str],
+) {
+    let (graph, file) =
+        build_stack_graph(python_source, tsg_source).expect("Could not load stack graph");
+    check_stack_graph_nodes(&graph, file, expected_nodes);
+}
+
+#[test]
+fn can_use_string_functions() {
+    let tsg = r#"
+      (dotted_name)@name {
+        node upper
+        attr (upper) type = "push_symbol", symbol = (join (string-split (string-uppercase (source-text @name)) ".") "/")
+        node strip
+        attr (strip) type = "push_symbol", symbol = (string-strip-suffix (string-strip-prefix (source-text @name) "a.") ".c")
+        if (is-null (string-strip-prefix (source-text @name) "b.")) {
+          node lower
+          attr (lower) type = "push_symbol", symbol = (string-lowercase "B")
+        }
+      }
+    "#;
+    let python = "import a.b.c";
+    build_and_check_stack_graph_nodes(
+        python,
+        tsg,
+        &[
+            "[test.py(0) push A/B/C]",
+            "[test.py(1) push b]",
+            "[test.py(2) push b]",
+        ],
+    );
+}
+
+#[test]
+fn can_use_regex_functions() {
+    let tsg = r#"
+      (identifier)@id {
+        if (regex-match (source-text @id) "^[a-z]+_") {
+          node push
+          attr (push) type = "push_symbol", symbol = (join (regex-captures (source-text @id) "^([a-z]+)_([a-z]+)$") ",")
+        }
+      }
+    "#;
+    let python = "foo_bar; baz";
+    build_and_check_stack_graph_nodes(python, tsg, &["[test.py(0) push foo,bar]"]);
+}
+
+#[test]
+fn can_compute_module_names() {
+    let tsg = r#"
+      (module) {
+        node full
+        attr (full) type = "push_symbol", symbol = (path-module-name "src/a/b.py" ".")
+        node rooted
+        attr (rooted) type = "push_symbol", symbol = (path-module-name "./src/a/b.py" "." "lib" "src")
+        if (is-null (path-module-name "test/a.py" "." "src")) {
+          node unrooted
+        }
+      }
+    "#;
+    let python = "pass";
+    build_and_check_stack_graph_nodes(
+        python,
+        tsg,
+        &[
+            "[test.py(0) push src.a.b]",
+            "[test.py(1) push a.b]",
+            "[test.py(2) scope]",
+        ],
+    );
+}
+
+#[test]
+fn can_create_symbol_chains() {
+    let tsg = r#"
+      (dotted_name)@name {
+        node scope
+        let def = (pop-symbols scope (string-split (source-text @name) "."))
+        attr (def) is_definition, source_node = @name
+        let ref = (push-symbols scope (string-split (source-text @name) "."))
+        attr (ref) is_reference, source_node = @name
+      }
+    "#;
+    let python = "import a.b";
+    let (graph, file) = build_stack_graph(python, tsg).expect("Could not load stack graph");
+    check_stack_graph_nodes(
+        &graph,
+        file,
+        &[
+            "[test.py(0) scope]",
+            "[test.py(1) pop a]",
+            "[test.py(2) definition b]",
+            "[test.py(3) push a]",
+            "[test.py(4) reference b]",
+        ],
+    );
+    check_stack_graph_edges(
+        &graph,
+        &[
+            "[test.py(0) scope] -0-> [test.py(1) pop a]",
+            "[test.py(1) pop a] -0-> [test.py(2) definition b]",
+            "[test.py(3) push a] -0-> [test.py(0) scope]",
+            "[test.py(4) reference b] -0-> [test.py(3) push a]",
+        ],
+    );
+}
@@ -0,0 +1,133 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::path::Path;
+use std::time::Duration;
+use tree_sitter_stack_graphs::cli::fuzz::minimize;
+use tree_sitter_stack_graphs::cli::fuzz::mutate;
+use tree_sitter_stack_graphs::cli::fuzz::strip_locations;
+use tree_sitter_stack_graphs::cli::fuzz::Checker;
+use tree_sitter_stack_graphs::cli::fuzz::Finding;
+use tree_sitter_stack_graphs::cli::fuzz::Outcome;
+use tree_sitter_stack_graphs::cli::fuzz::Rng;
+use tree_sitter_stack_graphs::StackGraphLanguage;
+
+/// Rules that fail to execute for class definitions.
+const TSG: &str = r#"
+    (module)@mod {
+      node @mod.scope
+    }
+    (class_definition)@class {
+      edge @class.missing -> @class.missing
+    }
+"#;
+
+fn language() -> StackGraphLanguage {
+    StackGraphLanguage::from_str(tree_sitter_python::language(), TSG).unwrap()
+}
+
+#[test]
+fn rng_is_deterministic() {
+    let mut a = Rng::new(42);
+    let mut b = Rng::new(42);
+    let a = (0..10).map(|_| a.next_u64()).collect::<Vec<_>>();
+    let b = (0..10).map(|_| b.next_u64()).collect::<Vec<_>>();
+    assert_eq!(a, b);
+    let mut c = Rng::new(43);
+    assert_ne!(a, (0..10).map(|_| c.next_u64()).collect::<Vec<_>>());
+}
+
+#[test]
+fn rng_with_zero_seed_produces_numbers_in_range() {
+    let mut rng = Rng::new(0);
+    let numbers = (0..100).map(|_| rng.below(7)).collect::<Vec<_>>();
+    assert!(numbers.iter().all(|n| *n < 7));
+    assert!(numbers.iter().any(|n| *n != numbers[0]));
+}
+
+#[test]
+fn can_strip_locations() {
+    assert_eq!(
+        "Undefined variable x at",
+        strip_locations("Undefined variable x at (12, 5)")
+    );
+    assert_eq!(
+        strip_locations("Undefined scoped variable [syntax node class_definition (2, 1)]"),
+        strip_locations("Undefined scoped variable [syntax node class_definition (7, 13)]")
+    );
+}
+
+#[test]
+fn mutate_deletes_or_duplicates_a_named_node() {
+    let sgl = language();
+    let source = "x = 1\ny = 2\n";
+    for seed in 0..20 {
+        let mutated = mutate(&sgl, source, &mut Rng::new(seed)).unwrap();
+        assert_eq!(mutated, mutate(&sgl, source, &mut Rng::new(seed)).unwrap());
+        assert_ne!(source, mutated);
+        if mutated.len() < source.len() {
+            // deleted text must be a contiguous range of the source
+            let prefix = source
+                .chars()
+                .zip(mutated.chars())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let deleted = source.len() - mutated.len();
+            assert_eq!(
+                mutated,
+                format!("{}{}", &source[..prefix], &source[prefix + deleted..])
+            );
+        } else {
+            assert!(mutated.contains(' ') && mutated.len() > source.len());
+        }
+    }
+}
+
+#[test]
+fn checker_tolerates_parse_errors() {
+    let sgl = language();
+    let checker = Checker::new(&sgl, Path::new("test.py"), Duration::from_secs(5), 1000);
+    assert_eq!(Outcome::Ok, checker.check("x = 1\n"));
+    assert_eq!(Outcome::OkWithParseErrors, checker.check("x = = 1\n"));
+}
+
+#[test]
+fn can_minimize_findings() {
+    let sgl = language();
+    let checker = Checker::new(&sgl, Path::new("test.py"), Duration::from_secs(5), 1000);
+    let input = "x = 1\nclass A:\n    pass\ny = 2\n".to_string();
+    let finding = match checker.check(&input) {
+        Outcome::Finding(finding @ Finding::BuildError { .. }) => finding,
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    };
+    let signature = finding.signature();
+    let (minimized, finding) =
+        minimize(&checker, &sgl, input, finding, Duration::from_secs(60)).unwrap();
+    assert_eq!(signature, finding.signature());
+    assert!(minimized.contains("class A"));
+    assert!(!minimized.contains("x = 1"));
+    assert!(!minimized.contains("y = 2"));
+}
+
+#[test]
+fn panic_signatures_ignore_messages_that_depend_on_the_input() {
+    let panic = |message: &str| Finding::Panic {
+        message: message.to_string(),
+        location: Some("src/graph.rs:10:5".to_string()),
+    };
+    let long = panic("index out of bounds: the len is 7 but the index is 9");
+    let short = panic("index out of bounds: the len is 2 but the index is 3");
+    assert_eq!(long.signature(), short.signature());
+    assert_ne!(
+        long.signature(),
+        Finding::Panic {
+            message: "index out of bounds: the len is 7 but the index is 9".to_string(),
+            location: Some("src/graph.rs:20:5".to_string()),
+        }
+        .signature()
+    );
+}
@@ -0,0 +1,9 @@
+// A grammar that parses any file as a single text node, for testing the path loader.
+module.exports = grammar({
+  name: 'stack_graphs_plain',
+  extras: $ => [],
+  rules: {
+    source_file: $ => optional($.text),
+    text: $ => /(.|\n)+/,
+  }
+});
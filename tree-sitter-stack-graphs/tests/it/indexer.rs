@@ -0,0 +1,104 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use stack_graphs::storage::SQLiteReader;
+use tree_sitter_stack_graphs::cli::index::IndexArgs;
+use tree_sitter_stack_graphs::cli::index::PROJECT_CONFIG_FILE;
+use tree_sitter_stack_graphs::loader::BuiltinsVariant;
+use tree_sitter_stack_graphs::loader::FileAnalyzers;
+use tree_sitter_stack_graphs::loader::LanguageConfiguration;
+use tree_sitter_stack_graphs::loader::Loader;
+use tree_sitter_stack_graphs::NoCancellation;
+
+/// Rules that create definitions for expression statements.
+static TSG: &str = r#"
+  global ROOT_NODE
+
+  (module (expression_statement (identifier) @id)) {
+    node @id.def
+    attr (@id.def) type = "pop_symbol", symbol = (source-text @id), source_node = @id, is_definition
+    edge ROOT_NODE -> @id.def
+  }
+"#;
+
+fn loader() -> Loader {
+    let variant = |name: &str, source: &str| BuiltinsVariant {
+        name: name.into(),
+        path: format!("builtins/{}.py", name).into(),
+        source: source.into(),
+        config: String::new(),
+    };
+    let lc = LanguageConfiguration::from_sources(
+        tree_sitter_python::language(),
+        Some("source.py".into()),
+        None,
+        vec!["py".into()],
+        &[("python.tsg".into(), TSG)],
+        Some(("builtins.py".into(), "print")),
+        None,
+        vec![variant("dom", "document"), variant("node", "process")],
+        FileAnalyzers::new(),
+        &NoCancellation,
+    )
+    .expect("Expected loading language configuration to succeed");
+    Loader::from_language_configurations(vec![lc], None).expect("Expected loader")
+}
+
+/// Indexes a project with the given project configuration, and returns the names of the files
+/// in the database that are not part of the project.
+fn index_project(config: &str, builtins_variants: Option<&str>) -> Vec<String> {
+    let project_dir = tempfile::tempdir().expect("Expected temporary directory");
+    std::fs::write(project_dir.path().join(PROJECT_CONFIG_FILE), config)
+        .expect("Expected writing configuration to succeed");
+    std::fs::write(project_dir.path().join("test.py"), "x")
+        .expect("Expected writing source to succeed");
+    let db_dir = tempfile::tempdir().expect("Expected temporary directory");
+    let db_path = db_dir.path().join("db.sqlite");
+
+    let mut args = IndexArgs::new(vec![project_dir.path().to_path_buf()]);
+    args.builtins_variants = builtins_variants.map(String::from);
+    args.run(&db_path, loader())
+        .expect("Expected indexing to succeed");
+
+    let project_dir = project_dir
+        .path()
+        .canonicalize()
+        .expect("Expected project directory");
+    let mut db = SQLiteReader::open(&db_path).expect("Expected database");
+    let mut files = db.list_all().expect("Expected file list");
+    let mut names = files
+        .try_iter()
+        .expect("Expected file list")
+        .map(|entry| entry.expect("Expected file entry").path)
+        .filter(|path| !path.starts_with(&project_dir))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+#[test]
+fn can_index_builtins_variants_selected_by_project_configuration() {
+    assert_eq!(
+        vec!["builtins.py", "builtins/dom.py"],
+        index_project("[globals]\nBUILTINS = dom\n", None)
+    );
+}
+
+#[test]
+fn can_override_builtins_variants_of_project_configuration() {
+    assert_eq!(
+        vec!["builtins.py", "builtins/node.py"],
+        index_project("[globals]\nBUILTINS = dom\n", Some("node, deno"))
+    );
+}
+
+#[test]
+fn can_index_default_builtins_without_project_configuration() {
+    assert_eq!(vec!["builtins.py"], index_project("", None));
+}
@@ -8,13 +8,29 @@
 use once_cell::sync::Lazy;
 use pretty_assertions::assert_eq;
 use stack_graphs::graph::StackGraph;
+use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use tree_sitter_graph::Variables;
+use tree_sitter_stack_graphs::loader::builtins_selection;
+use tree_sitter_stack_graphs::loader::BuiltinsVariant;
+use tree_sitter_stack_graphs::loader::BuiltinsVariants;
 use tree_sitter_stack_graphs::loader::FileAnalyzers;
+use tree_sitter_stack_graphs::loader::FileDetection;
+use tree_sitter_stack_graphs::loader::FileMatch;
+use tree_sitter_stack_graphs::loader::FileReader;
 use tree_sitter_stack_graphs::loader::LanguageConfiguration;
+use tree_sitter_stack_graphs::loader::LoadError;
 use tree_sitter_stack_graphs::loader::Loader;
+use tree_sitter_stack_graphs::loader::MatchEvidence;
+use tree_sitter_stack_graphs::loader::DEFAULT_BUILTINS_PATHS;
+use tree_sitter_stack_graphs::loader::DEFAULT_TSG_PATHS;
+use tree_sitter_stack_graphs::LanguageError;
 use tree_sitter_stack_graphs::NoCancellation;
 use tree_sitter_stack_graphs::StackGraphLanguage;
 
+use crate::edges::check_stack_graph_edges;
+
 static PATH: Lazy<PathBuf> = Lazy::new(|| PathBuf::from("test.py"));
 static TSG: Lazy<String> = Lazy::new(|| {
     r#"
@@ -32,8 +48,11 @@ fn can_load_from_provided_language_configuration() {
         scope: Some("source.py".into()),
         content_regex: None,
         file_types: vec!["py".into()],
+        detection: FileDetection::default(),
         sgl,
         builtins: StackGraph::new(),
+        builtins_paths: None,
+        builtins_variants: BuiltinsVariants::new(),
         special_files: FileAnalyzers::new(),
     };
     let mut loader =
@@ -49,3 +68,446 @@ fn can_load_from_provided_language_configuration() {
         .expect("Expected loading stack graph language to succeed");
     assert_eq!(lc.primary.map(|lc| lc.language), Some(language));
 }
+
+#[test]
+fn can_compose_language_configuration_from_tsg_sources() {
+    let language = tree_sitter_python::language();
+    let common_tsg = r#"
+      (module) @mod {
+        node @mod.scope
+      }
+    "#;
+    let tsg = r#"
+      (module) @mod {
+        node ref
+        attr (ref) type = "push_symbol", symbol = "x"
+        edge ref -> @mod.scope
+      }
+    "#;
+    let lc = LanguageConfiguration::from_sources(
+        language,
+        Some("source.py".into()),
+        None,
+        vec!["py".into()],
+        &[
+            ("common.tsg".into(), common_tsg),
+            ("python.tsg".into(), tsg),
+        ],
+        None,
+        None,
+        vec![],
+        FileAnalyzers::new(),
+        &NoCancellation,
+    )
+    .expect("Expected composed rules to load");
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    lc.sgl
+        .build_stack_graph_into(&mut graph, file, "pass", &Variables::new(), &NoCancellation)
+        .expect("Expected build to succeed");
+    check_stack_graph_edges(&graph, &["[test.py(1) push x] -0-> [test.py(0) scope]"]);
+
+    assert_eq!((Path::new("common.tsg"), 2), lc.sgl.tsg_location(2));
+    assert_eq!((Path::new("python.tsg"), 3), lc.sgl.tsg_location(8));
+}
+
+#[test]
+fn can_report_parse_errors_in_composed_tsg_sources() {
+    let language = tree_sitter_python::language();
+    let common_tsg = r#"
+      (module) @mod {
+        node @mod.scope
+      }
+    "#;
+    let tsg = r#"
+      (module) @mod {
+        edge @mod.scope -> undefined
+      }
+    "#;
+    let err = StackGraphLanguage::from_sources(
+        language,
+        &[
+            ("common.tsg".into(), common_tsg),
+            ("python.tsg".into(), tsg),
+        ],
+    )
+    .err()
+    .expect("Expected composed rules to fail");
+    match &err {
+        LanguageError::ParseErrorInFile {
+            tsg_path,
+            tsg: file_tsg,
+            ..
+        } => {
+            assert_eq!(Path::new("python.tsg"), tsg_path);
+            assert_eq!(format!("{}\n", tsg), *file_tsg);
+        }
+        err => panic!("Unexpected error {:?}", err),
+    }
+    assert_eq!(
+        "Undefined variable undefined at (3, 28) in python.tsg",
+        err.to_string()
+    );
+}
+
+#[test]
+fn can_report_syntax_errors_in_composed_tsg_sources() {
+    let language = tree_sitter_python::language();
+    let common_tsg = r#"
+      (module) @mod {
+        node @mod.scope
+      }
+    "#;
+    let tsg = r#"
+      (module) @mod {
+        edge @mod.scope ->
+      }
+    "#;
+    let err = StackGraphLanguage::from_sources(
+        language,
+        &[
+            ("common.tsg".into(), common_tsg),
+            ("python.tsg".into(), tsg),
+        ],
+    )
+    .err()
+    .expect("Expected composed rules to fail");
+    assert!(matches!(
+        &err,
+        LanguageError::ParseErrorInFile { tsg_path, .. } if tsg_path == Path::new("python.tsg")
+    ));
+    assert!(
+        err.to_string().ends_with(" at (4, 7) in python.tsg"),
+        "Unexpected error {}",
+        err
+    );
+}
+
+#[test]
+fn can_report_execution_errors_in_composed_tsg_sources() {
+    let language = tree_sitter_python::language();
+    let common_tsg = r#"
+      (module) @mod {
+        node @mod.scope
+      }
+    "#;
+    let tsg = r#"
+      (module) @mod {
+        edge @mod.scope -> @mod.undefined
+      }
+    "#;
+    let sgl = StackGraphLanguage::from_sources(
+        language,
+        &[
+            ("common.tsg".into(), common_tsg),
+            ("python.tsg".into(), tsg),
+        ],
+    )
+    .expect("Expected composed rules to load");
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let err = sgl
+        .build_stack_graph_into(&mut graph, file, "pass", &Variables::new(), &NoCancellation)
+        .err()
+        .expect("Expected build to fail");
+    assert!(
+        err.to_string().starts_with(
+            "Error in <common.tsg + python.tsg>, composed from common.tsg at lines 1-5, python.tsg at lines 6-10. Caused by: "
+        ),
+        "Unexpected error {}",
+        err
+    );
+}
+
+#[test]
+fn can_load_selected_builtins_variants() {
+    let language = tree_sitter_python::language();
+    let tsg = r#"
+      (identifier) @id {
+        node def
+        attr (def) type = "pop_symbol", symbol = (source-text @id), source_node = @id, is_definition
+      }
+    "#;
+    let variant = |name: &str, source: &str| BuiltinsVariant {
+        name: name.into(),
+        path: format!("{}.py", name).into(),
+        source: source.into(),
+        config: String::new(),
+    };
+    let mut builtins_variants = BuiltinsVariants::new();
+    builtins_variants.add(variant("dom", "document"));
+    builtins_variants.add(variant("node", "process"));
+    let sgl = StackGraphLanguage::from_str(language, tsg).unwrap();
+
+    let graph = builtins_variants
+        .load(&sgl, &builtins_selection("node, dom"), &NoCancellation)
+        .expect("Expected builtins to load");
+    assert_eq!(
+        vec!["dom.py", "node.py"],
+        graph
+            .iter_files()
+            .map(|f| graph[f].name())
+            .collect::<Vec<_>>()
+    );
+    let cached = builtins_variants
+        .load(&sgl, &builtins_selection("dom,node"), &NoCancellation)
+        .expect("Expected builtins to load");
+    assert!(Arc::ptr_eq(&graph, &cached));
+
+    assert!(matches!(
+        builtins_variants.load(&sgl, &builtins_selection("deno"), &NoCancellation),
+        Err(LoadError::UnknownBuiltinsVariant(name)) if name == "deno"
+    ));
+}
+
+fn python_configuration(scope: &str, detection: FileDetection) -> LanguageConfiguration {
+    let language = tree_sitter_python::language();
+    let mut lc = LanguageConfiguration::from_sources(
+        language,
+        Some(scope.into()),
+        None,
+        vec!["py".into()],
+        &[("test.tsg".into(), TSG.as_str())],
+        None,
+        None,
+        vec![],
+        FileAnalyzers::new(),
+        &NoCancellation,
+    )
+    .expect("Expected loading language configuration to succeed");
+    lc.detection = detection;
+    lc
+}
+
+fn selected_scope(loader: &mut Loader, path: &str, content: &str) -> Option<String> {
+    loader
+        .load_for_file(Path::new(path), &mut Some(content), &NoCancellation)
+        .expect("Expected loading stack graph language to succeed")
+        .primary
+        .and_then(|lc| lc.scope.clone())
+}
+
+#[test]
+fn can_select_language_by_shebang_modeline_and_priority() {
+    let mut loader = Loader::from_language_configurations(
+        vec![
+            python_configuration(
+                "source.py",
+                FileDetection {
+                    interpreters: vec!["python3".into()],
+                    modelines: vec!["python".into()],
+                    ..FileDetection::default()
+                },
+            ),
+            python_configuration(
+                "source.starlark",
+                FileDetection {
+                    first_line_regex: Some(regex::Regex::new("^# starlark").unwrap()),
+                    modelines: vec!["bazel".into()],
+                    priority: 1,
+                    ..FileDetection::default()
+                },
+            ),
+        ],
+        None,
+    )
+    .expect("Expected loader to succeed");
+
+    assert_eq!(
+        Some("source.starlark".into()),
+        selected_scope(&mut loader, "test.py", "pass")
+    );
+    assert_eq!(
+        Some("source.py".into()),
+        selected_scope(&mut loader, "script", "# -*- mode: Python -*-\npass")
+    );
+    assert_eq!(
+        Some("source.py".into()),
+        selected_scope(&mut loader, "script", "#!/usr/bin/env -S python3 -u\npass")
+    );
+    assert_eq!(
+        Some("source.starlark".into()),
+        selected_scope(&mut loader, "BUILD", "# starlark\npass")
+    );
+    assert_eq!(
+        Some("source.starlark".into()),
+        selected_scope(&mut loader, "BUILD", "pass\n# vim: set ts=4 ft=bazel:")
+    );
+    assert_eq!(
+        Some("source.starlark".into()),
+        selected_scope(&mut loader, "test.py", "#!/usr/bin/env python3\npass")
+    );
+    assert_eq!(None, selected_scope(&mut loader, "script", "#!/bin/sh"));
+    assert_eq!(
+        Some("source.py".into()),
+        selected_scope(&mut loader, "test.py", "# -*- mode: python -*-\npass")
+    );
+    assert_eq!(
+        Some("source.starlark".into()),
+        selected_scope(&mut loader, "test.py", "# vim: set ft=bazel:\npass")
+    );
+}
+
+#[test]
+fn can_select_path_loaded_language_with_configured_detection() {
+    let grammar_path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/it/grammars/tree-sitter-plain");
+    let mut loader = Loader::from_paths(
+        vec![grammar_path],
+        None,
+        DEFAULT_TSG_PATHS.clone(),
+        DEFAULT_BUILTINS_PATHS.clone(),
+    )
+    .expect("Expected loader to succeed");
+
+    assert_eq!(
+        Some("source.plain".into()),
+        selected_scope(&mut loader, "test.plain", "text")
+    );
+    assert_eq!(
+        Some("source.plain".into()),
+        selected_scope(&mut loader, "script", "#!/usr/bin/env plain2\ntext")
+    );
+    assert_eq!(
+        Some("source.plain".into()),
+        selected_scope(&mut loader, "script", "text\n# vim: set ft=plain:")
+    );
+    assert_eq!(None, selected_scope(&mut loader, "script", "#!/bin/sh"));
+    assert_eq!(
+        None,
+        selected_scope(&mut loader, "test.plain", "# -*- mode: markdown -*-\ntext")
+    );
+}
+
+#[test]
+fn can_skip_files_that_are_not_text() {
+    let mut loader = Loader::from_language_configurations(
+        vec![python_configuration(
+            "source.py",
+            FileDetection {
+                interpreters: vec!["python3".into()],
+                modelines: vec!["python".into()],
+                ..FileDetection::default()
+            },
+        )],
+        None,
+    )
+    .expect("Expected loader to succeed");
+    let dir = tempfile::tempdir().expect("Expected temporary directory");
+    let mut binary = b"#!/usr/bin/env python3\n".to_vec();
+    binary.extend([0xff, 0xfe, 0x00, 0x80]);
+    for name in ["image", "image.png", "image.py"] {
+        std::fs::write(dir.path().join(name), &binary).expect("Expected writing file to succeed");
+    }
+
+    let mut file_reader = FileReader::new();
+    let scope = |loader: &mut Loader, file_reader: &mut FileReader, name: &str| {
+        loader
+            .load_for_file(&dir.path().join(name), file_reader, &NoCancellation)
+            .expect("Expected loading stack graph language to succeed")
+            .primary
+            .and_then(|lc| lc.scope.clone())
+    };
+    assert_eq!(None, scope(&mut loader, &mut file_reader, "image"));
+    assert_eq!(None, scope(&mut loader, &mut file_reader, "image.png"));
+    assert_eq!(
+        Some("source.py".into()),
+        scope(&mut loader, &mut file_reader, "image.py")
+    );
+    let explanations = loader
+        .explain_file(&dir.path().join("image"), &mut file_reader)
+        .expect("Expected explaining language selection to succeed");
+    assert!(!explanations[0].file_match.is_match());
+}
+
+#[test]
+fn can_explain_language_selection() {
+    let mut loader = Loader::from_language_configurations(
+        vec![
+            python_configuration(
+                "source.py",
+                FileDetection {
+                    interpreters: vec!["python3".into()],
+                    ..FileDetection::default()
+                },
+            ),
+            python_configuration(
+                "source.starlark",
+                FileDetection {
+                    modelines: vec!["bazel".into()],
+                    ..FileDetection::default()
+                },
+            ),
+        ],
+        None,
+    )
+    .expect("Expected loader to succeed");
+
+    let explanations = loader
+        .explain_file(
+            Path::new("script"),
+            &mut Some("#!/usr/bin/python3\n# vim:ft=python"),
+        )
+        .expect("Expected explaining language selection to succeed");
+    assert_eq!(
+        vec![
+            (
+                Some("source.py".to_string()),
+                true,
+                FileMatch::Match {
+                    evidence: MatchEvidence::Shebang("python3".into()),
+                    priority: 0,
+                    score: 0,
+                }
+            ),
+            (
+                Some("source.starlark".to_string()),
+                false,
+                FileMatch::NoMatch("modeline selects language ‘python’".into())
+            ),
+        ],
+        explanations
+            .into_iter()
+            .map(|e| (e.scope, e.selected, e.file_match))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn can_report_parse_errors_across_composed_tsg_sources() {
+    let language = tree_sitter_python::language();
+    let common_tsg = "global FILE_PATH\n";
+    let tsg = "global FILE_PATH\n";
+    let err = StackGraphLanguage::from_sources(
+        language,
+        &[
+            ("common.tsg".into(), common_tsg),
+            ("python.tsg".into(), tsg),
+        ],
+    )
+    .err()
+    .expect("Expected composed rules to fail");
+    match &err {
+        LanguageError::ParseErrorInFile {
+            tsg_path,
+            tsg: combined_tsg,
+            ..
+        } => {
+            assert_eq!(Path::new("<common.tsg + python.tsg>"), tsg_path);
+            assert_eq!(format!("{}{}", common_tsg, tsg), *combined_tsg);
+        }
+        err => panic!("Unexpected error {:?}", err),
+    }
+    assert_eq!(
+        concat!(
+            "Duplicate global variable FILE_PATH at (2, 8)\n",
+            "<common.tsg + python.tsg>:2:8:\n",
+            "2 | global FILE_PATH\n",
+            "  |        ^\n",
+        ),
+        err.display_pretty(Path::new("common.tsg"), common_tsg)
+            .to_string()
+    );
+}
@@ -14,10 +14,22 @@ use tree_sitter_stack_graphs::NoCancellation;
 use tree_sitter_stack_graphs::StackGraphLanguage;
 
 mod builder;
+#[cfg(feature = "bincode")]
+mod builtins;
+mod check;
 mod edges;
+mod functions;
+#[cfg(feature = "cli")]
+mod fuzz;
+#[cfg(feature = "cli")]
+mod indexer;
 mod loader;
 mod nodes;
+#[cfg(feature = "cli")]
+mod report;
 mod test;
+#[cfg(feature = "cli")]
+mod tester;
 
 pub(self) fn build_stack_graph(
     python_source: &str,
@@ -9,7 +9,11 @@ use pretty_assertions::assert_eq;
 use stack_graphs::arena::Handle;
 use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
+use tree_sitter_graph::Variables;
+use tree_sitter_stack_graphs::AttributeDiagnostic;
 use tree_sitter_stack_graphs::BuildError;
+use tree_sitter_stack_graphs::NoCancellation;
+use tree_sitter_stack_graphs::StackGraphLanguage;
 
 use super::build_stack_graph;
 
@@ -284,3 +288,133 @@ fn can_calculate_spans() {
     let trimmed_line = &python[source_info.span.start.trimmed_line.clone()];
     assert_eq!(trimmed_line, "a");
 }
+
+#[test]
+fn can_set_source_info_attributes() {
+    let tsg = r#"
+      (function_definition name: (identifier) @id body: (_) @body) {
+         node result
+         attr (result) type = "pop_symbol", symbol = (source-text @id), source_node = @id, is_definition
+         attr (result) syntax_type = "function", definiens_node = @body
+         attr (result) fully_qualified_name = (format "mod.{}" (source-text @id))
+      }
+    "#;
+    let python = "def f():\n  pass\n";
+    let (graph, file) = build_stack_graph(python, tsg).unwrap();
+    let node_handle = graph.nodes_for_file(file).next().unwrap();
+    let source_info = graph.source_info(node_handle).unwrap();
+
+    let syntax_type = source_info.syntax_type.into_option().unwrap();
+    assert_eq!("function", &graph[syntax_type]);
+
+    let definiens = format!(
+        "{}:{}-{}:{}",
+        source_info.definiens_span.start.line,
+        source_info.definiens_span.start.column.utf8_offset,
+        source_info.definiens_span.end.line,
+        source_info.definiens_span.end.column.utf8_offset,
+    );
+    assert_eq!("1:2-1:6", definiens);
+
+    let fully_qualified_name = source_info.fully_qualified_name.into_option().unwrap();
+    assert_eq!("mod.f", &graph[fully_qualified_name]);
+}
+
+#[test]
+fn cannot_set_source_info_attributes_with_invalid_values() {
+    let tsg = r#"
+      (identifier) @id {
+         node result
+         attr (result) type = "pop_symbol", symbol = "test", source_node = @id, is_definition
+         attr (result) definiens_node = "test"
+      }
+    "#;
+    let python = "a";
+    let result = build_stack_graph(python, tsg);
+    assert!(matches!(result, Err(BuildError::ExecutionError(_))));
+
+    let tsg = r#"
+      (identifier) @id {
+         node result
+         attr (result) type = "pop_symbol", symbol = "test", source_node = @id, is_definition
+         attr (result) syntax_type = 1
+      }
+    "#;
+    let result = build_stack_graph(python, tsg);
+    assert!(matches!(result, Err(BuildError::ExecutionError(_))));
+}
+
+#[test]
+fn can_report_unexpected_attributes() {
+    let tsg = r#"
+      (identifier) @id {
+         node result
+         attr (result) type = "push_symbol", symbol = "test", source_node = @id, is_definition
+      }
+    "#;
+    let python = "a";
+    let language = StackGraphLanguage::from_str(tree_sitter_python::language(), tsg).unwrap();
+    let globals = Variables::new();
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let mut diagnostics = Vec::new();
+    let mut builder = language.builder_into_stack_graph(&mut graph, file, python);
+    builder.set_attribute_diagnostics(&mut diagnostics);
+    builder
+        .build(&globals, &NoCancellation)
+        .expect("Failed to build graph");
+    assert_eq!(
+        vec![AttributeDiagnostic {
+            attribute: "is_definition".to_string(),
+            node_type: "push_symbol".to_string(),
+            tsg_line: Some(3),
+            tsg_path: None,
+        }],
+        diagnostics
+    );
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let mut builder = language.builder_into_stack_graph(&mut graph, file, python);
+    builder.set_strict_attributes(true);
+    let result = builder.build(&globals, &NoCancellation);
+    assert!(matches!(
+        result,
+        Err(BuildError::UnexpectedAttribute(diagnostic)) if diagnostic.attribute == "is_definition"
+    ));
+}
+
+#[test]
+fn can_report_unexpected_attributes_set_by_other_stanzas() {
+    let tsg = r#"
+      (identifier) @id {
+         node @id.node
+         attr (@id.node) type = "push_symbol", symbol = "test", source_node = @id
+      }
+      (expression_statement (identifier) @id) {
+         attr (@id.node) is_definition
+      }
+    "#;
+    let python = "a";
+    let language = StackGraphLanguage::from_str(tree_sitter_python::language(), tsg).unwrap();
+    let globals = Variables::new();
+
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let mut diagnostics = Vec::new();
+    let mut builder = language.builder_into_stack_graph(&mut graph, file, python);
+    builder.set_attribute_diagnostics(&mut diagnostics);
+    builder
+        .build(&globals, &NoCancellation)
+        .expect("Failed to build graph");
+    assert_eq!(
+        vec![AttributeDiagnostic {
+            attribute: "is_definition".to_string(),
+            node_type: "push_symbol".to_string(),
+            tsg_line: Some(1),
+            tsg_path: None,
+        }],
+        diagnostics
+    );
+}
@@ -0,0 +1,113 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use std::path::Path;
+use tree_sitter_stack_graphs::cli::test::report::TestReport;
+use tree_sitter_stack_graphs::test::Test;
+use tree_sitter_stack_graphs::NoCancellation;
+
+use crate::test::build_test;
+use crate::test::TSG;
+
+/// Builds a report with a suite containing every kind of test case outcome, and a suite for a
+/// test file that could not be run.
+fn build_report() -> TestReport {
+    let python = r#"
+      x = 1;
+        x;
+      # ^ defined: 2
+      # ^ defined: 3
+      # ^ defined: 3 (xfail)
+      # ^ defined: 2 (xfail)
+    "#;
+    let path = Path::new("<a & 'b'>.py");
+    let mut test = Test::from_source(path, python, path).expect("Could not parse test");
+    let (mut partials, mut db) = build_test(&mut test, &TSG);
+    let result = test
+        .run(&mut partials, &mut db, &NoCancellation)
+        .expect("should never be cancelled");
+    let mut report = TestReport::new();
+    report.add_result(path, &result);
+    report.add_error(
+        Path::new("broken.py"),
+        &anyhow::anyhow!("cannot parse \"broken.py\"\nsecond line"),
+    );
+    report
+}
+
+#[test]
+fn can_write_junit_report() {
+    let mut output = Vec::new();
+    build_report().write_junit(&mut output).unwrap();
+    let expected = r#"
+<?xml version="1.0" encoding="UTF-8"?>
+<testsuites tests="5" failures="2" errors="1" skipped="1">
+  <testsuite name="&lt;a &amp; &apos;b&apos;&gt;.py" tests="4" failures="2" errors="0" skipped="1">
+    <testcase name="3:9: defined: 2" classname="&lt;a &amp; &apos;b&apos;&gt;.py"/>
+    <testcase name="3:9: defined: 3" classname="&lt;a &amp; &apos;b&apos;&gt;.py">
+      <failure message="&lt;a &amp; &apos;b&apos;&gt;.py:3:9: definition(s) for reference(s) ‘x’ missing expected on line(s) 3 found unexpected ‘x’ on lines(s) 2">&lt;a &amp; &apos;b&apos;&gt;.py:3:9: definition(s) for reference(s) ‘x’ missing expected on line(s) 3 found unexpected ‘x’ on lines(s) 2
+in fragment &lt;a &amp; &apos;b&apos;&gt;.py on line 3: x;</failure>
+    </testcase>
+    <testcase name="3:9: defined: 3" classname="&lt;a &amp; &apos;b&apos;&gt;.py">
+      <skipped message="expected failure: &lt;a &amp; &apos;b&apos;&gt;.py:3:9: definition(s) for reference(s) ‘x’ missing expected on line(s) 3 found unexpected ‘x’ on lines(s) 2">&lt;a &amp; &apos;b&apos;&gt;.py:3:9: definition(s) for reference(s) ‘x’ missing expected on line(s) 3 found unexpected ‘x’ on lines(s) 2
+in fragment &lt;a &amp; &apos;b&apos;&gt;.py on line 3: x;</skipped>
+    </testcase>
+    <testcase name="3:9: defined: 2" classname="&lt;a &amp; &apos;b&apos;&gt;.py">
+      <failure message="&lt;a &amp; &apos;b&apos;&gt;.py:3:9: assertion passed, but was expected to fail">&lt;a &amp; &apos;b&apos;&gt;.py:3:9: assertion passed, but was expected to fail
+in fragment &lt;a &amp; &apos;b&apos;&gt;.py on line 3: x;</failure>
+    </testcase>
+  </testsuite>
+  <testsuite name="broken.py" tests="1" failures="0" errors="1" skipped="0">
+    <testcase name="test file" classname="broken.py">
+      <error message="cannot parse &quot;broken.py&quot;">cannot parse &quot;broken.py&quot;
+second line</error>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#;
+    assert_eq!(expected.trim_start(), String::from_utf8(output).unwrap());
+}
+
+#[test]
+fn can_write_tap_report() {
+    let mut output = Vec::new();
+    build_report().write_tap(&mut output).unwrap();
+    let expected = r#"
+TAP version 14
+# Subtest: <a & 'b'>.py
+    1..4
+    ok 1 - 3:9: defined: 2
+    not ok 2 - 3:9: defined: 3
+      ---
+      message: |
+        <a & 'b'>.py:3:9: definition(s) for reference(s) ‘x’ missing expected on line(s) 3 found unexpected ‘x’ on lines(s) 2
+        in fragment <a & 'b'>.py on line 3: x;
+      ...
+    not ok 3 - 3:9: defined: 3 # TODO expected failure
+      ---
+      message: |
+        <a & 'b'>.py:3:9: definition(s) for reference(s) ‘x’ missing expected on line(s) 3 found unexpected ‘x’ on lines(s) 2
+        in fragment <a & 'b'>.py on line 3: x;
+      ...
+    not ok 4 - 3:9: defined: 2
+      ---
+      message: |
+        <a & 'b'>.py:3:9: assertion passed, but was expected to fail
+        in fragment <a & 'b'>.py on line 3: x;
+      ...
+not ok 1 - <a & 'b'>.py
+not ok 2 - broken.py
+  ---
+  message: |
+    cannot parse "broken.py"
+    second line
+  ...
+1..2
+"#;
+    assert_eq!(expected.trim_start(), String::from_utf8(output).unwrap());
+}
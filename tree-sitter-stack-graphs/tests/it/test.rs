@@ -17,12 +17,14 @@ use std::path::Path;
 use std::path::PathBuf;
 use tree_sitter_graph::Variables;
 use tree_sitter_stack_graphs::test::Test;
+use tree_sitter_stack_graphs::test::TestCaseOutcome;
+use tree_sitter_stack_graphs::test::TestFailure;
 use tree_sitter_stack_graphs::BuildError;
 use tree_sitter_stack_graphs::NoCancellation;
 use tree_sitter_stack_graphs::StackGraphLanguage;
 
 static PATH: Lazy<PathBuf> = Lazy::new(|| PathBuf::from("test.py"));
-static TSG: Lazy<String> = Lazy::new(|| {
+pub(crate) static TSG: Lazy<String> = Lazy::new(|| {
     r#"
       global ROOT_NODE
       (module) @mod {
@@ -76,23 +78,8 @@ fn build_stack_graph_into(
     Ok(())
 }
 
-fn check_test(
-    python_path: &Path,
-    python_source: &str,
-    tsg_source: &str,
-    expected_successes: usize,
-    expected_failures: usize,
-) {
-    let mut test =
-        Test::from_source(python_path, python_source, python_path).expect("Could not parse test");
-    let assertion_count: usize = test.fragments.iter().map(|f| f.assertions.len()).sum();
-    assert_eq!(
-        expected_successes + expected_failures,
-        assertion_count,
-        "expected {} assertions, got {}",
-        expected_successes + expected_failures,
-        assertion_count,
-    );
+/// Builds the stack graphs of the test fragments and computes their minimal partial paths.
+pub(crate) fn build_test(test: &mut Test, tsg_source: &str) -> (PartialPaths, Database) {
     let mut globals = Variables::new();
     for fragments in &test.fragments {
         globals.clear();
@@ -120,7 +107,56 @@ fn check_test(
         )
         .expect("should nopt be cancelled");
     }
+    (partials, db)
+}
+
+/// Adds builtins to the test graph, and their minimal partial paths to the database.
+fn add_builtins(
+    test: &mut Test,
+    partials: &mut PartialPaths,
+    db: &mut Database,
+    python_source: &str,
+    tsg_source: &str,
+) {
+    let file = test.graph.add_file("<builtins>").unwrap();
+    build_stack_graph_into(
+        &mut test.graph,
+        file,
+        python_source,
+        tsg_source,
+        &Variables::new(),
+    )
+    .expect("Could not load builtins");
+    ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+        &test.graph,
+        partials,
+        file,
+        &stack_graphs::NoCancellation,
+        |graph, partials, path| {
+            db.add_partial_path(graph, partials, path.clone());
+        },
+    )
+    .expect("should not be cancelled");
+}
 
+fn check_test(
+    python_path: &Path,
+    python_source: &str,
+    tsg_source: &str,
+    expected_successes: usize,
+    expected_failures: usize,
+) {
+    let mut test =
+        Test::from_source(python_path, python_source, python_path).expect("Could not parse test");
+    let assertion_count: usize = test.fragments.iter().map(|f| f.assertions.len()).sum();
+    assert_eq!(
+        expected_successes + expected_failures,
+        assertion_count,
+        "expected {} assertions, got {}",
+        expected_successes + expected_failures,
+        assertion_count,
+    );
+    let (mut partials, mut db) = build_test(&mut test, tsg_source);
     let results = test
         .run(&mut partials, &mut db, &NoCancellation)
         .expect("should never be cancelled");
@@ -212,6 +248,229 @@ fn can_assert_refers_no_symbols() {
     check_test(&PATH, python, &TSG, 1, 0);
 }
 
+#[test]
+fn can_assert_defined_in_fragment() {
+    let python = r#"
+      # --- path: a.py ---
+      x = 1;
+
+      # --- path: b.py ---
+      y = 1;
+
+      # --- path: c.py ---
+        x;
+      # ^ defined-in: a.py:3
+        y;
+      # ^ defined-in: a.py:3
+    "#;
+    check_test(&PATH, python, &TSG, 1, 1);
+}
+
+#[test]
+fn cannot_assert_defined_in_unknown_fragment() {
+    let python = r#"
+      # --- path: a.py ---
+      x = 1;
+        x;
+      # ^ defined-in: c.py:3
+    "#;
+    if let Ok(_) = Test::from_source(&PATH, python, &PATH) {
+        panic!("Parsing test unexpectedly succeeded.");
+    }
+}
+
+#[test]
+fn can_assert_defined_count() {
+    let python = r#"
+      # --- path: a.py ---
+      x = 1;
+
+      # --- path: b.py ---
+      x = 1;
+
+      # --- path: c.py ---
+        x;
+      # ^ defined-count: 2
+        x;
+      # ^ defined-count: 1
+    "#;
+    check_test(&PATH, python, &TSG, 1, 1);
+}
+
+#[test]
+fn can_assert_not_defined() {
+    let python = r#"
+      x = 1;
+      y = 1;
+        x;
+      # ^ not-defined: 3
+        x;
+      # ^ not-defined: 2
+        z;
+      # ^ not-defined:
+    "#;
+    check_test(&PATH, python, &TSG, 2, 1);
+}
+
+#[test]
+fn not_defined_fails_for_definitions_in_builtins() {
+    let python = r#"
+        x;
+      # ^ not-defined:
+        x;
+      # ^ not-defined: 2
+        y;
+      # ^ not-defined:
+    "#;
+    let mut test = Test::from_source(&PATH, python, &PATH).expect("Could not parse test");
+    let (mut partials, mut db) = build_test(&mut test, &TSG);
+    add_builtins(&mut test, &mut partials, &mut db, "x = 1;\n", &TSG);
+    let result = test
+        .run(&mut partials, &mut db, &NoCancellation)
+        .expect("should never be cancelled");
+    assert_eq!(2, result.success_count());
+    assert_eq!(1, result.failure_count());
+    match result.failures_iter().next().unwrap() {
+        TestFailure::IncorrectResolutions {
+            position,
+            unexpected_lines,
+            ..
+        } => {
+            assert_eq!(1, position.line);
+            assert_eq!(Some(&vec![None]), unexpected_lines.get("x"));
+        }
+        failure => panic!("unexpected failure {}", failure),
+    }
+}
+
+#[test]
+fn defined_count_ignores_definitions_in_builtins() {
+    let python = r#"
+      # --- path: a.py ---
+      x = 1;
+
+      # --- path: b.py ---
+        x;
+      # ^ defined-count: 1
+      # ^ defined: 3
+    "#;
+    let mut test = Test::from_source(&PATH, python, &PATH).expect("Could not parse test");
+    let (mut partials, mut db) = build_test(&mut test, &TSG);
+    add_builtins(&mut test, &mut partials, &mut db, "x = 1;\n", &TSG);
+    let result = test
+        .run(&mut partials, &mut db, &NoCancellation)
+        .expect("should never be cancelled");
+    assert_eq!(2, result.success_count());
+    assert_eq!(0, result.failure_count());
+}
+
+#[test]
+fn can_assert_references() {
+    let python = r#"
+      x = 1;
+    # ^ references: 4, 5
+      x;
+      x;
+      y = 1;
+    # ^ references:
+      y = 1;
+    # ^ references: 4
+    "#;
+    check_test(&PATH, python, &TSG, 2, 1);
+}
+
+#[test]
+fn can_snapshot_graph_and_paths() {
+    let python = r#"
+      x = 1;
+      x;
+    "#;
+    let mut test = Test::from_source(&PATH, python, &PATH).expect("Could not parse test");
+    let (mut partials, db) = build_test(&mut test, &TSG);
+    let snapshot = test.snapshot(&mut partials, &db);
+    assert!(snapshot.starts_with("--- path: test.py ---\n"));
+    assert!(snapshot.contains("node [test.py(4) definition x] at 2:7\n"));
+    assert!(snapshot.contains("node [test.py(7) reference x] at 3:7\n"));
+    assert!(snapshot
+        .contains("\n--- paths ---\n<%1> ($1) [test.py(7) reference x] -> [root] <x,%1> ($1)\n"));
+    assert_eq!(snapshot, test.snapshot(&mut partials, &db));
+}
+
+#[test]
+fn can_mark_assertions_as_expected_failures() {
+    let python = r#"
+      x = 1;
+      x;
+    # ^ defined: 2 (xfail)
+    # ^ defined: 3 (xfail)
+    # ^ defined: 3
+    # ^ defined: (xfail)
+    "#;
+    let mut test = Test::from_source(&PATH, python, &PATH).expect("Could not parse test");
+    let (mut partials, mut db) = build_test(&mut test, &TSG);
+    let result = test
+        .run(&mut partials, &mut db, &NoCancellation)
+        .expect("should never be cancelled");
+    assert_eq!(0, result.success_count());
+    assert_eq!(1, result.failure_count());
+    assert_eq!(2, result.expected_failure_count());
+    assert_eq!(1, result.unexpected_success_count());
+    let unexpected_success = result.unexpected_successes_iter().next().unwrap();
+    assert_eq!(2, unexpected_success.position.line);
+}
+
+#[test]
+fn can_mark_test_as_expected_failure() {
+    let python = r#"
+      # --- xfail ---
+      x = 1;
+      x;
+    # ^ defined: 3
+    # ^ defined: 4
+    "#;
+    let mut test = Test::from_source(&PATH, python, &PATH).expect("Could not parse test");
+    assert!(test.xfail);
+    let (mut partials, mut db) = build_test(&mut test, &TSG);
+    let result = test
+        .run(&mut partials, &mut db, &NoCancellation)
+        .expect("should never be cancelled");
+    assert_eq!(0, result.failure_count());
+    assert_eq!(1, result.expected_failure_count());
+    assert_eq!(1, result.unexpected_success_count());
+}
+
+#[test]
+fn can_list_test_cases() {
+    let python = r#"
+      x = 1;
+      x;
+    # ^ defined: 2
+    # ^ defined: 3
+    # ^ defined: 3 (xfail)
+    "#;
+    let mut test = Test::from_source(&PATH, python, &PATH).expect("Could not parse test");
+    let (mut partials, mut db) = build_test(&mut test, &TSG);
+    let result = test
+        .run(&mut partials, &mut db, &NoCancellation)
+        .expect("should never be cancelled");
+    let cases = result.cases_iter().collect::<Vec<_>>();
+    assert_eq!(3, cases.len());
+    assert_eq!(
+        vec!["3:7: defined: 2", "3:7: defined: 3", "3:7: defined: 3"],
+        cases.iter().map(|c| c.name.as_str()).collect::<Vec<_>>()
+    );
+    for case in &cases {
+        assert_eq!(Some(PATH.as_path()), case.fragment_path.as_deref());
+        assert_eq!(Some("x;"), case.source_line.as_deref());
+    }
+    assert!(matches!(cases[0].outcome, TestCaseOutcome::Success));
+    assert!(matches!(cases[1].outcome, TestCaseOutcome::Failure(_)));
+    assert!(matches!(
+        cases[2].outcome,
+        TestCaseOutcome::ExpectedFailure(_)
+    ));
+}
+
 #[test]
 fn test_cannot_use_unknown_assertion() {
     let python = r#"
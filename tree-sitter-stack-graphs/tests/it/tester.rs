@@ -0,0 +1,154 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::path::Path;
+use tree_sitter_stack_graphs::cli::test::report::ReportFormat;
+use tree_sitter_stack_graphs::cli::test::TestArgs;
+use tree_sitter_stack_graphs::loader::FileAnalyzers;
+use tree_sitter_stack_graphs::loader::LanguageConfiguration;
+use tree_sitter_stack_graphs::loader::Loader;
+use tree_sitter_stack_graphs::NoCancellation;
+
+/// Rules that create references for expression statements.
+static REFERENCES_TSG: &str = r#"
+  global ROOT_NODE
+
+  (module (expression_statement (identifier) @id)) {
+    node @id.ref
+    attr (@id.ref) type = "push_symbol", symbol = (source-text @id), source_node = @id, is_reference
+    edge @id.ref -> ROOT_NODE
+  }
+"#;
+
+/// Rules that create definitions for expression statements.
+static DEFINITIONS_TSG: &str = r#"
+  global ROOT_NODE
+
+  (module (expression_statement (identifier) @id)) {
+    node @id.def
+    attr (@id.def) type = "pop_symbol", symbol = (source-text @id), source_node = @id, is_definition
+    edge ROOT_NODE -> @id.def
+  }
+"#;
+
+/// A test file with a fragment for each language. The reference in the first fragment only
+/// resolves if the second fragment is built with the rules of the second language.
+static TEST_SOURCE: &str = r#"# --- path: a.py ---
+  x
+# ^ defined: 5
+# --- path: b.pyi ---
+x
+"#;
+
+fn language_configuration(
+    file_type: &str,
+    tsg_source: &str,
+    builtins_source: &str,
+) -> LanguageConfiguration {
+    LanguageConfiguration::from_sources(
+        tree_sitter_python::language(),
+        Some(format!("source.{}", file_type)),
+        None,
+        vec![file_type.into()],
+        &[(format!("{}.tsg", file_type).into(), tsg_source)],
+        Some((format!("builtins.{}", file_type).into(), builtins_source)),
+        None,
+        vec![],
+        FileAnalyzers::new(),
+        &NoCancellation,
+    )
+    .expect("Expected loading language configuration to succeed")
+}
+
+fn loader() -> Loader {
+    Loader::from_language_configurations(
+        vec![
+            language_configuration("py", REFERENCES_TSG, "print"),
+            language_configuration("pyi", DEFINITIONS_TSG, "len"),
+        ],
+        None,
+    )
+    .expect("Expected loader")
+}
+
+fn run_tests(test_dir: &Path, jobs: usize) -> anyhow::Result<()> {
+    let mut args = TestArgs::new(vec![test_dir.to_path_buf()]);
+    args.quiet = true;
+    args.jobs = jobs;
+    args.run(loader())
+}
+
+fn write_tests(test_dir: &Path) {
+    for name in ["first.py", "second.py"] {
+        std::fs::write(test_dir.join(name), TEST_SOURCE).expect("Expected writing test to succeed");
+    }
+}
+
+#[test]
+fn can_run_test_with_fragments_in_multiple_languages() {
+    let test_dir = tempfile::tempdir().expect("Expected temporary directory");
+    write_tests(test_dir.path());
+    run_tests(test_dir.path(), 1).expect("Expected tests to succeed");
+}
+
+#[test]
+fn can_run_tests_with_fragments_in_multiple_languages_in_parallel() {
+    let test_dir = tempfile::tempdir().expect("Expected temporary directory");
+    write_tests(test_dir.path());
+    run_tests(test_dir.path(), 2).expect("Expected tests to succeed");
+}
+
+#[test]
+fn reports_failures_in_fragments_in_multiple_languages() {
+    let test_dir = tempfile::tempdir().expect("Expected temporary directory");
+    std::fs::write(
+        test_dir.path().join("test.py"),
+        TEST_SOURCE.replace("defined: 5", "defined: 2"),
+    )
+    .expect("Expected writing test to succeed");
+    assert!(run_tests(test_dir.path(), 1).is_err());
+}
+
+#[test]
+fn reports_snapshot_differences_with_context() {
+    let test_dir = tempfile::tempdir().expect("Expected temporary directory");
+    let test_path = test_dir.path().join("test.py");
+    std::fs::write(&test_path, TEST_SOURCE).expect("Expected writing test to succeed");
+    let mut args = TestArgs::new(vec![test_dir.path().to_path_buf()]);
+    args.quiet = true;
+    args.update_snapshots = true;
+    args.run(loader())
+        .expect("Expected updating snapshots to succeed");
+
+    let snapshot_path = test_dir.path().join("test.py.snap");
+    let snapshot = std::fs::read_to_string(&snapshot_path).expect("Expected snapshot");
+    let mut lines = snapshot.lines().collect::<Vec<_>>();
+    lines[5] = "edited line";
+    std::fs::write(&snapshot_path, lines.join("\n") + "\n")
+        .expect("Expected writing snapshot to succeed");
+
+    let report_path = test_dir.path().join("report.tap");
+    let mut args = TestArgs::new(vec![test_dir.path().to_path_buf()]);
+    args.quiet = true;
+    args.snapshots = true;
+    args.report = vec![ReportFormat::Tap(Some(report_path.clone()))];
+    assert!(args.run(loader()).is_err());
+
+    let report = std::fs::read_to_string(&report_path).expect("Expected report");
+    let expected_diff = r#"
+        @@ -3,7 +3,7 @@
+          edge [a.py(0) reference x] -> [root]
+          --- path: b.pyi ---
+          node [b.pyi(0) definition x] at 5:1
+        - edited line
+        + edge [root] -> [b.pyi(0) definition x]
+          --- paths ---
+          <%1> ($1) [a.py(0) reference x] -> [root] <x,%1> ($1)
+          <%1> ($1) [builtins.py(0) reference print] -> [root] <print,%1> ($1)
+"#;
+    assert!(report.contains(expected_diff.trim_start_matches('\n')));
+}